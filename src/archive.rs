@@ -0,0 +1,70 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use zip::ZipArchive;
+
+use crate::tarball;
+
+/// A project found in a source archive.
+pub struct Project {
+    /// Folder of the project in the archive, empty at the top
+    pub dir: String,
+    /// `tarball::FILES` of the project, as (file name, content)
+    pub files: Vec<(String, String)>,
+    /// Versions of the packages under its node_modules, when it was archived
+    /// installed
+    pub installed: HashMap<String, HashSet<String>>,
+}
+
+/// Read the projects of a `.zip` in memory, in folder order. A folder is a
+/// project when it holds one of `tarball::FILES`; package.json files inside
+/// node_modules are what the project around it installs.
+pub fn read(path: &Path) -> io::Result<Vec<Project>> {
+    let mut archive = ZipArchive::new(File::open(path)?).map_err(io::Error::other)?;
+    let mut projects: BTreeMap<String, Project> = BTreeMap::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(io::Error::other)?;
+        // Entries escaping the archive, such as `../x`, have no enclosed name
+        let Some(name) = entry.enclosed_name().filter(|_| entry.is_file()) else {
+            continue;
+        };
+        let name = name.to_string_lossy().replace('\\', "/");
+        let (dir, file) = name.rsplit_once('/').unwrap_or(("", &name));
+        let installed_in = match name.split_once("node_modules/") {
+            Some((project, _)) if file == "package.json" && (project.is_empty() || project.ends_with('/')) => {
+                Some(project.trim_end_matches('/').to_string())
+            }
+            Some(_) => continue,
+            None => None,
+        };
+        if installed_in.is_none() && !tarball::FILES.contains(&file) {
+            continue;
+        }
+        let Some(content) = tarball::read_limited(&mut entry)? else {
+            eprintln!("[warning] Skipping {} in {}, it is larger than {} bytes", name, path.display(), tarball::MAX_FILE_SIZE);
+            continue;
+        };
+        let key = installed_in.clone().unwrap_or_else(|| dir.to_string());
+        let project = projects.entry(key.clone()).or_insert_with(|| Project {
+            dir: key,
+            files: Vec::new(),
+            installed: HashMap::new(),
+        });
+        match installed_in {
+            Some(installed_in) => {
+                let relative = name[installed_in.len()..].trim_start_matches('/');
+                if let Some((name, version)) = tarball::bundled_package(relative, &content) {
+                    project.installed.entry(name).or_default().insert(version);
+                }
+            }
+            None => project.files.push((file.to_string(), content)),
+        }
+    }
+    let mut projects: Vec<Project> = projects.into_values().filter(|p| !p.files.is_empty()).collect();
+    for project in &mut projects {
+        project.files.sort_by_key(|(file, _)| tarball::FILES.iter().position(|f| f == file));
+    }
+    Ok(projects)
+}
@@ -0,0 +1,109 @@
+use std::collections::{BTreeSet, HashSet};
+use std::env;
+use std::io;
+
+use clap::ValueEnum;
+use rayon::prelude::*;
+use serde_json::Value;
+
+use crate::{ghsa, osv};
+use crate::packages::{ListEntry, PackageList};
+
+/// Vulnerability databases `--audit` checks the scanned inventory against.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, ValueEnum)]
+pub enum Audit {
+    /// OSV.dev, queried in batches
+    Osv,
+    /// GitHub Advisory Database, queried through the GraphQL API (needs GITHUB_TOKEN)
+    Ghsa,
+}
+
+impl Audit {
+    pub fn label(self) -> &'static str {
+        match self {
+            Audit::Osv => "OSV",
+            Audit::Ghsa => "GitHub advisory",
+        }
+    }
+
+    /// List source of the entries the audit adds.
+    pub fn source(self) -> &'static str {
+        match self {
+            Audit::Osv => "osv-audit",
+            Audit::Ghsa => "ghsa-audit",
+        }
+    }
+}
+
+/// Look up every (name, version) of the inventory in OSV and turn the
+/// advisories affecting them into list entries, so they are matched and
+/// reported like the blocklist. JSR and GitHub Packages names are skipped.
+pub fn osv(inventory: &BTreeSet<(String, String)>, verbose: bool) -> io::Result<Vec<ListEntry>> {
+    let queries: Vec<(&str, &str, &str)> = inventory
+        .iter()
+        .filter_map(|(name, version)| osv::package(name).map(|(eco, bare)| (eco, bare, version.as_str())))
+        .collect();
+    let ids: BTreeSet<String> = osv::query_batch(&queries)?.into_iter().flatten().collect();
+    if verbose {
+        eprintln!("[debug] OSV has {} advisories for {} package versions", ids.len(), queries.len());
+    }
+    let advisories: Vec<Value> = ids
+        .par_iter()
+        .filter_map(|id| match osv::fetch(id) {
+            Ok(advisory) => Some(advisory),
+            Err(e) => {
+                eprintln!("[warning] Could not fetch OSV advisory {}: {}", id, e);
+                None
+            }
+        })
+        .collect();
+    let ecosystems: BTreeSet<&str> = queries.iter().map(|(eco, _, _)| *eco).collect();
+    let mut list: Vec<ListEntry> = Vec::new();
+    for advisory in &advisories {
+        for ecosystem in &ecosystems {
+            list.extend(osv::parse(advisory, ecosystem));
+        }
+    }
+    Ok(list)
+}
+
+/// Look up every package name in the GitHub Advisory Database and turn the
+/// vulnerable ranges into list entries. Unlike OSV, the lookup is by name, so
+/// dependencies that are only declared are covered as well. The GraphQL API
+/// has no anonymous access, so this fails without `GITHUB_TOKEN`.
+pub fn ghsa(names: &BTreeSet<String>, verbose: bool) -> io::Result<Vec<ListEntry>> {
+    let token = env::var("GITHUB_TOKEN")
+        .map_err(|_| io::Error::new(io::ErrorKind::PermissionDenied, "GITHUB_TOKEN is not set"))?;
+    let packages: Vec<(&str, &str)> = names.iter().filter_map(|name| ghsa::package(name)).collect();
+    let list = ghsa::query_packages(&packages, &token)?;
+    if verbose {
+        eprintln!("[debug] GitHub has {} vulnerable ranges for {} packages", list.len(), packages.len());
+    }
+    Ok(list)
+}
+
+/// Run each of `databases` once for the `known` versions of the scanned
+/// packages and add what they report to `packages`. A database that fails
+/// is skipped with a warning.
+pub fn extend(packages: &mut PackageList, databases: &[Audit], known: Vec<(String, HashSet<String>)>, verbose: bool) {
+    let names: BTreeSet<String> = known.iter().map(|(name, _)| name.clone()).collect();
+    let inventory: BTreeSet<(String, String)> = known
+        .into_iter()
+        .flat_map(|(name, versions)| versions.into_iter().map(move |version| (name.clone(), version)))
+        .collect();
+    for database in databases.iter().collect::<BTreeSet<_>>() {
+        let result = match database {
+            Audit::Osv => osv(&inventory, verbose),
+            Audit::Ghsa => ghsa(&names, verbose),
+        };
+        match result {
+            Ok(list) => {
+                if verbose {
+                    eprintln!("[debug] Loaded {} packages from the {} audit of {} packages", list.len(), database.label(), names.len());
+                }
+                packages.extend(list, database.source());
+            }
+            Err(e) => eprintln!("[warning] {} audit failed, continuing without it: {}", database.label(), e),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::{Value, json};
+
+use crate::git;
+use crate::semver::{Range, parse_version};
+use crate::{Preload, file_preload, get_pkg_range, lockfile_versions, present_lockfiles};
+
+/// Commits that introduced `name@version` into the manifest and lockfiles of
+/// a directory, one entry per file. The history of each file is walked back
+/// once from the newest commit while the version is still present, in
+/// package.json as a range admitting it, and the oldest of those commits
+/// introduced it. Files where the version is not committed, or outside a git
+/// repository, give no entry.
+pub fn commits(dir: &str, name: &str, version: &str, preload: &Preload) -> Vec<Value> {
+    let contains = |file: &str, content: &str| {
+        let snapshot = file_preload(file, content);
+        match file {
+            "package.json" => {
+                let range = Range::parse(&get_pkg_range(name, &snapshot));
+                range.is_some_and(|r| parse_version(version).is_some_and(|v| r.matches(v)))
+            }
+            _ => lockfile_versions(name, &snapshot).get(file).is_some_and(|v| v.contains(version)),
+        }
+    };
+    let mut entries: Vec<Value> = Vec::new();
+    let Ok(mut blobs) = git::Blobs::open(Path::new(dir)) else {
+        return entries;
+    };
+    let manifest = preload.pkg_json.is_some().then_some("package.json");
+    for file in manifest.into_iter().chain(present_lockfiles(preload)) {
+        let Ok(output) = Command::new("git")
+            .args(["log", "--format=%H%x09%an%x09%aI%x09%s", "--", file])
+            .current_dir(dir)
+            .output()
+        else {
+            continue;
+        };
+        if !output.status.success() {
+            continue;
+        }
+        let mut introduced: Option<String> = None;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let commit = line.split('\t').next().unwrap_or("");
+            let committed = blobs.read(commit, file).ok().flatten();
+            if !committed.is_some_and(|content| contains(file, &content)) {
+                break;
+            }
+            introduced = Some(line.to_string());
+        }
+        let Some(line) = introduced else {
+            continue;
+        };
+        let fields: Vec<&str> = line.splitn(4, '\t').collect();
+        entries.push(json!({
+            "location": dir,
+            "package": name,
+            "version": version,
+            "file": file,
+            "commit": fields.first(),
+            "author": fields.get(1),
+            "date": fields.get(2),
+            "subject": fields.get(3),
+        }));
+    }
+    entries
+}
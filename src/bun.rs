@@ -0,0 +1,104 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use serde_json::Value;
+
+use crate::semver::release;
+use crate::yarn;
+
+/// Resolved packages of a Bun lockfile as (name, version) pairs.
+pub struct Lockfile {
+    /// `bun.lock` or `bun.lockb`
+    pub file: &'static str,
+    pub packages: Vec<(String, String)>,
+}
+
+impl Lockfile {
+    /// Versions of `name` resolved in the lockfile, reduced to
+    /// `major.minor.patch`.
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.packages
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .filter_map(|(_, v)| release(v))
+    }
+}
+
+/// Read the Bun lockfile of `dir`: the text `bun.lock` of Bun 1.2+, or the
+/// binary `bun.lockb`, which `bun` itself prints as a yarn.lock. Returns None
+/// without a Bun lockfile and an error when it cannot be read.
+pub fn load(dir: &Path) -> Option<io::Result<Lockfile>> {
+    let text = dir.join("bun.lock");
+    if text.is_file() {
+        return Some(fs::read_to_string(&text).and_then(|content| parse(&content)));
+    }
+    dir.join("bun.lockb").is_file().then(|| print_binary(dir))
+}
+
+/// Parse a text `bun.lock`. Every entry of `packages` starts with the
+/// resolved `name@version`, workspace, git and file entries are skipped.
+pub fn parse(content: &str) -> io::Result<Lockfile> {
+    let value: Value = serde_json::from_str(&strip_trailing_commas(content))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut packages: Vec<(String, String)> = Vec::new();
+    for entry in value.get("packages").and_then(|p| p.as_object()).into_iter().flat_map(|p| p.values()) {
+        let Some(resolved) = entry.get(0).and_then(|r| r.as_str()) else {
+            continue;
+        };
+        let from = usize::from(resolved.starts_with('@'));
+        let Some(at) = resolved[from..].find('@').map(|i| from + i) else {
+            continue;
+        };
+        let (name, version) = (&resolved[..at], &resolved[at + 1..]);
+        if !version.contains(':') {
+            packages.push((name.to_string(), version.to_string()));
+        }
+    }
+    packages.sort();
+    packages.dedup();
+    Ok(Lockfile { file: "bun.lock", packages })
+}
+
+/// Print `bun.lockb` as a yarn.lock with `bun ./bun.lockb` and parse that.
+fn print_binary(dir: &Path) -> io::Result<Lockfile> {
+    let output = Command::new("bun").arg("./bun.lockb").current_dir(dir).output().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(e.kind(), "bun.lockb can only be read with bun on PATH"),
+        _ => e,
+    })?;
+    if !output.status.success() {
+        return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    let lockfile = yarn::parse(&String::from_utf8_lossy(&output.stdout));
+    let mut packages: Vec<(String, String)> = Vec::new();
+    for entry in &lockfile.entries {
+        packages.extend(entry.names().into_iter().map(|n| (n.to_string(), entry.version.clone())));
+    }
+    packages.sort();
+    packages.dedup();
+    Ok(Lockfile { file: "bun.lockb", packages })
+}
+
+/// bun.lock is JSON with trailing commas, drop them outside of strings.
+fn strip_trailing_commas(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let rest = chars.clone().find(|c| !c.is_whitespace());
+            if matches!(rest, Some('}' | ']')) {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Prefix of gem names, e.g. `gem:rails`, so they never collide with npm
+/// names.
+pub const PREFIX: &str = "gem:";
+
+/// Lockfile names of Bundler: `Gemfile.lock`, or `gems.locked` next to a
+/// `gems.rb`.
+pub const LOCKFILES: [&str; 2] = ["Gemfile.lock", "gems.locked"];
+
+/// A gem of the specs of a lockfile, with the gems depending on it as
+/// `name@version`.
+pub struct Gem {
+    pub name: String,
+    pub version: String,
+    pub depended_by: Vec<String>,
+}
+
+/// The gems a Bundler lockfile resolves.
+pub struct Bundle {
+    /// `Gemfile.lock` or `gems.locked`
+    pub file: &'static str,
+    /// Gems of the `DEPENDENCIES` section, which the Gemfile declares, with
+    /// their requirement
+    pub dependencies: Vec<(String, String)>,
+    /// Gems of the specs of the `GEM`, `GIT` and `PATH` sections
+    pub specs: Vec<Gem>,
+}
+
+impl Bundle {
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.specs.iter().filter(move |g| g.name == name).map(|g| g.version.as_str())
+    }
+
+    /// The gems depending on `name` at `version`.
+    pub fn depended_by(&self, name: &str, version: &str) -> impl Iterator<Item = &String> {
+        self.specs
+            .iter()
+            .filter(move |g| g.name == name && g.version == version)
+            .flat_map(|g| &g.depended_by)
+    }
+}
+
+/// The lowest version a Gemfile requirement admits, padded to
+/// `major.minor.patch`, e.g. `5.0.0` for `~> 5.0` or `>= 1.2, < 2`.
+/// Requirements without a lower bound are returned as is.
+pub fn lowest(requirement: &str) -> String {
+    let lower = requirement
+        .split(',')
+        .map(|c| c.trim())
+        .find(|c| !c.is_empty() && !c.starts_with(['<', '!']))
+        .map(|c| c.trim_start_matches(['~', '=', '>', ' ']));
+    let Some(lower) = lower.filter(|l| l.starts_with(|ch: char| ch.is_ascii_digit())) else {
+        return requirement.to_string();
+    };
+    // Gem versions use `.` for prereleases, such as `7.1.0.rc1`
+    let mut parts: Vec<&str> = lower.split('.').take_while(|p| p.chars().all(|c| c.is_ascii_digit())).collect();
+    parts.resize(3, "0");
+    parts.join(".")
+}
+
+/// Read the Bundler lockfile of `dir`. Returns None when there is none.
+pub fn load(dir: &Path) -> Option<io::Result<Bundle>> {
+    let file = LOCKFILES.into_iter().find(|f| dir.join(f).is_file())?;
+    Some(fs::read_to_string(dir.join(file)).map(|content| parse(file, &content)))
+}
+
+/// Split `name (requirement)` into the name and requirement. A trailing `!`
+/// marks a dependency from a git or path source.
+fn split_entry(entry: &str) -> (&str, &str) {
+    let entry = entry.trim_end_matches('!');
+    match entry.split_once(" (") {
+        Some((name, rest)) => (name, rest.trim_end_matches(')')),
+        None => (entry, ""),
+    }
+}
+
+/// Parse a lockfile. Specs are indented by four spaces as `name (version)`,
+/// where the version may end in a platform such as `-x86_64-linux`, and
+/// their own dependencies by six.
+fn parse(file: &'static str, content: &str) -> Bundle {
+    let mut section = "";
+    let mut dependencies: Vec<(String, String)> = Vec::new();
+    let mut specs: Vec<(String, String)> = Vec::new();
+    let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+    for line in content.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let entry = line.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        if indent == 0 {
+            section = entry;
+            continue;
+        }
+        match (section, indent) {
+            ("DEPENDENCIES", 2) => {
+                let (name, requirement) = split_entry(entry);
+                dependencies.push((format!("{}{}", PREFIX, name), requirement.to_string()));
+            }
+            ("GEM" | "GIT" | "PATH", 4) => {
+                let (name, version) = split_entry(entry);
+                // Gem versions use `.` for prereleases, a `-` starts the platform
+                let version = version.split('-').next().unwrap_or(version);
+                specs.push((name.to_string(), version.to_string()));
+            }
+            ("GEM" | "GIT" | "PATH", 6) => {
+                if let Some((parent, version)) = specs.last() {
+                    let (name, _) = split_entry(entry);
+                    parents.entry(name.to_string()).or_default().push(format!("{}@{}", parent, version));
+                }
+            }
+            _ => {}
+        }
+    }
+    // Platform variants of a gem are one version
+    specs.sort();
+    specs.dedup();
+    let specs = specs
+        .into_iter()
+        .map(|(name, version)| {
+            let mut depended_by = parents.get(&name).cloned().unwrap_or_default();
+            depended_by.sort();
+            depended_by.dedup();
+            Gem {
+                name: format!("{}{}", PREFIX, name),
+                version,
+                depended_by,
+            }
+        })
+        .collect();
+    Bundle {
+        file,
+        dependencies,
+        specs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowest_of_requirements() {
+        assert_eq!(lowest("~> 5.0"), "5.0.0");
+        assert_eq!(lowest("~> 5.0.3"), "5.0.3");
+        assert_eq!(lowest(">= 1.2, < 2"), "1.2.0");
+        assert_eq!(lowest("< 2, >= 1.4.1"), "1.4.1");
+        assert_eq!(lowest("= 7.1.0.rc1"), "7.1.0");
+        assert_eq!(lowest("!= 1.1, >= 1.0"), "1.0.0");
+        assert_eq!(lowest("< 2"), "< 2");
+        assert_eq!(lowest(""), "");
+    }
+
+    #[test]
+    fn parses_dependencies_and_specs() {
+        let bundle = parse(
+            "Gemfile.lock",
+            "GEM\n  remote: https://rubygems.org/\n  specs:\n    nokogiri (1.16.0-x86_64-linux)\n      racc (~> 1.4)\n    racc (1.7.3)\n\nDEPENDENCIES\n  nokogiri (~> 1.16)\n  mygem!\n",
+        );
+        let dependencies = vec![("gem:nokogiri".to_string(), "~> 1.16".to_string()), ("gem:mygem".to_string(), String::new())];
+        assert_eq!(bundle.dependencies, dependencies);
+        assert_eq!(bundle.versions("gem:nokogiri").collect::<Vec<_>>(), vec!["1.16.0"]);
+        assert_eq!(bundle.depended_by("gem:racc", "1.7.3").collect::<Vec<_>>(), vec!["nokogiri@1.16.0"]);
+    }
+}
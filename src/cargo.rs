@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use toml::{Table, Value};
+
+use crate::semver::{Range, parse_semver};
+
+/// Prefix of crate names, e.g. `cargo:serde`, so they never collide with npm
+/// names.
+pub const PREFIX: &str = "cargo:";
+
+/// Cargo.toml dependency tables, with the `dependency` column of their rows.
+const SECTIONS: [(&str, &str); 3] = [("dependencies", "yes"), ("dev-dependencies", "dev"), ("build-dependencies", "build")];
+
+/// A dependency declared in a Cargo.toml.
+pub struct Dependency {
+    /// Crate name with the `cargo:` prefix, after a `package` rename
+    pub name: String,
+    /// Version requirement, or the git URL of a git dependency
+    pub requirement: String,
+    /// Table it is declared in, e.g. `dev-dependencies` or
+    /// `target.'cfg(unix)'.dependencies`
+    pub section: String,
+    /// `yes`, `dev` or `build`
+    pub kind: &'static str,
+}
+
+/// A crate resolved in Cargo.lock, with the packages depending on it as
+/// `name@version`.
+pub struct Locked {
+    pub name: String,
+    pub version: String,
+    pub depended_by: Vec<String>,
+}
+
+/// The dependencies of a crate and the crates its Cargo.lock resolves.
+pub struct Project {
+    /// `cargo:` name of the package, None for a virtual workspace manifest
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub dependencies: Vec<Dependency>,
+    /// Crates of the Cargo.lock in the project directory
+    pub locked: Vec<Locked>,
+    /// Crates of the Cargo.lock of the enclosing workspace, which a member
+    /// without a lockfile of its own is built from
+    workspace_locked: Vec<Locked>,
+}
+
+impl Project {
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.locked.iter().filter(move |l| l.name == name).map(|l| l.version.as_str())
+    }
+
+    /// The packages depending on `name` at `version` in the project's Cargo.lock.
+    pub fn depended_by(&self, name: &str, version: &str) -> impl Iterator<Item = &String> {
+        self.locked
+            .iter()
+            .filter(move |l| l.name == name && l.version == version)
+            .flat_map(|l| &l.depended_by)
+    }
+
+    /// The version Cargo.lock resolves `dependency` to: the highest locked
+    /// version its requirement admits, or the only locked version of a git
+    /// dependency.
+    pub fn resolve(&self, dependency: &Dependency) -> Option<&str> {
+        let lock = if self.locked.is_empty() { &self.workspace_locked } else { &self.locked };
+        let mut candidates = lock.iter().filter(|l| l.name == dependency.name).map(|l| l.version.as_str());
+        match requirement(&dependency.requirement) {
+            Some(range) => candidates
+                .filter(|v| parse_semver(v).is_some_and(|p| range.matches_semver(&p, false)))
+                .max_by_key(|v| parse_semver(v)),
+            None => {
+                let only = candidates.next();
+                if candidates.next().is_some() { None } else { only }
+            }
+        }
+    }
+}
+
+/// A Cargo version requirement as a range. Comparators are separated by
+/// commas and a bare version is a caret requirement, so `1.2` admits
+/// `>=1.2.0 <2.0.0` where npm would read it as `1.2.x`.
+fn requirement(req: &str) -> Option<Range> {
+    let comparators: Vec<String> = req
+        .split(',')
+        .map(|c| c.trim())
+        .map(|c| if c.starts_with(|ch: char| ch.is_ascii_digit()) { format!("^{}", c) } else { c.to_string() })
+        .collect();
+    Range::parse(&comparators.join(" "))
+}
+
+/// The lowest version a requirement admits, padded to `major.minor.patch`,
+/// e.g. `1.2.0` for `^1.2` or `>=1.2, <1.5`. Requirements without a lower
+/// bound are returned as is.
+pub fn lowest(req: &str) -> String {
+    let lower = req
+        .split(',')
+        .map(|c| c.trim())
+        .find(|c| !c.starts_with('<') && !c.is_empty() && *c != "*")
+        .map(|c| c.trim_start_matches(['^', '~', '=', '>', ' ']));
+    let Some(lower) = lower.filter(|l| l.starts_with(|ch: char| ch.is_ascii_digit())) else {
+        return req.to_string();
+    };
+    let core = lower.split(['-', '+']).next().unwrap_or(lower);
+    let mut parts: Vec<&str> = core.split('.').filter(|p| p.chars().all(|c| c.is_ascii_digit())).collect();
+    parts.resize(3, "0");
+    parts.join(".")
+}
+
+fn read_toml(path: &Path) -> io::Result<Table> {
+    let content = fs::read_to_string(path)?;
+    content.parse().map_err(|e: toml::de::Error| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Read Cargo.toml and Cargo.lock of `dir`. A member of a workspace inherits
+/// `workspace = true` entries from the root's `[workspace.dependencies]` and
+/// is resolved through the root's Cargo.lock. Returns None when the
+/// directory has no Cargo.toml.
+pub fn load(dir: &Path) -> Option<io::Result<Project>> {
+    let path = dir.join("Cargo.toml");
+    if !path.is_file() {
+        return None;
+    }
+    let manifest = match read_toml(&path) {
+        Ok(manifest) => manifest,
+        Err(e) => return Some(Err(e)),
+    };
+    let workspace = if manifest.contains_key("workspace") {
+        Some((dir.to_path_buf(), manifest.clone()))
+    } else {
+        workspace_root(dir)
+    };
+    let inherited = workspace.as_ref().and_then(|(_, root)| root.get("workspace"));
+    let package = manifest.get("package");
+    let name = package.and_then(|p| p.get("name")).and_then(|n| n.as_str()).map(|n| format!("{}{}", PREFIX, n));
+    let version = match package.and_then(|p| p.get("version")) {
+        Some(Value::String(v)) => Some(v.clone()),
+        Some(Value::Table(t)) if t.get("workspace").and_then(|w| w.as_bool()) == Some(true) => inherited
+            .and_then(|w| w.get("package"))
+            .and_then(|p| p.get("version"))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string()),
+        _ => None,
+    };
+
+    let mut dependencies: Vec<Dependency> = Vec::new();
+    let targets = manifest.get("target").and_then(|t| t.as_table()).into_iter().flatten();
+    let tables = std::iter::once((String::new(), &manifest))
+        .chain(targets.filter_map(|(cfg, t)| Some((format!("target.'{}'.", cfg), t.as_table()?))));
+    for (prefix, table) in tables {
+        for (section, kind) in SECTIONS {
+            let Some(entries) = table.get(section).and_then(|s| s.as_table()) else {
+                continue;
+            };
+            for (key, entry) in entries {
+                let entry = match entry.get("workspace").and_then(|w| w.as_bool()) {
+                    Some(true) => match inherited.and_then(|w| w.get("dependencies")).and_then(|d| d.get(key)) {
+                        Some(entry) => entry,
+                        None => continue,
+                    },
+                    _ => entry,
+                };
+                let (crate_name, requirement) = match entry {
+                    Value::String(req) => (key.as_str(), req.as_str()),
+                    Value::Table(t) => {
+                        let crate_name = t.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+                        // Path dependencies without a version are crates of the repository
+                        match t.get("version").or_else(|| t.get("git")).and_then(|v| v.as_str()) {
+                            Some(req) => (crate_name, req),
+                            None => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                dependencies.push(Dependency {
+                    name: format!("{}{}", PREFIX, crate_name),
+                    requirement: requirement.to_string(),
+                    section: format!("{}{}", prefix, section),
+                    kind,
+                });
+            }
+        }
+    }
+
+    let locked = match read_lock(&dir.join("Cargo.lock")) {
+        Some(Ok(locked)) => locked,
+        Some(Err(e)) => return Some(Err(e)),
+        None => Vec::new(),
+    };
+    let workspace_locked = match &workspace {
+        Some((root, _)) if locked.is_empty() && root != dir => read_lock(&root.join("Cargo.lock")).and_then(|l| l.ok()),
+        _ => None,
+    };
+    Some(Ok(Project {
+        name,
+        version,
+        dependencies,
+        locked,
+        workspace_locked: workspace_locked.unwrap_or_default(),
+    }))
+}
+
+/// The nearest directory above `dir` whose Cargo.toml has a `[workspace]`
+/// table, with that manifest. The search stops at the repository root.
+fn workspace_root(dir: &Path) -> Option<(PathBuf, Table)> {
+    for ancestor in dir.ancestors().skip(1) {
+        if let Ok(manifest) = read_toml(&ancestor.join("Cargo.toml"))
+            && manifest.contains_key("workspace")
+        {
+            return Some((ancestor.to_path_buf(), manifest));
+        }
+        if ancestor.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// The crates a Cargo.lock resolves from a registry or git, with the
+/// packages depending on them. Entries of `dependencies` are `name`,
+/// `name version` or `name version (source)`, the version only when the
+/// lockfile has several versions of the crate.
+fn read_lock(path: &Path) -> Option<io::Result<Vec<Locked>>> {
+    if !path.is_file() {
+        return None;
+    }
+    let lock = match read_toml(path) {
+        Ok(lock) => lock,
+        Err(e) => return Some(Err(e)),
+    };
+    let packages: Vec<&Table> = lock
+        .get("package")
+        .and_then(|p| p.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_table())
+        .collect();
+    let field = |p: &Table, key: &str| p.get(key).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let mut parents: HashMap<(String, String), Vec<String>> = HashMap::new();
+    for package in &packages {
+        let parent = format!("{}@{}", field(package, "name"), field(package, "version"));
+        for dependency in package.get("dependencies").and_then(|d| d.as_array()).into_iter().flatten() {
+            let mut parts = dependency.as_str().unwrap_or("").split_whitespace();
+            let name = parts.next().unwrap_or("").to_string();
+            let version = match parts.next() {
+                Some(version) => version.to_string(),
+                None => match packages.iter().find(|p| field(p, "name") == name) {
+                    Some(p) => field(p, "version"),
+                    None => continue,
+                },
+            };
+            parents.entry((name, version)).or_default().push(parent.clone());
+        }
+    }
+    let locked = packages
+        .iter()
+        .filter(|p| p.contains_key("source"))
+        .map(|p| {
+            let (name, version) = (field(p, "name"), field(p, "version"));
+            let mut depended_by = parents.remove(&(name.clone(), version.clone())).unwrap_or_default();
+            depended_by.sort();
+            Locked {
+                name: format!("{}{}", PREFIX, name),
+                version,
+                depended_by,
+            }
+        })
+        .collect();
+    Some(Ok(locked))
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::semver::parse_version;
+
+/// Prefix of Composer package names, e.g. `composer:monolog/monolog`, so they
+/// never collide with npm names.
+pub const PREFIX: &str = "composer:";
+
+/// composer.json requirement sections, with the `dependency` column of their
+/// rows.
+const SECTIONS: [(&str, &str); 2] = [("require", "yes"), ("require-dev", "dev")];
+
+/// A package required in composer.json.
+pub struct Require {
+    /// Package name with the `composer:` prefix
+    pub name: String,
+    pub constraint: String,
+    /// `require` or `require-dev`
+    pub section: &'static str,
+    /// `yes` or `dev`
+    pub kind: &'static str,
+}
+
+/// A package installed from composer.lock, with the packages requiring it as
+/// `name@version`.
+pub struct Locked {
+    pub name: String,
+    pub version: String,
+    pub depended_by: Vec<String>,
+}
+
+/// The requirements of a Composer project and the packages its composer.lock
+/// installs.
+pub struct Project {
+    /// `composer:` name of the project, if it has one
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub requires: Vec<Require>,
+    pub locked: Vec<Locked>,
+}
+
+impl Project {
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.locked.iter().filter(move |l| l.name == name).map(|l| l.version.as_str())
+    }
+
+    /// The packages requiring `name` at `version` in composer.lock.
+    pub fn depended_by(&self, name: &str, version: &str) -> impl Iterator<Item = &String> {
+        self.locked
+            .iter()
+            .filter(move |l| l.name == name && l.version == version)
+            .flat_map(|l| &l.depended_by)
+    }
+}
+
+/// Platform requirements such as `php`, `ext-json` or `composer-plugin-api`
+/// are not packages: package names always have a vendor.
+fn is_package(name: &str) -> bool {
+    name.contains('/')
+}
+
+/// The lowest version a constraint admits, padded to `major.minor.patch`,
+/// e.g. `1.2.0` for `^2.0 || ^1.2` or `>=1.2 <1.5`. Constraints without a
+/// numeric lower bound, such as `dev-main` or `*`, are returned as is.
+pub fn lowest(constraint: &str) -> String {
+    let lower_bound = |alternative: &str| -> Option<String> {
+        let lower = alternative
+            .split([' ', ','])
+            .find(|c| !c.is_empty() && !c.starts_with('<'))?
+            .trim_start_matches(['^', '~', '=', '>', 'v']);
+        if !lower.starts_with(|ch: char| ch.is_ascii_digit()) {
+            return None;
+        }
+        let core = lower.split(['-', '@', '+']).next().unwrap_or(lower);
+        let mut parts: Vec<&str> = core.split('.').filter(|p| p.chars().all(|c| c.is_ascii_digit())).collect();
+        parts.resize(3, "0");
+        Some(parts.join("."))
+    };
+    constraint
+        .split('|')
+        .filter_map(|alternative| lower_bound(alternative.trim()))
+        .min_by_key(|v| parse_version(v))
+        .unwrap_or_else(|| constraint.to_string())
+}
+
+fn read_json(path: &Path) -> io::Result<Value> {
+    serde_json::from_reader(File::open(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Read composer.json and composer.lock of `dir`. Returns None when the
+/// directory has no composer.json.
+pub fn load(dir: &Path) -> Option<io::Result<Project>> {
+    let path = dir.join("composer.json");
+    if !path.is_file() {
+        return None;
+    }
+    let manifest = match read_json(&path) {
+        Ok(manifest) => manifest,
+        Err(e) => return Some(Err(e)),
+    };
+    let field = |key: &str| manifest.get(key).and_then(|v| v.as_str()).map(|v| v.to_string());
+    let mut requires: Vec<Require> = Vec::new();
+    for (section, kind) in SECTIONS {
+        for (name, constraint) in manifest.get(section).and_then(|s| s.as_object()).into_iter().flatten() {
+            if !is_package(name) {
+                continue;
+            }
+            requires.push(Require {
+                name: format!("{}{}", PREFIX, name.to_lowercase()),
+                constraint: constraint.as_str().unwrap_or("").to_string(),
+                section,
+                kind,
+            });
+        }
+    }
+    let lock_path = dir.join("composer.lock");
+    let locked = if lock_path.is_file() {
+        match read_json(&lock_path) {
+            Ok(lock) => locked_packages(&lock),
+            Err(e) => return Some(Err(e)),
+        }
+    } else {
+        Vec::new()
+    };
+    Some(Ok(Project {
+        name: field("name").map(|n| format!("{}{}", PREFIX, n.to_lowercase())),
+        version: field("version"),
+        requires,
+        locked,
+    }))
+}
+
+/// The packages of `packages` and `packages-dev` in a composer.lock, with
+/// the packages whose `require` names them.
+fn locked_packages(lock: &Value) -> Vec<Locked> {
+    let entries: Vec<&Value> = ["packages", "packages-dev"]
+        .iter()
+        .filter_map(|s| lock.get(s).and_then(|p| p.as_array()))
+        .flatten()
+        .collect();
+    let field = |entry: &Value, key: &str| entry.get(key).and_then(|v| v.as_str()).unwrap_or("").to_lowercase();
+    let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in &entries {
+        let parent = format!("{}@{}", field(entry, "name"), entry.get("version").and_then(|v| v.as_str()).unwrap_or(""));
+        for name in entry.get("require").and_then(|r| r.as_object()).into_iter().flatten().map(|(n, _)| n) {
+            if is_package(name) {
+                parents.entry(name.to_lowercase()).or_default().push(parent.clone());
+            }
+        }
+    }
+    entries
+        .iter()
+        .map(|entry| {
+            let name = field(entry, "name");
+            let mut depended_by = parents.remove(&name).unwrap_or_default();
+            depended_by.sort();
+            Locked {
+                name: format!("{}{}", PREFIX, name),
+                version: entry.get("version").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+                depended_by,
+            }
+        })
+        .filter(|l| !l.version.is_empty())
+        .collect()
+}
@@ -0,0 +1,227 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Command};
+use toml::{Table, Value};
+
+/// File name looked up from the start path upwards.
+pub const FILE_NAME: &str = "package-checker.toml";
+
+/// Find `package-checker.toml` in `start` or the closest parent directory.
+pub fn discover(start: &Path) -> Option<PathBuf> {
+    let start = fs::canonicalize(start).ok()?;
+    start.ancestors().map(|dir| dir.join(FILE_NAME)).find(|p| p.is_file())
+}
+
+/// Read a config file whose keys are the long option names, in kebab or snake
+/// case, and turn it into command line arguments. Options already given on
+/// the command line or in a `PACKAGE_CHECKER_*` environment variable are
+/// skipped so both override the file.
+///
+/// ```toml
+/// start-path = "/srv/checkouts"
+/// package-file = ["packages.txt", "https://example.com/blocklist.csv"]
+/// no-npm = true
+/// jobs = 4
+/// ```
+pub fn to_args(path: &Path, cmd: &Command, cli: &ArgMatches) -> Result<Vec<String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let table: Table = content.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    let mut args: Vec<String> = Vec::new();
+    for (key, value) in &table {
+        // Structured settings that are not command line options
+        if key == HOOK_KEY {
+            continue;
+        }
+        let id = key.replace('-', "_");
+        let Some(arg) = cmd.get_arguments().find(|a| a.get_id() == id.as_str() && a.get_long().is_some()) else {
+            return Err(format!("unknown option {}", key));
+        };
+        if id == "config" {
+            return Err("config cannot be set from a config file".to_string());
+        }
+        if matches!(cli.value_source(&id), Some(ValueSource::CommandLine | ValueSource::EnvVariable)) {
+            continue;
+        }
+        let long = format!("--{}", arg.get_long().unwrap_or_default());
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, Value::Boolean(set)) => {
+                if *set {
+                    args.push(long);
+                }
+            }
+            (ArgAction::SetTrue, _) => return Err(format!("{} must be true or false", key)),
+            (_, Value::Array(items)) => {
+                for item in items {
+                    args.push(long.clone());
+                    args.push(scalar(key, item)?);
+                }
+            }
+            (_, value) => {
+                args.push(long);
+                args.push(scalar(key, value)?);
+            }
+        }
+    }
+    Ok(args)
+}
+
+fn scalar(key: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Integer(i) => Ok(i.to_string()),
+        Value::Float(f) => Ok(f.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(format!("{} must be a string, number or list of them", key)),
+    }
+}
+
+/// Table array holding post-scan hooks.
+const HOOK_KEY: &str = "hook";
+
+/// What a failing post-scan hook does to the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnFailure {
+    Ignore,
+    Warn,
+    /// Exit with status 1 once the scan is done
+    Fail,
+}
+
+/// A command run after the report is written.
+///
+/// ```toml
+/// [[hook]]
+/// command = "./scripts/notify-slack.sh"
+/// timeout = 60          # seconds, default 300
+/// on-failure = "fail"   # "ignore", "warn" (default) or "fail"
+/// ```
+#[derive(Clone, Debug)]
+pub struct Hook {
+    pub command: String,
+    pub timeout: u64,
+    pub on_failure: OnFailure,
+}
+
+/// Read the `[[hook]]` entries of a config file.
+pub fn hooks(path: &Path) -> Result<Vec<Hook>, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let table: Table = content.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    let Some(entries) = table.get(HOOK_KEY) else {
+        return Ok(Vec::new());
+    };
+    let entries = entries.as_array().ok_or("hook must be an array of tables ([[hook]])")?;
+    let mut hooks: Vec<Hook> = Vec::new();
+    for entry in entries {
+        let entry = entry.as_table().ok_or("hook must be an array of tables ([[hook]])")?;
+        let mut hook = Hook {
+            command: String::new(),
+            timeout: 300,
+            on_failure: OnFailure::Warn,
+        };
+        for (key, value) in entry {
+            match (key.as_str(), value) {
+                ("command", Value::String(c)) => hook.command = c.clone(),
+                ("timeout", Value::Integer(t)) if *t > 0 => hook.timeout = *t as u64,
+                ("on-failure" | "on_failure", Value::String(p)) => {
+                    hook.on_failure = match p.as_str() {
+                        "ignore" => OnFailure::Ignore,
+                        "warn" => OnFailure::Warn,
+                        "fail" => OnFailure::Fail,
+                        other => return Err(format!("hook on-failure must be ignore, warn or fail, not {}", other)),
+                    }
+                }
+                ("command" | "timeout" | "on-failure" | "on_failure", _) => return Err(format!("invalid hook {}", key)),
+                _ => return Err(format!("unknown hook option {}", key)),
+            }
+        }
+        if hook.command.is_empty() {
+            return Err("hook needs a command".to_string());
+        }
+        hooks.push(hook);
+    }
+    Ok(hooks)
+}
+
+/// Per project overrides, read from a `.package-checker.toml` next to a
+/// `package.json`.
+pub const DIR_FILE_NAME: &str = ".package-checker.toml";
+
+/// Settings a project can override for its own directory.
+///
+/// ```toml
+/// sections = ["dependencies"]
+/// exclude = ["fixtures/*", "examples/*"]
+/// allow = ["debug@4.4.2 # test fixture, never installed"]
+/// ```
+#[derive(Default)]
+pub struct DirConfig {
+    /// package.json sections to check instead of `--section`
+    pub sections: Option<Vec<String>>,
+    /// Globs of subdirectories, relative to the project, that are not scanned
+    pub exclude: Vec<String>,
+    /// Exemptions in the package file format, limited to the project
+    pub allow: Vec<String>,
+}
+
+/// The path an `allow` entry of the project in `dir` is limited to: the
+/// project itself, or `sub` inside it. None when `sub` is absolute or leaves
+/// the project, as the entry would then exempt matches of other projects.
+pub fn allow_path(dir: &Path, sub: Option<&str>) -> Option<PathBuf> {
+    let Some(sub) = sub else {
+        return Some(dir.to_path_buf());
+    };
+    let sub = Path::new(sub);
+    sub.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        .then(|| dir.join(sub))
+}
+
+/// Read the `.package-checker.toml` of `dir`, if there is one.
+pub fn load_dir(dir: &Path) -> Result<Option<DirConfig>, String> {
+    let path = dir.join(DIR_FILE_NAME);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let table: Table = content.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    let mut config = DirConfig::default();
+    for (key, value) in &table {
+        match key.as_str() {
+            "sections" => config.sections = Some(string_list(key, value)?),
+            "exclude" => config.exclude = string_list(key, value)?,
+            "allow" => config.allow = string_list(key, value)?,
+            _ => return Err(format!("unknown option {}", key)),
+        }
+    }
+    Ok(Some(config))
+}
+
+fn string_list(key: &str, value: &Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::String(s) => Ok(vec![s.clone()]),
+        Value::Array(items) => items
+            .iter()
+            .map(|i| i.as_str().map(|s| s.to_string()).ok_or_else(|| format!("{} must be a list of strings", key)))
+            .collect(),
+        _ => Err(format!("{} must be a list of strings", key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_allow_paths_to_the_project() {
+        let dir = Path::new("/repo/app");
+        assert_eq!(allow_path(dir, None), Some(PathBuf::from("/repo/app")));
+        assert_eq!(allow_path(dir, Some("test/fixtures")), Some(PathBuf::from("/repo/app/test/fixtures")));
+        assert_eq!(allow_path(dir, Some("./fixtures")), Some(PathBuf::from("/repo/app/./fixtures")));
+        assert_eq!(allow_path(dir, Some("/")), None);
+        assert_eq!(allow_path(dir, Some("/repo/other")), None);
+        assert_eq!(allow_path(dir, Some("..")), None);
+        assert_eq!(allow_path(dir, Some("fixtures/../../other")), None);
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{digest, http};
+use crate::packages::{self, ListEntry};
+
+/// Known compromised packages shipped with the binary.
+const BUNDLED: &str = include_str!("known_packages.txt");
+
+/// Feed `update-db` downloads by default, the list as maintained in this
+/// repository.
+pub const DEFAULT_FEED: &str = "https://raw.githubusercontent.com/schubergphilis/package-checker/main/src/known_packages.txt";
+
+/// Source name of built-in entries in the report.
+pub const SOURCE: &str = "builtin";
+
+fn cached_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("known-packages.txt")
+}
+
+/// Load the known compromised packages, preferring a copy refreshed with
+/// `update-db` over the list bundled at build time.
+pub fn load(cache_dir: &Path, verbose: bool) -> Vec<ListEntry> {
+    let path = cached_path(cache_dir);
+    if let Ok(content) = fs::read(&path) {
+        if verbose {
+            eprintln!("[debug] Using known packages from {}", path.display());
+        }
+        return packages::parse_text(content.as_slice(), &path.display().to_string());
+    }
+    packages::parse_text(BUNDLED.as_bytes(), SOURCE)
+}
+
+/// SHA-256 of the list `load` reads, recording which version was checked.
+pub fn sha256(cache_dir: &Path) -> String {
+    match fs::read(cached_path(cache_dir)) {
+        Ok(content) => digest::sha256(&content),
+        Err(_) => digest::sha256(BUNDLED.as_bytes()),
+    }
+}
+
+/// Download the feed at `url` into the cache and return the number of entries.
+/// A download without any valid entry is rejected so a broken feed does not
+/// replace a working list.
+pub fn update(url: &str, headers: &[String], cache_dir: &Path) -> io::Result<usize> {
+    let body = http::get(url, headers)?;
+    let entries = packages::parse_text(body.as_slice(), url);
+    if entries.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} contains no package entries", url),
+        ));
+    }
+    fs::create_dir_all(cache_dir)?;
+    let path = cached_path(cache_dir);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, body)?;
+    fs::rename(&tmp, &path)?;
+    Ok(entries.len())
+}
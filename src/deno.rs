@@ -0,0 +1,201 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::semver::release;
+
+/// Config files marking a Deno project.
+pub const CONFIG_FILES: [&str; 2] = ["deno.json", "deno.jsonc"];
+
+/// npm and JSR packages used by a Deno project. JSR packages are named with a
+/// `jsr:` prefix, e.g. `jsr:@std/path`, so they never collide with npm names.
+pub struct Project {
+    /// Name of the project from its config, if it is a publishable package
+    pub name: Option<String>,
+    /// Import map entries as (package, range)
+    pub imports: Vec<(String, String)>,
+    /// Packages resolved in deno.lock as (package, version)
+    pub locked: Vec<(String, String)>,
+}
+
+impl Project {
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.locked
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .filter_map(|(_, v)| release(v))
+    }
+}
+
+/// Read the import map of `deno.json` / `deno.jsonc` (or the file its
+/// `importMap` points to) and the packages resolved in `deno.lock`. Returns
+/// None when the directory is not a Deno project.
+pub fn load(dir: &Path) -> Option<Project> {
+    let config = CONFIG_FILES.iter().find_map(|f| read_json(&dir.join(f)));
+    let lock = read_json(&dir.join("deno.lock"));
+    if config.is_none() && lock.is_none() {
+        return None;
+    }
+    let mut imports: Vec<(String, String)> = Vec::new();
+    let mut maps: Vec<Value> = config.iter().cloned().collect();
+    if let Some(path) = config.as_ref().and_then(|c| c.get("importMap")).and_then(|p| p.as_str()) {
+        maps.extend(read_json(&dir.join(path)));
+    }
+    for map in &maps {
+        // Top level imports and the imports of every scope
+        let scopes = map.get("scopes").and_then(|s| s.as_object()).into_iter().flat_map(|s| s.values());
+        for table in std::iter::once(map).chain(scopes) {
+            for target in table.get("imports").and_then(|i| i.as_object()).into_iter().flat_map(|i| i.values()) {
+                if let Some(import) = target.as_str().and_then(parse_specifier) {
+                    imports.push(import);
+                }
+            }
+        }
+    }
+    imports.sort();
+    imports.dedup();
+    let name = config
+        .as_ref()
+        .and_then(|c| c.get("name"))
+        .and_then(|n| n.as_str())
+        .map(|n| format!("jsr:{}", n));
+    Some(Project {
+        name,
+        imports,
+        locked: lock.as_ref().map(locked_packages).unwrap_or_default(),
+    })
+}
+
+/// Packages in a deno.lock: under `npm.packages` in version 2, under
+/// `packages` in version 3 and at the top level from version 4 on.
+fn locked_packages(lock: &Value) -> Vec<(String, String)> {
+    let root = lock.get("packages").filter(|p| p.get("npm").is_some() || p.get("jsr").is_some()).unwrap_or(lock);
+    let mut locked: Vec<(String, String)> = Vec::new();
+    for (registry, prefix) in [("npm", ""), ("jsr", "jsr:")] {
+        let Some(section) = root.get(registry) else {
+            continue;
+        };
+        let v2 = section.get("specifiers").and(section.get("packages"));
+        let Some(packages) = v2.unwrap_or(section).as_object() else {
+            continue;
+        };
+        for key in packages.keys() {
+            // npm keys carry peer dependencies after `_`, e.g. `pkg@1.0.0_react@18.2.0`
+            let key = key.split('_').next().unwrap_or(key);
+            if let Some((name, version)) = split_name(key) {
+                locked.push((format!("{}{}", prefix, name), version.to_string()));
+            }
+        }
+    }
+    locked.sort();
+    locked.dedup();
+    locked
+}
+
+/// Parse an `npm:` or `jsr:` specifier such as `npm:chalk@^5.0.0/sub/path`
+/// into the package name and range. Other specifiers (URLs, local paths) are
+/// not packages.
+pub fn parse_specifier(spec: &str) -> Option<(String, String)> {
+    let (prefix, rest) = if let Some(rest) = spec.strip_prefix("npm:") {
+        ("", rest)
+    } else {
+        ("jsr:", spec.strip_prefix("jsr:")?)
+    };
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+    let (name, range) = match split_name(rest) {
+        // Drop a subpath after the range
+        Some((name, range)) => (name, range.split('/').next().unwrap_or(range)),
+        None => {
+            let parts = if rest.starts_with('@') { 2 } else { 1 };
+            let end = rest.match_indices('/').nth(parts - 1).map_or(rest.len(), |(i, _)| i);
+            (&rest[..end], "")
+        }
+    };
+    let range = if range.is_empty() { "*" } else { range };
+    Some((format!("{}{}", prefix, name), range.to_string()))
+}
+
+/// Split `name@version` at the `@` after the scope.
+fn split_name(spec: &str) -> Option<(&str, &str)> {
+    let from = usize::from(spec.starts_with('@'));
+    let i = spec[from..].find('@')?;
+    Some((&spec[..from + i], &spec[from + i + 1..]))
+}
+
+/// Read a JSON file, allowing the whole-line `//` comments of deno.jsonc.
+fn read_json(path: &Path) -> Option<Value> {
+    let content = fs::read_to_string(path).ok()?;
+    let stripped: String = content
+        .lines()
+        .filter(|l| !l.trim_start().starts_with("//"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    serde_json::from_str(&stripped).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(lock: &str) -> Vec<(String, String)> {
+        locked_packages(&serde_json::from_str(lock).unwrap())
+    }
+
+    fn pair(name: &str, version: &str) -> (String, String) {
+        (name.to_string(), version.to_string())
+    }
+
+    #[test]
+    fn reads_v2_locks() {
+        let lock = r#"{
+            "version": "2",
+            "remote": {"https://deno.land/std@0.200.0/path/mod.ts": "abc"},
+            "npm": {
+                "specifiers": {"chalk@5": "chalk@5.3.0"},
+                "packages": {"chalk@5.3.0": {"integrity": "sha512-abc", "dependencies": {}}}
+            }
+        }"#;
+        assert_eq!(locked(lock), vec![pair("chalk", "5.3.0")]);
+    }
+
+    #[test]
+    fn reads_v3_locks() {
+        let lock = r#"{
+            "version": "3",
+            "packages": {
+                "specifiers": {"npm:chalk@5": "npm:chalk@5.3.0", "jsr:@std/path@1": "jsr:@std/path@1.0.8"},
+                "jsr": {"@std/path@1.0.8": {"integrity": "abc"}},
+                "npm": {
+                    "chalk@5.3.0": {"integrity": "sha512-abc", "dependencies": {}},
+                    "react-dom@18.2.0_react@18.2.0": {"integrity": "sha512-def", "dependencies": {}}
+                }
+            },
+            "remote": {}
+        }"#;
+        assert_eq!(locked(lock), vec![pair("chalk", "5.3.0"), pair("jsr:@std/path", "1.0.8"), pair("react-dom", "18.2.0")]);
+    }
+
+    #[test]
+    fn reads_v4_locks() {
+        let lock = r#"{
+            "version": "4",
+            "specifiers": {"npm:@scope/pkg@^1.2.0": "1.2.3", "jsr:@std/fs@1": "1.0.4"},
+            "jsr": {"@std/fs@1.0.4": {"integrity": "abc"}},
+            "npm": {"@scope/pkg@1.2.3": {"integrity": "sha512-abc"}}
+        }"#;
+        assert_eq!(locked(lock), vec![pair("@scope/pkg", "1.2.3"), pair("jsr:@std/fs", "1.0.4")]);
+    }
+
+    #[test]
+    fn parses_specifiers() {
+        assert_eq!(parse_specifier("npm:chalk@^5.0.0"), Some(pair("chalk", "^5.0.0")));
+        assert_eq!(parse_specifier("npm:/chalk@5.3.0/source/index.js"), Some(pair("chalk", "5.3.0")));
+        assert_eq!(parse_specifier("npm:@scope/pkg@1"), Some(pair("@scope/pkg", "1")));
+        assert_eq!(parse_specifier("jsr:@std/path@^1.0.0"), Some(pair("jsr:@std/path", "^1.0.0")));
+        assert_eq!(parse_specifier("jsr:@std/path/posix"), Some(pair("jsr:@std/path", "*")));
+        assert_eq!(parse_specifier("npm:lodash"), Some(pair("lodash", "*")));
+        assert_eq!(parse_specifier("https://deno.land/std/path/mod.ts"), None);
+        assert_eq!(parse_specifier("./local.ts"), None);
+    }
+}
@@ -0,0 +1,72 @@
+use clap::ValueEnum;
+
+use crate::{bundler, cargo, composer, go, maven, nuget};
+
+/// Package ecosystem selected with `--ecosystem`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Ecosystem {
+    /// package.json, its lockfiles and Deno projects
+    Npm,
+    /// Cargo.toml and Cargo.lock
+    Cargo,
+    /// go.mod and go.sum
+    Go,
+    /// composer.json and composer.lock
+    Composer,
+    /// Gemfile.lock and gems.locked
+    Bundler,
+    /// Gradle lockfiles and Maven projects
+    Maven,
+    /// packages.lock.json and project.assets.json of .NET projects
+    #[value(name = "nuget")]
+    NuGet,
+}
+
+impl Ecosystem {
+    /// Files marking a directory as a project of the ecosystem, a leading `*`
+    /// matching any file name with that suffix.
+    pub fn project_files(self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Npm => &["package.json", "deno.json", "deno.jsonc", "deno.lock"],
+            Ecosystem::Cargo => &["Cargo.toml"],
+            Ecosystem::Go => &["go.mod"],
+            Ecosystem::Composer => &["composer.json"],
+            Ecosystem::Bundler => &bundler::LOCKFILES,
+            Ecosystem::Maven => &maven::PROJECT_FILES,
+            Ecosystem::NuGet => &nuget::PROJECT_FILES,
+        }
+    }
+}
+
+/// Whether `file_name` is one of `patterns`, where `*.csproj` matches any
+/// file name ending in `.csproj`.
+pub fn is_project_file(patterns: &[&str], file_name: &str) -> bool {
+    patterns.iter().any(|p| match p.strip_prefix('*') {
+        Some(suffix) => file_name.ends_with(suffix),
+        None => *p == file_name,
+    })
+}
+
+/// The name a package of an advisory for `ecosystem` is listed under:
+/// crates (OSV's `crates.io`, GitHub's `rust`) get the `cargo:` prefix, Go
+/// modules the `go:` prefix, Composer packages (`Packagist`, `composer`) the
+/// `composer:` prefix, gems (`RubyGems`) the `gem:` prefix, Maven artifacts
+/// (`Maven`, named `group:artifact`) the `maven:` prefix and NuGet packages
+/// (`NuGet`) the `nuget:` prefix in lowercase, other names are kept.
+pub fn list_name(name: &str, ecosystem: &str) -> String {
+    if ecosystem.eq_ignore_ascii_case("crates.io") || ecosystem.eq_ignore_ascii_case("rust") {
+        format!("{}{}", cargo::PREFIX, name)
+    } else if ecosystem.eq_ignore_ascii_case("go") {
+        format!("{}{}", go::PREFIX, name)
+    } else if ecosystem.eq_ignore_ascii_case("packagist") || ecosystem.eq_ignore_ascii_case("composer") {
+        format!("{}{}", composer::PREFIX, name.to_lowercase())
+    } else if ecosystem.eq_ignore_ascii_case("rubygems") {
+        format!("{}{}", bundler::PREFIX, name)
+    } else if ecosystem.eq_ignore_ascii_case("maven") {
+        format!("{}{}", maven::PREFIX, name)
+    } else if ecosystem.eq_ignore_ascii_case("nuget") {
+        format!("{}{}", nuget::PREFIX, name.to_lowercase())
+    } else {
+        name.to_string()
+    }
+}
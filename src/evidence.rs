@@ -0,0 +1,60 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+use crate::{Preload, node_modules};
+
+/// Details about the installs of a flagged package at the flagged `versions`
+/// for responders, one entry per install: where it is installed, the
+/// integrity and tarball it was installed from and the files at the top of
+/// the package. Nothing when none of the versions is installed.
+pub fn collect(dir: &str, name: &str, versions: &[&str], preload: &Preload) -> Vec<Value> {
+    node_modules::install_paths(Path::new(dir), name, versions)
+        .into_iter()
+        .filter_map(|install_path| install(dir, name, &install_path, preload))
+        .collect()
+}
+
+fn install(dir: &str, name: &str, install_path: &Path, preload: &Preload) -> Option<Value> {
+    let manifest: Value = File::open(install_path.join("package.json"))
+        .ok()
+        .and_then(|f| serde_json::from_reader(f).ok())?;
+    // package-lock.json keys installs by their path in the project, e.g.
+    // `node_modules/a/node_modules/b`
+    let relative = install_path.strip_prefix(dir).ok()?.to_string_lossy().replace('\\', "/");
+    let lock_entry = preload
+        .plock
+        .as_ref()
+        .and_then(|p| p.get("packages"))
+        .and_then(|p| p.get(&relative));
+    // package-lock.json first, then what npm recorded in the installed manifest
+    let field = |lock_key: &str, manifest_keys: [&str; 2]| {
+        lock_entry
+            .and_then(|e| e.get(lock_key))
+            .or_else(|| manifest_keys.iter().find_map(|k| manifest.pointer(k)))
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+    let mut files: Vec<String> = fs::read_dir(install_path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|e| {
+                    let name = e.file_name().to_string_lossy().into_owned();
+                    if e.file_type().is_ok_and(|t| t.is_dir()) { format!("{}/", name) } else { name }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    Some(json!({
+        "location": dir,
+        "package": name,
+        "version": manifest.get("version").and_then(|v| v.as_str()),
+        "install_path": install_path.display().to_string(),
+        "integrity": field("integrity", ["/_integrity", "/dist/integrity"]),
+        "tarball": field("resolved", ["/_resolved", "/dist/tarball"]),
+        "files": files,
+    }))
+}
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// What a direct dependency costs a project once installed.
+pub struct Footprint {
+    /// Bytes on disk of the installed package, including its nested
+    /// node_modules
+    pub size: u64,
+    /// Distinct packages it pulls in, directly or indirectly
+    pub transitive: usize,
+}
+
+/// Measure the dependency `name` as installed in `project/node_modules`.
+/// Returns None when it is not installed.
+pub fn measure(project: &Path, name: &str) -> Option<Footprint> {
+    let root = fs::canonicalize(project.join("node_modules").join(name)).ok()?;
+    let size = disk_usage(&root);
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut queue = vec![root.clone()];
+    while let Some(dir) = queue.pop() {
+        for dep in dependencies(&dir) {
+            if let Some(resolved) = resolve(&dir, project, &dep)
+                && resolved != root
+                && seen.insert(resolved.clone())
+            {
+                queue.push(resolved);
+            }
+        }
+    }
+    Some(Footprint {
+        size,
+        transitive: seen.len(),
+    })
+}
+
+/// Names in `dependencies` and `optionalDependencies` of an installed package.
+fn dependencies(dir: &Path) -> Vec<String> {
+    let Some(manifest) = File::open(dir.join("package.json"))
+        .ok()
+        .and_then(|f| serde_json::from_reader::<_, Value>(f).ok())
+    else {
+        return Vec::new();
+    };
+    ["dependencies", "optionalDependencies"]
+        .iter()
+        .filter_map(|s| manifest.get(s).and_then(|d| d.as_object()))
+        .flat_map(|d| d.keys().cloned())
+        .collect()
+}
+
+/// Resolve `name` the way node does from the package in `dir`: the closest
+/// `node_modules/<name>` in `dir` or its parents. pnpm's symlinked store
+/// layout resolves through the canonical path, the project's own
+/// node_modules is the last resort.
+fn resolve(dir: &Path, project: &Path, name: &str) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|a| a.join("node_modules").join(name))
+        .chain(std::iter::once(project.join("node_modules").join(name)))
+        .find(|p| p.is_dir())
+        .and_then(|p| fs::canonicalize(p).ok())
+}
+
+/// Total size of the files under `path`, without following symlinks.
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.filter_map(Result::ok).map(|e| disk_usage(&e.path())).sum())
+        .unwrap_or(0)
+}
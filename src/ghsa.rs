@@ -0,0 +1,138 @@
+use std::io;
+
+use serde_json::{Value, json};
+
+use crate::{ecosystem, http, severity};
+use crate::matcher::{Namespace, split_namespace};
+use crate::packages::ListEntry;
+
+const GITHUB_API: &str = "https://api.github.com";
+
+/// Packages per GraphQL request, each queried under an alias of its own.
+const BATCH_SIZE: usize = 50;
+
+/// True when the JSON is a GitHub REST advisory (`ghsa_id` with
+/// `vulnerabilities`) or a list of them.
+pub fn is_ghsa(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => map.contains_key("ghsa_id") && map.contains_key("vulnerabilities"),
+        Value::Array(items) => items.iter().any(|i| i.get("ghsa_id").is_some()),
+        _ => false,
+    }
+}
+
+/// Translate the vulnerable packages of GHSA advisories for `ecosystem` into
+/// entries with the GHSA ID as reason and its CVSS score. Ranges are rewritten
+/// to comparator ranges, e.g. `>= 1.0.0, < 1.2.3` becomes `>=1.0.0 <1.2.3`.
+pub fn parse(value: &Value, ecosystem: &str) -> Vec<ListEntry> {
+    let advisories: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        _ => vec![value],
+    };
+    let mut packages: Vec<ListEntry> = Vec::new();
+    for advisory in advisories {
+        let id = advisory.get("ghsa_id").and_then(|i| i.as_str()).map(|i| i.to_string());
+        let score = severity::ghsa_score(advisory);
+        for vuln in advisory.get("vulnerabilities").and_then(|v| v.as_array()).into_iter().flatten() {
+            let Some(package) = vuln.get("package") else {
+                continue;
+            };
+            let eco = package.get("ecosystem").and_then(|e| e.as_str()).unwrap_or("");
+            let name = package.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            let range = vuln.get("vulnerable_version_range").and_then(|r| r.as_str()).unwrap_or("");
+            if name.is_empty() || range.is_empty() || !eco.eq_ignore_ascii_case(ecosystem) {
+                continue;
+            }
+            packages.push(ListEntry::new(ecosystem::list_name(name, eco), translate_range(range), id.clone()).with_score(score));
+        }
+    }
+    packages
+}
+
+fn translate_range(range: &str) -> String {
+    let comparators: Vec<String> = range
+        .split(',')
+        .map(|c| c.split_whitespace().collect::<String>())
+        .filter(|c| !c.is_empty())
+        .collect();
+    match comparators.as_slice() {
+        [single] if single.starts_with('=') => single.trim_start_matches('=').to_string(),
+        _ => comparators.join(" "),
+    }
+}
+
+/// Fetch an advisory by GHSA ID from the GitHub API, using `GITHUB_TOKEN`
+/// when it is set to avoid the anonymous rate limit.
+pub fn fetch(id: &str) -> io::Result<Value> {
+    let mut headers = vec!["Accept: application/vnd.github+json".to_string()];
+    if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+        headers.push(format!("Authorization: Bearer {}", token));
+    }
+    let body = http::get_cached(&format!("{}/advisories/{}", GITHUB_API, id), &headers)?;
+    serde_json::from_slice(&body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The GraphQL advisory ecosystem and package name of a listed name, e.g.
+/// `RUST` and `serde` for `cargo:serde`. None for JSR and GitHub Packages,
+/// which the advisory database does not cover.
+pub fn package(name: &str) -> Option<(&'static str, &str)> {
+    let (namespace, bare) = split_namespace(name);
+    let ecosystem = match namespace {
+        Namespace::Npm => "NPM",
+        Namespace::Cargo => "RUST",
+        Namespace::Go => "GO",
+        Namespace::Composer => "COMPOSER",
+        Namespace::RubyGems => "RUBYGEMS",
+        Namespace::Maven => "MAVEN",
+        Namespace::NuGet => "NUGET",
+        Namespace::Jsr | Namespace::GitHub => return None,
+    };
+    Some((ecosystem, bare))
+}
+
+/// The vulnerable ranges of each (ecosystem, name) in the GitHub Advisory
+/// Database, queried through the GraphQL API, which requires a token.
+/// Withdrawn advisories are left out, and only the first 100 vulnerable
+/// ranges of a package are returned.
+pub fn query_packages(packages: &[(&str, &str)], token: &str) -> io::Result<Vec<ListEntry>> {
+    let headers = vec![format!("Authorization: Bearer {}", token)];
+    let mut list: Vec<ListEntry> = Vec::new();
+    for chunk in packages.chunks(BATCH_SIZE) {
+        let fields: Vec<String> = chunk
+            .iter()
+            .enumerate()
+            .map(|(i, (eco, name))| {
+                format!(
+                    "p{}: securityVulnerabilities(ecosystem: {}, package: {}, first: 100) {{ nodes {{ package {{ ecosystem name }} \
+                     vulnerableVersionRange advisory {{ ghsaId severity cvss {{ score }} withdrawnAt }} }} }}",
+                    i,
+                    eco,
+                    Value::String(name.to_string())
+                )
+            })
+            .collect();
+        let request = json!({ "query": format!("query {{ {} }}", fields.join(" ")) });
+        let body = http::post_json_cached(&format!("{}/graphql", GITHUB_API), &request.to_string(), &headers)?;
+        let response: Value = serde_json::from_slice(&body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let Some(data) = response.get("data").and_then(|d| d.as_object()) else {
+            let message = response.pointer("/errors/0/message").and_then(|m| m.as_str()).unwrap_or("no data in the response");
+            return Err(io::Error::other(message.to_string()));
+        };
+        for node in data.values().filter_map(|f| f.get("nodes")?.as_array()).flatten() {
+            let advisory = node.get("advisory").unwrap_or(&Value::Null);
+            if advisory.get("withdrawnAt").is_some_and(|w| !w.is_null()) {
+                continue;
+            }
+            let eco = node.pointer("/package/ecosystem").and_then(|e| e.as_str()).unwrap_or("");
+            let name = node.pointer("/package/name").and_then(|n| n.as_str()).unwrap_or("");
+            let range = node.get("vulnerableVersionRange").and_then(|r| r.as_str()).unwrap_or("");
+            if name.is_empty() || range.is_empty() {
+                continue;
+            }
+            let id = advisory.get("ghsaId").and_then(|i| i.as_str()).map(|i| i.to_string());
+            let score = severity::ghsa_score(advisory);
+            list.push(ListEntry::new(ecosystem::list_name(name, eco), translate_range(range), id).with_score(score));
+        }
+    }
+    Ok(list)
+}
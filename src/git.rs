@@ -0,0 +1,232 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::thread;
+
+use tempfile::TempDir;
+
+/// Settings restricting the transports `remote_file` fetches over to the
+/// network ones, so that a specifier from a scanned package.json cannot read
+/// local repositories or run commands through `ext::`.
+const NETWORK_ONLY: [&str; 10] = [
+    "-c",
+    "protocol.allow=never",
+    "-c",
+    "protocol.https.allow=always",
+    "-c",
+    "protocol.http.allow=always",
+    "-c",
+    "protocol.ssh.allow=always",
+    "-c",
+    "protocol.git.allow=always",
+];
+
+/// Files under `root` changed since `base`: committed on the branch,
+/// uncommitted or untracked.
+pub fn changed_files(root: &Path, base: &str) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let range = format!("{}...HEAD", base);
+    let commands: [&[&str]; 3] = [
+        &["diff", "--name-only", "--relative", &range],
+        &["diff", "--name-only", "--relative", "HEAD"],
+        &["ls-files", "--others", "--exclude-standard"],
+    ];
+    for args in commands {
+        let output = run(root, args)?;
+        files.extend(String::from_utf8_lossy(&output).lines().map(|l| root.join(l)));
+    }
+    Ok(files)
+}
+
+/// A shallow clone in a temporary directory that is removed again when this
+/// is dropped.
+pub struct Checkout {
+    dir: TempDir,
+}
+
+impl Checkout {
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Check out `rev` of the repository at `url`, its default branch when None,
+/// fetching only that commit. Fetching a revision rather than cloning a
+/// branch also takes tags and, where the server allows it, commit hashes.
+pub fn shallow_clone(url: &str, rev: Option<&str>) -> io::Result<Checkout> {
+    let rev = rev.unwrap_or("HEAD");
+    reject_options(url, rev)?;
+    let checkout = Checkout {
+        dir: tempfile::Builder::new().prefix("package-checker-remote-").tempdir()?,
+    };
+    run(checkout.path(), &["init", "--quiet"])?;
+    run(checkout.path(), &["fetch", "--quiet", "--depth", "1", "--", url, rev])?;
+    run(checkout.path(), &["checkout", "--quiet", "FETCH_HEAD"])?;
+    Ok(checkout)
+}
+
+/// Read `file` at `rev` of the repository at `url`, its default branch when
+/// None. Only that commit is fetched, into a temporary bare repository, and
+/// nothing is checked out.
+pub fn remote_file(url: &str, rev: Option<&str>, file: &str) -> io::Result<String> {
+    let rev = rev.unwrap_or("HEAD");
+    reject_options(url, rev)?;
+    let repository = Checkout {
+        dir: tempfile::Builder::new().prefix("package-checker-git-").tempdir()?,
+    };
+    run(repository.path(), &["init", "--quiet", "--bare"])?;
+    let fetch = [&NETWORK_ONLY[..], &["fetch", "--quiet", "--depth", "1", "--", url, rev]].concat();
+    // Transports allowed for users only are not used either
+    run_with(repository.path(), &fetch, &[("GIT_PROTOCOL_FROM_USER", "0")])?;
+    let content = run(repository.path(), &["show", &format!("FETCH_HEAD:{}", file)])?;
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// Refuse a URL or revision git would take for an option, such as
+/// `--upload-pack=<command>`.
+fn reject_options(url: &str, rev: &str) -> io::Result<()> {
+    match [url, rev].into_iter().find(|arg| arg.starts_with('-')) {
+        Some(arg) => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("refusing to fetch {}, it looks like an option", arg))),
+        None => Ok(()),
+    }
+}
+
+fn run(root: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    run_with(root, args, &[])
+}
+
+fn run_with(root: &Path, args: &[&str], envs: &[(&str, &str)]) -> io::Result<Vec<u8>> {
+    // Fail instead of waiting for credentials nobody is there to type
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .envs(envs.iter().copied())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Read `file` (or package.json) of `dir` as committed at the git ref `rev`.
+/// None when that commit has no such file, an error when `rev` is not a
+/// commit of the repository holding `dir`.
+pub fn read_at_rev(rev: &str, dir: &Path, file: &str) -> io::Result<Option<String>> {
+    let git = |args: &[&str]| Command::new("git").args(args).current_dir(dir).output();
+    if !git(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)])?.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} is neither a file nor a git ref of {}", rev, dir.display()),
+        ));
+    }
+    let output = git(&["show", &format!("{}:./{}", rev, file)])?;
+    Ok(output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// One `git cat-file --batch` reading files as committed at any number of
+/// revisions, one at a time.
+pub struct Blobs {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Blobs {
+    pub fn open(dir: &Path) -> io::Result<Blobs> {
+        let mut child = Command::new("git")
+            .args(["cat-file", "--batch"])
+            .current_dir(dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::other("git cat-file has no pipes"));
+        };
+        Ok(Blobs {
+            child,
+            stdin: Some(stdin),
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    /// `file`, relative to the directory the batch runs in, as committed at
+    /// `rev`. None when that commit has no such file.
+    pub fn read(&mut self, rev: &str, file: &str) -> io::Result<Option<String>> {
+        let stdin = self.stdin.as_mut().ok_or_else(|| io::Error::other("git cat-file is closed"))?;
+        writeln!(stdin, "{}:./{}", rev, file)?;
+        stdin.flush()?;
+        let mut header = String::new();
+        self.stdout.read_line(&mut header)?;
+        // `<oid> blob <size>`, or `<object> missing`
+        let size = match header.split_whitespace().collect::<Vec<&str>>()[..] {
+            [_, "blob", size] => size.parse::<usize>().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            [.., "missing" | "ambiguous"] => return Ok(None),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unexpected git cat-file output: {}", header.trim()))),
+        };
+        let mut content = vec![0; size + 1];
+        self.stdout.read_exact(&mut content)?;
+        content.truncate(size);
+        Ok(Some(String::from_utf8_lossy(&content).into_owned()))
+    }
+}
+
+impl Drop for Blobs {
+    fn drop(&mut self) {
+        // Closing stdin ends the batch
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+    }
+}
+
+/// Files named one of `names` in the tree of `rev` under `root`, with their
+/// content as committed, read from the object database without a checkout.
+/// Paths are relative to `root`, files inside node_modules are skipped.
+pub fn tree_files(root: &Path, rev: &str, names: &[&str]) -> io::Result<Vec<(PathBuf, String)>> {
+    let listing = run(root, &["ls-tree", "-r", "-z", "--name-only", rev])?;
+    let paths: Vec<PathBuf> = listing
+        .split(|b| *b == 0)
+        .map(|p| PathBuf::from(String::from_utf8_lossy(p).into_owned()))
+        .filter(|p| p.file_name().and_then(|f| f.to_str()).is_some_and(|f| names.contains(&f)))
+        .filter(|p| !p.components().any(|c| c.as_os_str() == "node_modules"))
+        .collect();
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    // One `git cat-file --batch` for all blobs, fed from another thread so
+    // neither side blocks on a full pipe
+    let mut child = Command::new("git")
+        .args(["cat-file", "--batch"])
+        .current_dir(root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        return Err(io::Error::other("git cat-file has no pipes"));
+    };
+    let requests: String = paths.iter().map(|p| format!("{}:./{}\n", rev, p.display())).collect();
+    let writer = thread::spawn(move || stdin.write_all(requests.as_bytes()));
+    let mut stdout = BufReader::new(stdout);
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+    for path in paths {
+        let mut header = String::new();
+        stdout.read_line(&mut header)?;
+        // `<oid> blob <size>`, or `<object> missing`
+        let size = match header.split_whitespace().collect::<Vec<&str>>()[..] {
+            [_, "blob", size] => size.parse::<usize>().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            _ => continue,
+        };
+        let mut content = vec![0; size + 1];
+        stdout.read_exact(&mut content)?;
+        content.truncate(size);
+        files.push((path, String::from_utf8_lossy(&content).into_owned()));
+    }
+    let _ = writer.join();
+    child.wait()?;
+    Ok(files)
+}
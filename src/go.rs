@@ -0,0 +1,184 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Prefix of Go module paths, e.g. `go:github.com/pkg/errors`, so they never
+/// collide with npm names.
+pub const PREFIX: &str = "go:";
+
+/// A module required in go.mod.
+pub struct Require {
+    /// Module path with the `go:` prefix, after `replace` directives
+    pub name: String,
+    pub version: String,
+    /// Marked `// indirect`: only needed by other requirements
+    pub indirect: bool,
+}
+
+/// The requirements of a Go module and the module versions its go.sum records.
+pub struct Module {
+    /// `go:` path of the module
+    pub path: Option<String>,
+    pub requires: Vec<Require>,
+    /// Modules whose source go.sum has a checksum for, as (name, version)
+    pub sums: Vec<(String, String)>,
+}
+
+impl Module {
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.sums.iter().filter(move |(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// The commit a pseudo-version such as `v0.0.0-20191109021931-daa7c04131f5`
+/// or `v1.2.4-0.20191109021931-daa7c04131f5` was made from, as the 12
+/// character revision prefix it ends with.
+pub fn pseudo_revision(version: &str) -> Option<&str> {
+    static PSEUDO: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"[.-]\d{14}-([0-9a-f]{12})(\+incompatible)?$").unwrap());
+    PSEUDO.captures(version).and_then(|c| c.get(1)).map(|m| m.as_str())
+}
+
+/// Whether a pseudo-version was made from `commit`, a commit hash of at
+/// least 12 characters.
+pub fn same_revision(version: &str, commit: &str) -> bool {
+    commit.len() >= 12
+        && commit.chars().all(|c| c.is_ascii_hexdigit())
+        && pseudo_revision(version).is_some_and(|revision| commit.starts_with(revision))
+}
+
+/// Read go.mod and go.sum of `dir`. Returns None when the directory has no
+/// go.mod.
+pub fn load(dir: &Path) -> Option<io::Result<Module>> {
+    let path = dir.join("go.mod");
+    if !path.is_file() {
+        return None;
+    }
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => return Some(Err(e)),
+    };
+    let mut module = parse_mod(&content);
+    if let Ok(sum) = fs::read_to_string(dir.join("go.sum")) {
+        module.sums = parse_sum(&sum);
+    }
+    Some(Ok(module))
+}
+
+/// A `replace` directive: `old [version] => new [version]`, where a `new`
+/// that is a directory replaces the module with local code.
+struct Replace {
+    old: String,
+    old_version: Option<String>,
+    new: String,
+    new_version: Option<String>,
+}
+
+fn unquote(s: &str) -> String {
+    s.trim_matches(|c| c == '"' || c == '`').to_string()
+}
+
+/// Parse the `module`, `require` and `replace` directives of a go.mod, on
+/// one line or in a `( ... )` block.
+fn parse_mod(content: &str) -> Module {
+    let mut path = None;
+    let mut requires: Vec<Require> = Vec::new();
+    let mut replaces: Vec<Replace> = Vec::new();
+    let mut block: Option<String> = None;
+    for line in content.lines() {
+        let (code, comment) = match line.find("//") {
+            Some(i) => (&line[..i], &line[i + 2..]),
+            None => (line, ""),
+        };
+        let mut words: Vec<&str> = code.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let verb = match block.clone() {
+            Some(_) if words == [")"] => {
+                block = None;
+                continue;
+            }
+            Some(verb) => verb,
+            None => {
+                let verb = words.remove(0).to_string();
+                if words == ["("] {
+                    block = Some(verb);
+                    continue;
+                }
+                verb
+            }
+        };
+        match (verb.as_str(), words.as_slice()) {
+            ("module", [name]) => path = Some(format!("{}{}", PREFIX, unquote(name))),
+            ("require", [name, version]) => requires.push(Require {
+                name: unquote(name),
+                version: version.to_string(),
+                indirect: comment.split(';').any(|c| c.trim() == "indirect"),
+            }),
+            ("replace", _) => {
+                let Some(arrow) = words.iter().position(|w| *w == "=>") else {
+                    continue;
+                };
+                let (old, new) = (&words[..arrow], &words[arrow + 1..]);
+                let (Some(old_name), Some(new_name)) = (old.first(), new.first()) else {
+                    continue;
+                };
+                replaces.push(Replace {
+                    old: unquote(old_name),
+                    old_version: old.get(1).map(|v| v.to_string()),
+                    new: unquote(new_name),
+                    new_version: new.get(1).map(|v| v.to_string()),
+                });
+            }
+            _ => {}
+        }
+    }
+    // A replacement decides which module is built, local code is not checked
+    let mut replaced: Vec<Require> = Vec::new();
+    for require in requires {
+        let replace = replaces
+            .iter()
+            .find(|r| r.old == require.name && r.old_version.as_ref().is_none_or(|v| *v == require.version));
+        match replace {
+            Some(Replace { new_version: None, .. }) => {}
+            Some(Replace {
+                new,
+                new_version: Some(version),
+                ..
+            }) => replaced.push(Require {
+                name: format!("{}{}", PREFIX, new),
+                version: version.clone(),
+                indirect: require.indirect,
+            }),
+            None => replaced.push(Require {
+                name: format!("{}{}", PREFIX, require.name),
+                ..require
+            }),
+        }
+    }
+    Module {
+        path,
+        requires: replaced,
+        sums: Vec::new(),
+    }
+}
+
+/// Modules with a source checksum in go.sum. `/go.mod` lines only record the
+/// go.mod a version was resolved with, not source that was built.
+fn parse_sum(content: &str) -> Vec<(String, String)> {
+    let mut sums: Vec<(String, String)> = content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (name, version) = (fields.next()?, fields.next()?);
+            (!version.ends_with("/go.mod")).then(|| (format!("{}{}", PREFIX, name), version.to_string()))
+        })
+        .collect();
+    sums.sort();
+    sums.dedup();
+    sums
+}
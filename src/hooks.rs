@@ -0,0 +1,63 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::config::Hook;
+
+/// Run a post-scan hook through the shell. The scan summary is written to its
+/// stdin as JSON and the report paths are passed in `env`. The hook is killed
+/// when it runs longer than its timeout.
+pub fn run(hook: &Hook, summary: &Value, env: &[(&str, String)]) -> io::Result<()> {
+    let mut child = Command::new("sh")
+        .args(["-c", &hook.command])
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::piped())
+        .spawn()?;
+    let deadline = Instant::now() + Duration::from_secs(hook.timeout);
+    if let Some(mut stdin) = child.stdin.take() {
+        // Written from a thread, as a hook that does not read its input would
+        // block a large summary before the timeout is checked
+        let input = summary.to_string();
+        thread::spawn(move || stdin.write_all(input.as_bytes()));
+    }
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return match status.success() {
+                true => Ok(()),
+                false => Err(io::Error::other(format!("exited with {}", status))),
+            };
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(io::ErrorKind::TimedOut, format!("timed out after {}s", hook.timeout)));
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OnFailure;
+
+    #[test]
+    fn times_out_a_hook_that_ignores_its_input() {
+        let hook = Hook { command: "sleep 30".to_string(), timeout: 1, on_failure: OnFailure::Warn };
+        let summary = Value::String("x".repeat(1 << 20));
+        let started = Instant::now();
+        let err = run(&hook, &summary, &[]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn passes_the_summary_on_stdin() {
+        let hook = Hook { command: "grep -q '\"findings\":3'".to_string(), timeout: 10, on_failure: OnFailure::Warn };
+        let summary = serde_json::json!({ "findings": 3 });
+        assert!(run(&hook, &summary, &[]).is_ok());
+    }
+}
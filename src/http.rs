@@ -0,0 +1,172 @@
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::digest;
+
+/// Proxy given with `--proxy`, used instead of the proxy environment
+/// variables.
+static PROXY: OnceLock<String> = OnceLock::new();
+
+/// Response cache of the registry and advisory lookups, set up with
+/// `set_cache`.
+static CACHE: OnceLock<ResponseCache> = OnceLock::new();
+
+struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+    offline: bool,
+}
+
+/// Keep the responses of cached requests in `dir` and answer them from there
+/// for `ttl`. When `offline`, cached requests are answered from `dir` however
+/// old the response is, and every other request fails.
+pub fn set_cache(dir: PathBuf, ttl: Duration, offline: bool) {
+    let _ = CACHE.set(ResponseCache { dir, ttl, offline });
+}
+
+/// Send every request through `proxy`, e.g. `http://proxy.example.com:3128`.
+/// Hosts in `NO_PROXY` are still reached directly.
+pub fn set_proxy(proxy: &str) {
+    let _ = PROXY.set(proxy.to_string());
+}
+
+/// Fetch `url` with curl and return the response body. `headers` are passed
+/// as-is (`Name: value`). Non-2xx responses are reported as errors.
+pub fn get(url: &str, headers: &[String]) -> io::Result<Vec<u8>> {
+    request(url, headers, None)
+}
+
+/// Like `get`, answered from the response cache when it holds a fresh copy.
+pub fn get_cached(url: &str, headers: &[String]) -> io::Result<Vec<u8>> {
+    cached(url, headers, None)
+}
+
+/// POST a JSON `body` to `url` and return the response body.
+pub fn post_json(url: &str, body: &str, headers: &[String]) -> io::Result<Vec<u8>> {
+    request(url, headers, Some(("application/json", body.to_string())))
+}
+
+/// Like `post_json`, answered from the response cache when it holds a fresh
+/// copy. For queries that only read, such as advisory lookups.
+pub fn post_json_cached(url: &str, body: &str, headers: &[String]) -> io::Result<Vec<u8>> {
+    cached(url, headers, Some(body))
+}
+
+fn cached(url: &str, headers: &[String], body: Option<&str>) -> io::Result<Vec<u8>> {
+    let send = || request(url, headers, body.map(|b| ("application/json", b.to_string())));
+    let Some(cache) = CACHE.get() else {
+        return send();
+    };
+    // Credentials do not change what is asked for, so they are not part of the key
+    let asked: Vec<&String> = headers.iter().filter(|h| !h.to_ascii_lowercase().starts_with("authorization:")).collect();
+    let key = digest::sha256(format!("{}\n{:?}\n{}", url, asked, body.unwrap_or("")).as_bytes());
+    let path = cache.dir.join(key);
+    let age = fs::metadata(&path).and_then(|m| m.modified()).ok().and_then(|m| m.elapsed().ok());
+    if age.is_some_and(|age| cache.offline || age < cache.ttl)
+        && let Ok(response) = fs::read(&path)
+    {
+        return Ok(response);
+    }
+    if cache.offline {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not in the offline cache", url)));
+    }
+    let response = send()?;
+    // A cache that cannot be written only costs the next scan a request
+    let _ = fs::create_dir_all(&cache.dir).and_then(|_| fs::write(&path, &response));
+    Ok(response)
+}
+
+/// POST the contents of the file at `path` to `url` and return the response
+/// body.
+pub fn post_file(url: &str, path: &Path, content_type: &str, headers: &[String]) -> io::Result<Vec<u8>> {
+    request(url, headers, Some((content_type, format!("@{}", path.display()))))
+}
+
+/// A value quoted for a curl config file.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Plain http to a host other than this machine, where credentials would
+/// cross the network readable to anyone on the way.
+fn is_cleartext(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return false;
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    !matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// `body` is the content type and the curl `--data-binary` argument, which
+/// reads a file when it starts with `@`. Headers and the proxy, which may
+/// carry credentials, are passed as a config on stdin rather than as
+/// arguments anyone on the machine can read.
+fn request(url: &str, headers: &[String], body: Option<(&str, String)>) -> io::Result<Vec<u8>> {
+    if CACHE.get().is_some_and(|c| c.offline) {
+        return Err(io::Error::other(format!("request to {} skipped, running --offline", url)));
+    }
+    if is_cleartext(url) && headers.iter().any(|h| h.to_ascii_lowercase().starts_with("authorization:")) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("refusing to send credentials to {} over plain http", url),
+        ));
+    }
+    let mut cmd = Command::new("curl");
+    let mut config = String::new();
+    // curl itself honors HTTPS_PROXY and NO_PROXY, but for plain http only
+    // the lowercase http_proxy
+    if let Some(proxy) = PROXY.get() {
+        config.push_str(&format!("proxy = {}\n", quote(proxy)));
+        if let Ok(no_proxy) = env::var("NO_PROXY").or_else(|_| env::var("no_proxy")) {
+            config.push_str(&format!("noproxy = {}\n", quote(&no_proxy)));
+        }
+    } else if env::var_os("http_proxy").is_none()
+        && let Some(proxy) = env::var_os("HTTP_PROXY")
+    {
+        cmd.env("http_proxy", proxy);
+    }
+    for header in headers {
+        config.push_str(&format!("header = {}\n", quote(header)));
+    }
+    cmd.args(["--config", "-", "--fail", "--silent", "--show-error", "--location", "--max-time", "60"]);
+    if let Some((content_type, data)) = body {
+        config.push_str(&format!("header = {}\n", quote(&format!("Content-Type: {}", content_type))));
+        cmd.args(["--data-binary", &data]);
+    }
+    let mut child = cmd.arg(url).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    // curl reads the whole config before it starts, so this cannot block on
+    // a full stdout pipe
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(config.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!("request to {} failed: {}", url, stderr.trim())));
+    }
+    Ok(output.stdout)
+}
+
+pub fn is_url(s: &str) -> bool {
+    s.starts_with("https://") || s.starts_with("http://")
+}
+
+/// Directory for downloaded files: `$XDG_CACHE_HOME/package-checker`, falling
+/// back to `~/.cache/package-checker`.
+pub fn default_cache_dir() -> PathBuf {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("package-checker")
+}
@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::Value;
+
+/// Lockfiles per package manager. A directory with lockfiles of more than one
+/// manager installs different versions depending on who runs what.
+const MANAGER_LOCKFILES: [(&str, &[&str]); 4] = [
+    ("npm", &["package-lock.json", "npm-shrinkwrap.json"]),
+    ("yarn", &["yarn.lock"]),
+    ("pnpm", &["pnpm-lock.yaml"]),
+    ("bun", &["bun.lock", "bun.lockb"]),
+];
+
+/// CI configurations searched for install commands, relative to the project
+/// or one of its parents up to the repository root.
+const CI_FILES: [&str; 6] = [
+    ".gitlab-ci.yml",
+    ".circleci/config.yml",
+    "azure-pipelines.yml",
+    "bitbucket-pipelines.yml",
+    ".travis.yml",
+    "Jenkinsfile",
+];
+
+static INSTALL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?m)\b(?:npm (?:ci|install|i)\b|yarn(?: install\b| --frozen-lockfile| --immutable|[ \t]*$)|pnpm (?:install|i)\b|bun (?:install|i)\b)",
+    )
+    .unwrap()
+});
+
+/// A directory with lockfiles of several package managers.
+pub struct MixedLockfiles {
+    pub lockfiles: Vec<&'static str>,
+    /// Package manager CI installs with and where that was found: the
+    /// `packageManager` field of package.json or a CI configuration
+    pub ci: Option<(&'static str, String)>,
+}
+
+/// Check `dir` for lockfiles of more than one package manager.
+pub fn mixed_lockfiles(dir: &Path, pkg_json: Option<&Value>) -> Option<MixedLockfiles> {
+    let mut managers = 0;
+    let mut lockfiles: Vec<&'static str> = Vec::new();
+    for (_, files) in MANAGER_LOCKFILES {
+        let present: Vec<&'static str> = files.iter().copied().filter(|f| dir.join(f).is_file()).collect();
+        managers += usize::from(!present.is_empty());
+        lockfiles.extend(present);
+    }
+    if managers < 2 {
+        return None;
+    }
+    let declared = pkg_json
+        .and_then(|p| p.get("packageManager"))
+        .and_then(|m| m.as_str())
+        .and_then(|m| manager(m.split('@').next().unwrap_or(m)))
+        .map(|m| (m, "package.json packageManager".to_string()));
+    Some(MixedLockfiles {
+        lockfiles,
+        ci: declared.or_else(|| ci_manager(dir)),
+    })
+}
+
+/// The first package manager install command in a CI configuration of `dir`
+/// or a parent up to the repository root.
+fn ci_manager(dir: &Path) -> Option<(&'static str, String)> {
+    for ancestor in dir.ancestors() {
+        let mut configs: Vec<PathBuf> = CI_FILES.iter().map(|f| ancestor.join(f)).collect();
+        if let Ok(entries) = fs::read_dir(ancestor.join(".github/workflows")) {
+            let mut workflows: Vec<PathBuf> = entries.filter_map(Result::ok).map(|e| e.path()).collect();
+            workflows.sort();
+            configs.extend(workflows);
+        }
+        for config in configs {
+            let Ok(content) = fs::read_to_string(&config) else {
+                continue;
+            };
+            if let Some(command) = INSTALL.find(&content)
+                && let Some(m) = manager(command.as_str().split(' ').next().unwrap_or(""))
+            {
+                return Some((m, config.display().to_string()));
+            }
+        }
+        if ancestor.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+fn manager(name: &str) -> Option<&'static str> {
+    MANAGER_LOCKFILES.iter().map(|(m, _)| *m).find(|m| *m == name)
+}
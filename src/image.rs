@@ -0,0 +1,278 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use tar::EntryType;
+use tempfile::TempDir;
+
+/// Whiteout prefix of a layer: `.wh.name` deletes `name` from the layers below.
+const WHITEOUT: &str = ".wh.";
+
+/// Whiteout hiding every entry the layers below have in its directory.
+const OPAQUE: &str = ".wh..wh..opq";
+
+/// Symlinks followed while resolving one path before giving up, as the
+/// kernel does.
+const MAX_LINKS: usize = 40;
+
+/// The filesystem of an image, unpacked into a temporary directory that is
+/// removed again when this is dropped.
+pub struct Unpacked {
+    dir: TempDir,
+    /// The merged layers
+    pub rootfs: PathBuf,
+    /// Folders holding a top-level node_modules, such as `usr/local/lib` with
+    /// the globally installed packages
+    pub install_roots: Vec<PathBuf>,
+}
+
+/// Unpack `image`: a `docker save` or OCI image layout tarball, or else an
+/// image reference exported with `docker save` (or `podman save`).
+pub fn unpack(image: &str) -> io::Result<Unpacked> {
+    let dir = tempfile::Builder::new().prefix("package-checker-image-").tempdir()?;
+    let mut unpacked = Unpacked {
+        rootfs: dir.path().join("rootfs"),
+        install_roots: Vec::new(),
+        dir,
+    };
+    let archive = if Path::new(image).is_file() {
+        PathBuf::from(image)
+    } else {
+        let saved = unpacked.dir.path().join("image.tar");
+        save(image, &saved)?;
+        saved
+    };
+    let layout = unpacked.dir.path().join("image");
+    tar::Archive::new(File::open(&archive)?).unpack(&layout)?;
+    let _ = fs::remove_file(unpacked.dir.path().join("image.tar"));
+    fs::create_dir_all(&unpacked.rootfs)?;
+    for layer in layers(&layout)? {
+        apply_layer(&layout.join(layer), &unpacked.rootfs)?;
+    }
+    let _ = fs::remove_dir_all(&layout);
+    find_install_roots(&unpacked.rootfs, &mut unpacked.install_roots);
+    unpacked.install_roots.sort();
+    Ok(unpacked)
+}
+
+/// Export an image reference to `output` with the first container engine
+/// found on the PATH.
+fn save(image: &str, output: &Path) -> io::Result<()> {
+    for engine in ["docker", "podman"] {
+        let result = Command::new(engine).arg("save").arg("-o").arg(output).arg(image).output();
+        match result {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+            Ok(o) if o.status.success() => return Ok(()),
+            Ok(o) => {
+                let stderr = String::from_utf8_lossy(&o.stderr);
+                return Err(io::Error::other(format!("{} save failed: {}", engine, stderr.trim())));
+            }
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "neither docker nor podman found on PATH, pass a docker save tarball instead"))
+}
+
+fn read_json(path: &Path) -> io::Result<Value> {
+    serde_json::from_reader(File::open(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Path of a blob of an OCI image layout, e.g. `blobs/sha256/<hex>`.
+fn blob(digest: &str) -> Option<PathBuf> {
+    let (algorithm, hex) = digest.split_once(':')?;
+    Some(Path::new("blobs").join(algorithm).join(hex))
+}
+
+/// The layer files of an unpacked image archive, bottom layer first. `docker
+/// save` lists them in manifest.json, an OCI layout through index.json and
+/// the image manifest it points to.
+fn layers(layout: &Path) -> io::Result<Vec<PathBuf>> {
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("no {} in the image archive", what));
+    if layout.join("manifest.json").is_file() {
+        let manifest = read_json(&layout.join("manifest.json"))?;
+        let layers = manifest.pointer("/0/Layers").and_then(|l| l.as_array()).ok_or_else(|| invalid("layers"))?;
+        return Ok(layers.iter().filter_map(|l| l.as_str()).map(PathBuf::from).collect());
+    }
+    let mut manifest = read_json(&layout.join("index.json"))?;
+    // Follow image indexes (multi-platform images) down to an image manifest
+    while let Some(entries) = manifest.get("manifests").and_then(|m| m.as_array()) {
+        let digest = pick_manifest(entries).ok_or_else(|| invalid("image manifest"))?;
+        manifest = read_json(&layout.join(blob(digest).ok_or_else(|| invalid("image manifest"))?))?;
+    }
+    let mut layers: Vec<PathBuf> = Vec::new();
+    for layer in manifest.get("layers").and_then(|l| l.as_array()).ok_or_else(|| invalid("layers"))? {
+        let media_type = layer.get("mediaType").and_then(|m| m.as_str()).unwrap_or("");
+        if media_type.ends_with("+zstd") {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "zstd compressed layers are not supported"));
+        }
+        let digest = layer.get("digest").and_then(|d| d.as_str()).and_then(blob).ok_or_else(|| invalid("layer digest"))?;
+        layers.push(digest);
+    }
+    Ok(layers)
+}
+
+/// The manifest of an index for the platform this runs on, else the first
+/// one that is not an attestation (platform `unknown`).
+fn pick_manifest(entries: &[Value]) -> Option<&str> {
+    let architecture = match env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        arch => arch,
+    };
+    let platform = |entry: &Value, key: &str| entry.pointer(&format!("/platform/{}", key)).and_then(|v| v.as_str()).map(str::to_string);
+    entries
+        .iter()
+        .find(|e| platform(e, "architecture").as_deref() == Some(architecture))
+        .or_else(|| entries.iter().find(|e| platform(e, "os").as_deref() != Some("unknown")))
+        .and_then(|e| e.get("digest")?.as_str())
+}
+
+/// Path of a layer entry relative to the root, None when it would escape it.
+fn entry_path(path: &Path) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir | Component::RootDir => {}
+            _ => return None,
+        }
+    }
+    (!relative.as_os_str().is_empty()).then_some(relative)
+}
+
+/// `relative` inside `rootfs`, following the symlinks the layers extracted so
+/// far. None when a symlink leads out of the rootfs or they loop, so that
+/// nothing outside it is ever created or removed.
+fn resolve(rootfs: &Path, relative: &Path) -> Option<PathBuf> {
+    let mut resolved = PathBuf::new();
+    let mut pending: Vec<PathBuf> = relative.components().rev().map(|c| PathBuf::from(c.as_os_str())).collect();
+    let mut links = 0;
+    while let Some(part) = pending.pop() {
+        match part.components().next() {
+            Some(Component::Normal(name)) => match fs::read_link(rootfs.join(&resolved).join(name)) {
+                Ok(link) => {
+                    links += 1;
+                    if links > MAX_LINKS {
+                        return None;
+                    }
+                    if link.has_root() {
+                        resolved.clear();
+                    }
+                    pending.extend(link.components().rev().map(|c| PathBuf::from(c.as_os_str())));
+                }
+                Err(_) => resolved.push(name),
+            },
+            Some(Component::ParentDir) => resolved.pop().then_some(())?,
+            _ => {}
+        }
+    }
+    Some(rootfs.join(resolved))
+}
+
+fn remove(path: &Path) {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => {
+            let _ = fs::remove_dir_all(path);
+        }
+        Ok(_) => {
+            let _ = fs::remove_file(path);
+        }
+        Err(_) => {}
+    }
+}
+
+/// Extract a layer, gzip compressed or not, over the layers below it.
+/// Directories, files, hard links and relative symlinks are extracted;
+/// absolute symlinks, which would point into the host, and device files are
+/// skipped. Entries and whiteouts whose directory resolves outside the rootfs
+/// through a symlink are skipped as well.
+fn apply_layer(layer: &Path, rootfs: &Path) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(layer)?);
+    let gzipped = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+    let reader: Box<dyn Read> = if gzipped { Box::new(GzDecoder::new(reader)) } else { Box::new(reader) };
+    let mut archive = tar::Archive::new(reader);
+    // Entries of this layer, which its opaque whiteouts leave alone
+    let mut added: HashSet<PathBuf> = HashSet::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let Some(relative) = entry_path(&entry.path()?) else {
+            continue;
+        };
+        let Some(parent) = resolve(rootfs, relative.parent().unwrap_or(Path::new(""))) else {
+            continue;
+        };
+        let Some(name) = relative.file_name() else {
+            continue;
+        };
+        let file_name = name.to_str().unwrap_or("");
+        if file_name == OPAQUE {
+            for child in fs::read_dir(&parent).into_iter().flatten().filter_map(Result::ok) {
+                if !added.contains(&child.path()) {
+                    remove(&child.path());
+                }
+            }
+            continue;
+        }
+        if let Some(hidden) = file_name.strip_prefix(WHITEOUT) {
+            remove(&parent.join(hidden));
+            continue;
+        }
+        let target = parent.join(name);
+        let extract = match entry.header().entry_type() {
+            EntryType::Directory => {
+                // A directory replaces a file or symlink of the layers below
+                // instead of being created where that symlink points
+                if fs::symlink_metadata(&target).is_ok_and(|m| !m.is_dir()) {
+                    remove(&target);
+                }
+                fs::create_dir_all(&target)?;
+                false
+            }
+            EntryType::Regular | EntryType::Continuous | EntryType::Link => true,
+            EntryType::Symlink => entry.link_name()?.is_some_and(|l| l.is_relative()),
+            _ => false,
+        };
+        if extract {
+            fs::create_dir_all(&parent)?;
+            remove(&target);
+            let unpacked = if entry.header().entry_type() == EntryType::Link {
+                // Hard links name their source relative to the layer root
+                let source = entry.link_name()?.as_deref().and_then(entry_path).and_then(|source| {
+                    Some(resolve(rootfs, source.parent()?)?.join(source.file_name()?))
+                });
+                match source {
+                    Some(source) => fs::hard_link(source, &target),
+                    None => continue,
+                }
+            } else {
+                entry.unpack(&target).map(|_| ())
+            };
+            // A hard link to a file a whiteout removed is left out
+            if unpacked.is_err() {
+                continue;
+            }
+        }
+        added.insert(target);
+    }
+    Ok(())
+}
+
+/// Parents of the node_modules folders under `dir` that are not inside
+/// another node_modules.
+fn find_install_roots(dir: &Path, roots: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).into_iter().flatten().filter_map(Result::ok) {
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        if entry.file_name() == "node_modules" {
+            roots.push(dir.to_path_buf());
+        } else {
+            find_install_roots(&entry.path(), roots);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::{digest, package_lock, packages};
+use crate::registry::{Packument, is_from};
+use crate::yarn;
+
+/// Hosts serving the npm registry's tarballs. Entries resolved anywhere else
+/// (mirrors not configured in `.npmrc`, GitHub Packages, git, local paths)
+/// are not compared with it.
+const NPM_HOSTS: [&str; 2] = ["registry.npmjs.org", "registry.yarnpkg.com"];
+
+/// A package pinned by a lockfile together with the tarball it installs.
+pub struct Locked {
+    pub lockfile: &'static str,
+    pub name: String,
+    pub version: String,
+    /// Tarball URL, for yarn v1 with the SHA-1 as `#` fragment
+    pub resolved: Option<String>,
+    /// Subresource integrity string, possibly several space separated hashes
+    pub integrity: Option<String>,
+}
+
+impl Locked {
+    /// Whether the entry comes from the npm registry, or one of the
+    /// `registries` hosts configured in `.npmrc`, and has anything to compare
+    /// with it.
+    pub fn is_checkable(&self, registries: &[&str]) -> bool {
+        let from_npm = self
+            .resolved
+            .as_deref()
+            .is_none_or(|r| NPM_HOSTS.iter().chain(registries).any(|h| is_from(r, h)));
+        from_npm && (self.resolved.is_some() || self.integrity.is_some())
+    }
+}
+
+/// Entries of a package-lock.json or npm-shrinkwrap.json: the `packages` map
+/// of lockfile v2/v3, else the nested `dependencies` of v1. Links to
+/// workspaces and local folders are skipped.
+pub fn package_lock(lockfile: &'static str, lock: &Value) -> Vec<Locked> {
+    let mut locked: Vec<Locked> = Vec::new();
+    if let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) {
+        for (key, entry) in packages {
+            if entry.get("link").and_then(|l| l.as_bool()) == Some(true) {
+                continue;
+            }
+            if let Some(name) = package_lock::key_name(key, entry)
+                && let Some(version) = entry.get("version").and_then(|v| v.as_str())
+            {
+                locked.push(plock_entry(lockfile, name, version, entry));
+            }
+        }
+    } else if let Some(deps) = lock.get("dependencies").and_then(|d| d.as_object()) {
+        walk_v1(lockfile, deps, &mut locked);
+    }
+    locked
+}
+
+fn walk_v1(lockfile: &'static str, deps: &Map<String, Value>, locked: &mut Vec<Locked>) {
+    for (name, entry) in deps {
+        if let Some(version) = entry.get("version").and_then(|v| v.as_str()) {
+            // Aliases pin `npm:<real name>@<version>`
+            let (name, version) = packages::npm_alias(version).unwrap_or((name.as_str(), version));
+            locked.push(plock_entry(lockfile, name, version, entry));
+        }
+        if let Some(nested) = entry.get("dependencies").and_then(|d| d.as_object()) {
+            walk_v1(lockfile, nested, locked);
+        }
+    }
+}
+
+fn plock_entry(lockfile: &'static str, name: &str, version: &str, entry: &Value) -> Locked {
+    let field = |key: &str| entry.get(key).and_then(|f| f.as_str()).map(|f| f.to_string());
+    Locked {
+        lockfile,
+        name: name.to_string(),
+        version: version.to_string(),
+        resolved: field("resolved"),
+        integrity: field("integrity"),
+    }
+}
+
+/// Entries of a yarn.lock. Berry only records its own checksum of the cached
+/// zip, which the registry does not publish, so only v1 entries carry
+/// anything to compare.
+pub fn yarn(lock: &yarn::Lockfile) -> Vec<Locked> {
+    lock.entries
+        .iter()
+        .filter(|e| !e.is_workspace())
+        .map(|e| Locked {
+            lockfile: "yarn.lock",
+            name: e.resolved_name().to_string(),
+            version: e.version.clone(),
+            resolved: e.resolved.clone(),
+            integrity: e.integrity.clone(),
+        })
+        .collect()
+}
+
+/// How the tarball pinned by `locked` differs from what the registry
+/// publishes for that version, or None when it matches. Hashes are compared
+/// per algorithm present on both sides.
+pub fn mismatch(locked: &Locked, packument: &Packument) -> Option<String> {
+    let Some(dist) = packument.dist.get(&locked.version) else {
+        return Some(format!("{} is not published on the registry", locked.version));
+    };
+    if let (Some(resolved), Some(tarball)) = (&locked.resolved, &dist.tarball)
+        && tarball_url(resolved) != tarball_url(tarball)
+    {
+        return Some(format!("resolved {} instead of the registry tarball {}", resolved, tarball));
+    }
+    let mut pinned: Vec<(String, String)> = locked.integrity.as_deref().map(hashes).unwrap_or_default();
+    if let Some((_, sha1)) = locked.resolved.as_deref().and_then(|r| r.split_once('#')) {
+        pinned.push(("sha1".to_string(), sha1.to_ascii_lowercase()));
+    }
+    let mut published: Vec<(String, String)> = dist.integrity.as_deref().map(hashes).unwrap_or_default();
+    if let Some(shasum) = &dist.shasum {
+        published.push(("sha1".to_string(), shasum.to_ascii_lowercase()));
+    }
+    for (algorithm, hash) in &pinned {
+        let expected: Vec<&String> = published.iter().filter(|(a, _)| a == algorithm).map(|(_, h)| h).collect();
+        if let Some(first) = expected.first()
+            && !expected.contains(&hash)
+        {
+            return Some(format!(
+                "{} {}… differs from the registry's {}…",
+                algorithm,
+                &hash[..hash.len().min(12)],
+                &first[..first.len().min(12)]
+            ));
+        }
+    }
+    None
+}
+
+/// npm's cache directory: `npm_config_cache` when set, else `~/.npm`.
+pub fn npm_cache_dir() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("npm_config_cache").or_else(|_| env::var("NPM_CONFIG_CACHE")) {
+        return Some(PathBuf::from(dir));
+    }
+    env::var_os("HOME").map(|home| Path::new(&home).join(".npm"))
+}
+
+/// How the tarball npm cached for `locked` differs from the registry's
+/// SHA-512, or None when it matches. The cache is content addressed by the
+/// lockfile's SHA-512, so entries without one, or whose tarball is not
+/// cached, are not checked and give None as well.
+pub fn cache_mismatch(locked: &Locked, packument: &Packument, cache_dir: &Path) -> Option<String> {
+    let sha512 = |(algorithm, hash): &(String, String)| algorithm == "sha512" && hash.len() == 128;
+    let (_, pinned) = hashes(locked.integrity.as_deref()?).into_iter().find(sha512)?;
+    let path = cache_dir.join("_cacache/content-v2/sha512").join(&pinned[..2]).join(&pinned[2..4]).join(&pinned[4..]);
+    let tarball = fs::read(&path).ok()?;
+    let published = packument.dist.get(&locked.version)?.integrity.as_deref().map(hashes).unwrap_or_default();
+    let (_, expected) = published.into_iter().find(sha512)?;
+    let actual = digest::sha512(&tarball);
+    (actual != expected).then(|| {
+        format!("cached tarball {} has sha512 {}… instead of the registry's {}…", path.display(), &actual[..12], &expected[..12])
+    })
+}
+
+/// Tarball URL without the hash fragment, with yarn's registry alias and
+/// plain http mapped to the canonical npm URL.
+fn tarball_url(url: &str) -> String {
+    let url = url.split('#').next().unwrap_or(url);
+    url.replacen("http://", "https://", 1).replacen("registry.yarnpkg.com", "registry.npmjs.org", 1)
+}
+
+/// The hashes of an integrity string as (algorithm, lowercase hex).
+fn hashes(integrity: &str) -> Vec<(String, String)> {
+    integrity
+        .split_whitespace()
+        .filter_map(|h| h.split_once('-'))
+        // Options after `?` are not part of the digest
+        .filter_map(|(algorithm, digest)| Some((algorithm.to_string(), base64_hex(digest.split('?').next()?)?)))
+        .collect()
+}
+
+/// Decode standard base64 into lowercase hex.
+fn base64_hex(data: &str) -> Option<String> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut hex = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in data.trim_end_matches('=').bytes() {
+        let value = ALPHABET.iter().position(|&a| a == c)? as u32;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            hex.push_str(&format!("{:02x}", (buffer >> bits) & 0xff));
+        }
+    }
+    Some(hex)
+}
@@ -1,220 +1,1231 @@
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
 use std::fs::{self, File};
-use std::io::{self, BufRead, BufReader};
-use std::path::Path;
-use std::process::Command;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use rayon::prelude::*;
 use regex::Regex;
-use serde_json::Value;
-use walkdir::WalkDir;
+use serde_json::{Value, json};
+
+mod archive;
+mod audit;
+mod blame;
+mod bun;
+mod bundler;
+mod cargo;
+mod composer;
+mod config;
+mod db;
+mod deno;
+mod digest;
+mod ecosystem;
+mod evidence;
+mod footprint;
+mod ghsa;
+mod git;
+mod go;
+mod hooks;
+mod http;
+mod hygiene;
+mod image;
+mod integrity;
+mod match_type;
+mod matcher;
+mod maven;
+mod node_modules;
+mod npmrc;
+mod npm_audit;
+mod nuget;
+mod nx;
+mod osv;
+mod outdated;
+mod overrides;
+mod package_lock;
+mod packages;
+mod pnp;
+mod pnpm;
+mod push;
+mod registry;
+mod release_age;
+mod sbom;
+mod search;
+mod semver;
+mod severity;
+mod specifier;
+mod stale;
+mod tarball;
+mod telemetry;
+mod toolchain;
+mod trusted_lock;
+mod turbo;
+mod typosquat;
+mod verdict;
+mod workspaces;
+mod yarn;
+
+use audit::Audit;
+use ecosystem::Ecosystem;
+use match_type::{FailOn, MatchMode, MatchType};
+use matcher::Namespace;
+use npmrc::Npmrc;
+use packages::{Package, PackageList};
+use semver::{Range, parse_loose, parse_semver, parse_version, release};
+use severity::SeverityMap;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Config file with default options (default: package-checker.toml in the start path or a parent)
+    #[arg(long, env = "PACKAGE_CHECKER_CONFIG")]
+    config: Option<String>,
+
     /// Starting directory to check (default: .)
-    #[arg(long, default_value = ".")]
+    #[arg(long, default_value = ".", env = "PACKAGE_CHECKER_START_PATH")]
     start_path: String,
 
-    /// Package file to read (default: packages.txt)
-    #[arg(long, default_value = "packages.txt")]
-    package_file: String,
+    /// File with one start path per line, optionally followed by a label for the project column
+    #[arg(long, env = "PACKAGE_CHECKER_START_PATHS_FILE")]
+    start_paths_file: Option<String>,
+
+    /// Package file to read, can be given multiple times (default: packages.txt when present)
+    #[arg(long, env = "PACKAGE_CHECKER_PACKAGE_FILE", value_delimiter = ',')]
+    package_file: Vec<String>,
+
+    /// Packed npm package (.tgz) to check like a project, e.g. a release artifact before publishing it, can be given multiple times
+    #[arg(long, env = "PACKAGE_CHECKER_SCAN_TARBALL", value_delimiter = ',')]
+    scan_tarball: Vec<String>,
+
+    /// Source archive (.zip) whose projects are checked without extracting it, can be given multiple times
+    #[arg(long, env = "PACKAGE_CHECKER_SCAN_ARCHIVE", value_delimiter = ',')]
+    scan_archive: Vec<String>,
+
+    /// Do not check the .tgz files found in scanned projects and their dist, artifacts, release(s) and out folders
+    #[arg(long, env = "PACKAGE_CHECKER_NO_TARBALL_DISCOVERY")]
+    no_tarball_discovery: bool,
+
+    /// Check package.json and the npm, yarn and pnpm lockfiles as committed at this git revision instead of the working tree, e.g. origin/main
+    #[arg(long, env = "PACKAGE_CHECKER_GIT_REF")]
+    git_ref: Option<String>,
+
+    /// File of `name@version [path]` entries exempted from findings, can be given multiple times
+    #[arg(long, env = "PACKAGE_CHECKER_ALLOW_FILE", value_delimiter = ',')]
+    allow_file: Vec<String>,
+
+    /// Do not check the built-in list of known compromised packages
+    #[arg(long, env = "PACKAGE_CHECKER_NO_BUILTIN_DB")]
+    no_builtin_db: bool,
+
+    /// Extra HTTP header for package files given as a URL, e.g. "Authorization: Bearer <token>"
+    #[arg(long = "package-file-header", global = true, env = "PACKAGE_CHECKER_PACKAGE_FILE_HEADER")]
+    package_file_headers: Vec<String>,
+
+    /// Proxy for registry, advisory and package file requests, e.g. http://proxy.example.com:3128 (default: HTTPS_PROXY, HTTP_PROXY; NO_PROXY is honored)
+    #[arg(long, global = true, env = "PACKAGE_CHECKER_PROXY")]
+    proxy: Option<String>,
+
+    /// Also read the .npmrc of the start paths, with their registries and the credentials they take from the environment
+    #[arg(long, global = true, env = "PACKAGE_CHECKER_TRUST_PROJECT_NPMRC")]
+    trust_project_npmrc: bool,
+
+    /// Directory for cached downloads (default: ~/.cache/package-checker)
+    #[arg(long, global = true, env = "PACKAGE_CHECKER_CACHE_DIR")]
+    cache_dir: Option<String>,
+
+    /// Minutes registry and advisory responses are answered from the cache directory, 0 to always ask again
+    #[arg(long, global = true, default_value_t = 60, value_name = "MINUTES", env = "PACKAGE_CHECKER_CACHE_TTL")]
+    cache_ttl: u64,
+
+    /// Make no network requests: answer registry and advisory lookups from the cache directory, however old
+    #[arg(long, global = true, env = "PACKAGE_CHECKER_OFFLINE")]
+    offline: bool,
+
+    /// OSV advisory ID or package name to add to the check list, can be given multiple times
+    #[arg(long, env = "PACKAGE_CHECKER_OSV_QUERY", value_delimiter = ',')]
+    osv_query: Vec<String>,
+
+    /// GitHub advisory ID to add to the check list, can be given multiple times (uses GITHUB_TOKEN)
+    #[arg(long, env = "PACKAGE_CHECKER_GHSA_ID", value_delimiter = ',')]
+    ghsa_id: Vec<String>,
+
+    /// Vulnerability databases to look up every found package version in, reported alongside the check list
+    #[arg(long, env = "PACKAGE_CHECKER_AUDIT", value_enum, value_delimiter = ',')]
+    audit: Vec<Audit>,
+
+    /// Run npm audit in every npm project and report its advisories alongside the check list
+    #[arg(long, env = "PACKAGE_CHECKER_WITH_NPM_AUDIT")]
+    with_npm_audit: bool,
+
+    /// File mapping advisory score ranges and IDs to severity labels (default: CVSS ratings)
+    #[arg(long, env = "PACKAGE_CHECKER_SEVERITY_MAP")]
+    severity_map: Option<String>,
+
+    /// Ecosystem used for advisory queries and advisory JSON package files
+    #[arg(long, default_value = "npm", env = "PACKAGE_CHECKER_OSV_ECOSYSTEM")]
+    osv_ecosystem: String,
+
+    /// Only check projects whose package.json name matches, e.g. '@myorg/payments-*' or '/^@myorg\//'
+    #[arg(long, env = "PACKAGE_CHECKER_PROJECT_FILTER")]
+    project_filter: Option<String>,
+
+    /// Package ecosystems to scan: npm (package.json, its lockfiles and Deno projects), cargo (Cargo.toml, Cargo.lock), go (go.mod, go.sum), composer (composer.json, composer.lock), bundler (Gemfile.lock), maven (Gradle lockfiles, pom.xml) and nuget (packages.lock.json, project.assets.json)
+    #[arg(long, env = "PACKAGE_CHECKER_ECOSYSTEM", value_enum, value_delimiter = ',', default_values = ["npm"])]
+    ecosystem: Vec<Ecosystem>,
+
+    /// package.json sections whose entries are checked, also as dev, optional, peer and bundled
+    #[arg(
+        long,
+        env = "PACKAGE_CHECKER_SECTION",
+        value_delimiter = ',',
+        default_values = ["dependencies", "devDependencies", "optionalDependencies", "peerDependencies", "bundledDependencies"]
+    )]
+    section: Vec<String>,
+
+    /// package.json sections left out of --section, e.g. 'peer,bundled'
+    #[arg(long, env = "PACKAGE_CHECKER_SKIP_SECTION", value_delimiter = ',')]
+    skip_section: Vec<String>,
+
+    /// Only scan directories whose path relative to the start path matches, e.g. 'packages/*', can be repeated
+    #[arg(long, env = "PACKAGE_CHECKER_INCLUDE", value_delimiter = ',')]
+    include: Vec<String>,
+
+    /// Skip directories whose path relative to the start path matches, e.g. '**/examples/**', can be repeated
+    #[arg(long, env = "PACKAGE_CHECKER_EXCLUDE", value_delimiter = ',')]
+    exclude: Vec<String>,
+
+    /// Also scan paths excluded by .gitignore, .ignore and git exclude files
+    #[arg(long, env = "PACKAGE_CHECKER_NO_IGNORE")]
+    no_ignore: bool,
 
     /// Only check the start directory
-    #[arg(long)]
+    #[arg(long, env = "PACKAGE_CHECKER_ROOT_ONLY")]
     root_only: bool,
 
+    /// Only scan the projects of the Nx workspace at the start path, read from its project.json and package.json files
+    #[arg(long, env = "PACKAGE_CHECKER_NX")]
+    nx: bool,
+
+    /// Only scan the Nx projects affected by the changes since --base and the projects depending on them, implies --nx
+    #[arg(long, env = "PACKAGE_CHECKER_NX_AFFECTED")]
+    nx_affected: bool,
+
+    /// Git ref --nx-affected compares the working tree against
+    #[arg(long, env = "PACKAGE_CHECKER_BASE", default_value = "main")]
+    base: String,
+
+    /// Only scan the packages of the Turborepo at the start path: its workspace root and members
+    #[arg(long, env = "PACKAGE_CHECKER_TURBO")]
+    turbo: bool,
+
+    /// Turbo filter selecting the packages to scan, e.g. 'web...' or '...[origin/main]', can be repeated, implies --turbo
+    #[arg(long, env = "PACKAGE_CHECKER_TURBO_FILTER")]
+    turbo_filter: Vec<String>,
+
+    /// Exit with an error when part of the tree cannot be read
+    #[arg(long, env = "PACKAGE_CHECKER_FAIL_ON_UNREADABLE")]
+    fail_on_unreadable: bool,
+
+    /// Exit with an error on findings: 'locked' for versions pinned in a lockfile or installed, 'any' for declared ranges as well
+    #[arg(long, env = "PACKAGE_CHECKER_FAIL_ON", value_enum)]
+    fail_on: Option<FailOn>,
+
     /// Only list directories to be checked
-    #[arg(long)]
+    #[arg(long, env = "PACKAGE_CHECKER_LIST_DIRS")]
     list_dirs: bool,
 
     /// Number of worker threads to use
-    #[arg(short = 'j', long, default_value_t = num_cpus::get())]
+    #[arg(short = 'j', long, default_value_t = num_cpus::get(), env = "PACKAGE_CHECKER_JOBS")]
     jobs: usize,
 
-    /// Skip calling npm (fast)
-    #[arg(long = "no-npm")]
+    /// Skip checking installed packages in node_modules (fast)
+    #[arg(long = "no-npm", env = "PACKAGE_CHECKER_NO_NPM")]
     no_npm: bool,
 
+    /// Ask npm for installed packages (npm sbom, else npm ls) instead of reading node_modules
+    #[arg(long, env = "PACKAGE_CHECKER_USE_NPM")]
+    use_npm: bool,
+
+    /// Ask Maven for the artifacts of pom.xml projects without a dependencies-lock.json (mvn dependency:list)
+    #[arg(long, env = "PACKAGE_CHECKER_USE_MVN")]
+    use_mvn: bool,
+
+    /// Do not read CycloneDX and SPDX SBOM files (bom.json, *.cdx.json, *.spdx.json) as a version source
+    #[arg(long = "no-sbom", env = "PACKAGE_CHECKER_NO_SBOM")]
+    no_sbom: bool,
+
+    /// With --use-npm, use npm ls per package instead of a single npm sbom run
+    #[arg(long = "no-npm-sbom", env = "PACKAGE_CHECKER_NO_NPM_SBOM")]
+    no_npm_sbom: bool,
+
+    /// Resolve direct dependency ranges against the npm registry and compare with the lockfile pins
+    #[arg(long, env = "PACKAGE_CHECKER_FRESH_RESOLVE")]
+    fresh_resolve: bool,
+
+    /// Let ranges match prereleases of any version, not only those naming a prerelease of the same version
+    #[arg(long, env = "PACKAGE_CHECKER_INCLUDE_PRERELEASE")]
+    include_prerelease: bool,
+
+    /// How a found version has to relate to a listed entry: 'exact' pinned or installed versions only, 'range' for declared ranges admitting a listed version as well, 'major' for any version on the same major line
+    #[arg(long, env = "PACKAGE_CHECKER_MATCH_MODE", value_enum)]
+    match_mode: Option<MatchMode>,
+
+    /// Read versions that are not valid semver leniently, such as 1.2.3.4, 2.0.0.Final or 1.2.3beta, instead of leaving them unmatched
+    #[arg(long, env = "PACKAGE_CHECKER_LOOSE_VERSIONS")]
+    loose_versions: bool,
+
+    /// Look up dist-tags such as latest or next in the npm registry and check dependencies at the version they point at
+    #[arg(long, env = "PACKAGE_CHECKER_RESOLVE_DIST_TAGS")]
+    resolve_dist_tags: bool,
+
+    /// Fetch git dependencies and check them at the version in their package.json
+    #[arg(long, env = "PACKAGE_CHECKER_RESOLVE_GIT_DEPS")]
+    resolve_git_deps: bool,
+
+    /// Compare the integrity hashes and tarball URLs in package-lock.json and yarn.lock with the npm registry
+    #[arg(long, env = "PACKAGE_CHECKER_VERIFY_INTEGRITY")]
+    verify_integrity: bool,
+
+    /// Also hash the tarballs the lockfiles pin in the npm cache and compare them with the registry (implies --verify-integrity)
+    #[arg(long, env = "PACKAGE_CHECKER_VERIFY_CACHED_TARBALLS")]
+    verify_cached_tarballs: bool,
+
+    /// Look up every locked and installed npm package version in the registry and report the deprecated ones
+    #[arg(long, env = "PACKAGE_CHECKER_CHECK_DEPRECATED")]
+    check_deprecated: bool,
+
+    /// Exit with 1 when deprecated versions are found (implies --check-deprecated)
+    #[arg(long, env = "PACKAGE_CHECKER_FAIL_ON_DEPRECATED")]
+    fail_on_deprecated: bool,
+
+    /// Flag locked and installed npm package versions published less than this many days ago, whether listed or not
+    #[arg(long, value_name = "DAYS", env = "PACKAGE_CHECKER_MAX_RELEASE_AGE_CHECK")]
+    max_release_age_check: Option<u64>,
+
+    /// Warn about locked and installed npm package versions whose maintainers or publisher differ from the release before
+    #[arg(long, env = "PACKAGE_CHECKER_CHECK_MAINTAINERS")]
+    check_maintainers: bool,
+
+    /// Report npm dependencies whose names look like a misspelling or imitation of a popular package
+    #[arg(long, env = "PACKAGE_CHECKER_DETECT_TYPOSQUATS")]
+    detect_typosquats: bool,
+
+    /// Report package.json dependencies missing from the lockfiles or locked outside their declared range
+    #[arg(long, env = "PACKAGE_CHECKER_CHECK_LOCKFILE")]
+    check_lockfile: bool,
+
+    /// Reviewed lockfile, or a git ref to read the lockfiles from, to compare the current lockfiles with
+    #[arg(long, env = "PACKAGE_CHECKER_TRUSTED_LOCK")]
+    trusted_lock: Option<String>,
+
+    /// Record install path, integrity, tarball and files of installed flagged packages in output.meta.json
+    #[arg(long, env = "PACKAGE_CHECKER_EVIDENCE")]
+    evidence: bool,
+
+    /// Record the commit and author that introduced each flagged version into package.json or a lockfile in output.meta.json
+    #[arg(long, env = "PACKAGE_CHECKER_BLAME")]
+    blame: bool,
+
+    /// Add the installed size and transitive dependency count of direct dependencies to output.csv
+    #[arg(long, env = "PACKAGE_CHECKER_FOOTPRINT")]
+    footprint: bool,
+
+    /// Previous output.csv, findings in it that no list entry covers anymore are written to output.stale.csv
+    #[arg(long, env = "PACKAGE_CHECKER_PREVIOUS_REPORT")]
+    previous_report: Option<String>,
+
+    /// Keep at most N rows per severity in output.csv, findings first; critical rows are always kept
+    #[arg(long, env = "PACKAGE_CHECKER_MAX_ROWS_PER_SEVERITY")]
+    max_rows_per_severity: Option<usize>,
+
+    /// Write a package-checker.findings.json next to each package.json with findings
+    #[arg(long, env = "PACKAGE_CHECKER_WRITE_SIDECARS")]
+    write_sidecars: bool,
+
+    /// Write a clean / findings / errors / skipped verdict with the hashes of its input files for every directory to output.verdicts.json
+    #[arg(long, env = "PACKAGE_CHECKER_VERDICTS")]
+    verdicts: bool,
+
+    /// Post-scan hooks from the `[[hook]]` entries of the config file
+    #[arg(skip)]
+    hooks: Vec<config::Hook>,
+
+    /// Send anonymous aggregate scan statistics (no package names or paths)
+    #[arg(long, env = "PACKAGE_CHECKER_TELEMETRY")]
+    telemetry: bool,
+
+    /// Endpoint receiving the --telemetry statistics as a JSON POST
+    #[arg(long, env = "PACKAGE_CHECKER_TELEMETRY_ENDPOINT")]
+    telemetry_endpoint: Option<String>,
+
     /// Verbose logging (debug)
-    #[arg(short, long)]
+    #[arg(short, long, env = "PACKAGE_CHECKER_VERBOSE")]
     verbose: bool,
 }
 
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Refresh the built-in list of known compromised packages
+    UpdateDb {
+        /// Feed to download the list from
+        #[arg(long, env = "PACKAGE_CHECKER_DB_URL", default_value = db::DEFAULT_FEED)]
+        url: String,
+    },
+    /// Upload a finished report (output.csv, output.meta.json, ...) to a collection endpoint
+    Push {
+        /// Report file to upload
+        report: PathBuf,
+
+        /// URL the report is POSTed to
+        #[arg(long, env = "PACKAGE_CHECKER_PUSH_ENDPOINT")]
+        endpoint: String,
+
+        /// Bearer token sent in the Authorization header
+        #[arg(long, env = "PACKAGE_CHECKER_PUSH_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+
+        /// Extra header sent with the upload, e.g. 'X-Team: payments' (can be repeated)
+        #[arg(long = "header", env = "PACKAGE_CHECKER_PUSH_HEADER", hide_env_values = true)]
+        headers: Vec<String>,
+
+        /// Number of times a failed upload is retried
+        #[arg(long, default_value_t = 3, env = "PACKAGE_CHECKER_PUSH_RETRIES")]
+        retries: u32,
+    },
+    /// List the packages whose name contains one of the terms, e.g. 'eslint-config', with their versions and locations
+    Search {
+        /// Part of a package name to look for, can be given multiple times
+        #[arg(required = true)]
+        terms: Vec<String>,
+
+        /// Search a previous output.csv instead of scanning the start paths
+        #[arg(long, env = "PACKAGE_CHECKER_SEARCH_REPORT")]
+        report: Option<PathBuf>,
+    },
+    /// Report how far the direct dependencies of the scanned projects are behind their latest release in the npm registry
+    Outdated,
+    /// Scan the filesystem of a container image instead of the start paths
+    Image {
+        /// Image reference saved with docker (or podman), e.g. node:20-alpine, or a docker save or OCI layout tarball
+        image: String,
+    },
+    /// Shallow clone a git repository into a temporary directory and scan it instead of the start paths
+    ScanRemote {
+        /// Repository URL, e.g. https://github.com/org/repo.git
+        url: String,
+
+        /// Branch, tag or commit to check out (default: the default branch)
+        #[arg(long = "ref", env = "PACKAGE_CHECKER_SCAN_REMOTE_REF")]
+        git_ref: Option<String>,
+    },
+}
+
+struct Row {
+    package: String,
+    version: String,
+    location: String,
+    match_package: bool,
+    match_version: bool,
+    dependency: String,
+    depended_by: String,
+    source: String,
+    reason: String,
+    project: String,
+    allowed: String,
+    severity: String,
+    /// Installed size in bytes of a direct dependency, with `--footprint`
+    installed_size: Option<u64>,
+    /// Packages a direct dependency pulls in, with `--footprint`
+    transitive_deps: Option<usize>,
+    /// package.json section a direct dependency is declared in
+    section: String,
+    /// Workspace members a match found through a workspace root's lockfile
+    /// is attributed to
+    workspace: String,
+    /// Whether the version was pinned or declared, shown for matches
+    match_type: MatchType,
+}
+
+/// A tree to scan and the label reported for it in the project column.
+struct StartPath {
+    path: PathBuf,
+    label: String,
+}
+
+/// Read a `--start-paths-file`: one path per line, optionally followed by
+/// whitespace and a label. Blank lines and lines starting with `#` are
+/// skipped. Paths without a label are reported under the path itself.
+fn read_start_paths(file: &Path) -> io::Result<Vec<StartPath>> {
+    let content = fs::read_to_string(file)?;
+    let mut roots: Vec<StartPath> = Vec::new();
+    for line in content.lines() {
+        let l = line.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let (path, label) = match l.split_once(char::is_whitespace) {
+            Some((path, label)) => (path, label.trim()),
+            None => (l, l),
+        };
+        roots.push(StartPath {
+            path: PathBuf::from(path),
+            label: label.to_string(),
+        });
+    }
+    Ok(roots)
+}
+
 struct Preload {
-    yarn: Option<String>,
+    yarn: Option<yarn::Lockfile>,
     plock: Option<Value>,
-    pnpm: Option<String>,
+    /// `package-lock.json` or `npm-shrinkwrap.json`, whichever `plock` was read from
+    plock_file: &'static str,
+    pnpm: Option<pnpm::Lockfile>,
+    bun: Option<bun::Lockfile>,
+    deno: Option<deno::Project>,
+    cargo: Option<cargo::Project>,
+    go: Option<go::Module>,
+    composer: Option<composer::Project>,
+    bundler: Option<bundler::Bundle>,
+    maven: Option<maven::Project>,
+    nuget: Option<nuget::Project>,
+    /// CycloneDX and SPDX SBOMs, an extra version source for any ecosystem
+    sboms: Vec<sbom::Sbom>,
     deps: Option<String>,
     pkg_json: Option<Value>,
+    /// package.json sections checked in this directory
+    sections: Vec<String>,
+}
+
+/// How found versions are matched against the package list entries.
+#[derive(Clone, Copy)]
+struct Matching<'a> {
+    /// Ranges admit every prerelease in them
+    include_prerelease: bool,
+    /// Versions that are not valid semver are parsed with `parse_loose`
+    loose: bool,
+    /// `--match-mode`, None to check declared ranges at their lowest version
+    mode: Option<MatchMode>,
+    /// Found versions that could not be parsed, reported after the scan
+    unparseable: &'a Mutex<BTreeSet<String>>,
+}
+
+impl Matching<'_> {
+    /// Whether a version of this match type can match at all: the lowest
+    /// version of a requirement is no match in exact mode.
+    fn admits(&self, match_type: MatchType) -> bool {
+        self.mode != Some(MatchMode::Exact) || match_type == MatchType::Locked
+    }
+}
+
+/// Check a found version against a package list entry, which can be an exact
+/// version or a semver range such as `>=4.17.0 <4.17.21` or `1.x`. The
+/// leading `v` of Go versions is ignored. A prerelease such as `5.0.0-rc.1`
+/// only matches a range naming a prerelease of `5.0.0`, unless
+/// `include_prerelease` is set. Versions such as `1.2.3.4` only match an
+/// exact entry, unless they are parsed loosely.
+fn satisfies_range(version: &str, range: &str, matching: Matching) -> bool {
+    // Wildcard entries match any presence, including specs that are not a version
+    if range == packages::ANY_VERSION {
+        return true;
+    }
+    let bare = version.trim_start_matches('v');
+    let lowest = version.trim_start_matches('^').trim_start_matches('~').trim_start_matches('v');
+    if lowest == range.trim_start_matches('v') && (bare == lowest || matching.mode != Some(MatchMode::Exact)) {
+        return true;
+    }
+    // A Go pseudo-version also matches the commit it was made from
+    if go::same_revision(lowest, range) {
+        return true;
+    }
+    let Some(listed) = Range::parse(range) else {
+        return false;
+    };
+    // A declared range that is not a plain version, such as `^1.2.0`,
+    // `>=1.2.0 <2` or `1.2.x`, is checked at the lowest version it admits
+    let is_plain = release(lowest).is_some() && !lowest.contains(char::is_whitespace) && !lowest.contains("||");
+    let is_pinned = is_plain && bare == lowest;
+    let declared = if is_pinned { None } else { Range::parse(version).filter(|_| !version.is_empty()) };
+    match matching.mode {
+        Some(MatchMode::Exact) if !is_pinned => return false,
+        Some(MatchMode::Range) if !is_pinned => return declared.is_some_and(|d| d.intersects(&listed)),
+        _ => {}
+    }
+    let v = if is_plain {
+        parse_semver(lowest).or_else(|| if matching.loose { parse_loose(lowest) } else { None })
+    } else {
+        declared.and_then(|d| d.min_version())
+    };
+    let Some(v) = v else {
+        if is_plain {
+            matching.unparseable.lock().unwrap().insert(lowest.to_string());
+        }
+        return false;
+    };
+    if matching.mode == Some(MatchMode::Major) {
+        let (major, _, _) = v.release;
+        return Range::parse(&format!(">={}.0.0-0 <{}.0.0-0", major, major + 1)).is_some_and(|line| line.intersects(&listed));
+    }
+    listed.matches_semver(&v, matching.include_prerelease)
+}
+
+/// Severity label of a match from the advisories of the listed entries.
+fn severity_label<'a>(map: &SeverityMap, entries: impl Iterator<Item = &'a Package>) -> String {
+    let entries: Vec<&Package> = entries.collect();
+    let score = entries.iter().filter_map(|p| p.score).reduce(f32::max);
+    map.label(entries.iter().flat_map(|p| p.reasons.iter().map(|r| r.as_str())), score)
+        .unwrap_or_default()
+}
+
+/// Reason of the exemption covering `name` at `version` in `location`, if any.
+fn exemption_reason(packages: &PackageList, name: &str, version: &str, location: &str, matching: Matching) -> Option<String> {
+    packages
+        .exemptions()
+        .iter()
+        .find(|e| e.matches(name, location) && satisfies_range(version, &e.version, Matching { mode: None, ..matching }))
+        .map(|e| e.reason.clone())
+}
+
+/// What the projects are checked against.
+#[derive(Clone, Copy)]
+struct Checks<'a> {
+    packages: &'a PackageList,
+    matching: Matching<'a>,
+    severity_map: &'a SeverityMap,
+}
+
+/// A package one of the checks run before the scan, such as `--trusted-lock`
+/// or `--detect-typosquats`, calls out whether it is listed or not.
+struct Finding<'a> {
+    name: &'a str,
+    /// Versions it was found at, the row matches when any of them does
+    versions: &'a [String],
+    location: &'a str,
+    project: &'a str,
+    /// The check, shown in the dependency column
+    check: &'static str,
+    reason: String,
+    match_type: MatchType,
+}
+
+/// A dependency of a Cargo, Go, Composer, Bundler or NuGet project, at its
+/// locked version or the lowest version its requirement admits, or a Deno
+/// import at its declared range.
+struct Dependency<'a> {
+    name: &'a str,
+    /// Checked as given, shown without a leading `^` or `~`
+    version: &'a str,
+    match_type: MatchType,
+    location: &'a str,
+    project: &'a str,
+    /// `yes`, `indirect` or the kind of dependency, such as `dev`
+    kind: &'a str,
+    depended_by: &'a str,
+    section: &'a str,
 }
 
-fn parse_version(v: &str) -> Option<(i32, i32, i32)> {
-    let re = Regex::new(r"^\d+\.\d+\.\d+").unwrap();
-    re.captures(v).map(|cap| {
-        let parts: Vec<i32> = cap[0]
-            .split('.')
-            .map(|s| s.parse().unwrap_or(0))
+impl Checks<'_> {
+    /// The row of a finding, with the package list entries its versions match.
+    fn finding_row(&self, finding: Finding) -> Row {
+        let flagged: Vec<&Package> = self
+            .packages
+            .iter()
+            .filter(|p| p.matches(finding.name) && finding.versions.iter().any(|v| satisfies_range(v, &p.version, self.matching)))
             .collect();
-        (parts[0], parts[1], parts[2])
+        Row {
+            package: finding.name.to_string(),
+            version: finding.versions.join(" "),
+            location: finding.location.to_string(),
+            match_package: self.packages.has_name(finding.name),
+            match_version: !flagged.is_empty(),
+            dependency: finding.check.to_string(),
+            depended_by: String::new(),
+            source: packages::join_sources(flagged.iter().copied()),
+            reason: finding.reason,
+            project: finding.project.to_string(),
+            allowed: String::new(),
+            severity: severity_label(self.severity_map, flagged.iter().copied()),
+            installed_size: None,
+            transitive_deps: None,
+            section: String::new(),
+            workspace: String::new(),
+            match_type: finding.match_type,
+        }
+    }
+}
+
+/// Add the row of a dependency, and a finding when a listed version matches
+/// and no exemption covers it.
+fn push_dependency_row(checks: Checks, dependency: Dependency, rows: &Mutex<Vec<Row>>, found: &Mutex<Vec<(String, MatchType)>>) {
+    let Checks {
+        packages,
+        matching,
+        severity_map,
+    } = checks;
+    let Dependency { name, version, match_type, location, .. } = dependency;
+    let shown = version.trim_start_matches('^').trim_start_matches('~');
+    let match_package = packages.has_name(name);
+    let mut matched: Vec<&Package> = packages
+        .iter()
+        .filter(|p| p.matches(name) && matching.admits(match_type) && satisfies_range(version, &p.version, matching))
+        .collect();
+    let match_version = !matched.is_empty();
+    if !match_version {
+        matched = packages.iter().filter(|p| p.matches(name)).collect();
+    }
+    let allowed = match_version
+        .then(|| exemption_reason(packages, name, version, location, matching))
+        .flatten()
+        .unwrap_or_default();
+
+    if match_package && match_version && allowed.is_empty() {
+        found.lock().unwrap().push((format!("{}:{}@{}", location, name, shown), match_type));
+    }
+    rows.lock().unwrap().push(Row {
+        package: name.to_string(),
+        version: shown.to_string(),
+        location: location.to_string(),
+        match_package,
+        match_version,
+        dependency: dependency.kind.to_string(),
+        depended_by: dependency.depended_by.to_string(),
+        source: packages::join_sources(matched.iter().copied()),
+        reason: packages::join_reasons(matched.iter().copied()),
+        project: dependency.project.to_string(),
+        allowed,
+        severity: severity_label(severity_map, matched.iter().copied()),
+        installed_size: None,
+        transitive_deps: None,
+        section: dependency.section.to_string(),
+        workspace: String::new(),
+        match_type,
+    });
+}
+
+/// Ignore file with gitignore-style patterns, read from the start path.
+const IGNORE_FILE: &str = ".packagecheckerignore";
+
+/// Build the ignore rules for a start path from the Nx cache directory its
+/// nx.json configures and its `.packagecheckerignore`.
+fn load_ignore(root: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for dir in nx::cache_directories(root) {
+        let _ = builder.add_line(None, &format!("/{}/", dir));
+    }
+    let ignore_path = root.join(IGNORE_FILE);
+    if ignore_path.is_file()
+        && let Some(e) = builder.add(&ignore_path)
+    {
+        eprintln!("[warning] Problem reading {}: {}", ignore_path.display(), e);
+    }
+    builder.build().unwrap_or_else(|e| {
+        eprintln!("[warning] Ignoring {}: {}", ignore_path.display(), e);
+        Gitignore::empty()
     })
 }
 
-fn satisfies_range(version: &str, range: &str) -> bool {
-    let version = version.trim_start_matches('^').trim_start_matches('~');
-    if let Some((v_major, v_minor, v_patch)) = parse_version(version) {
-        if range.starts_with('^') {
-            let range_version = range.trim_start_matches('^');
-            if let Some((r_major, r_minor, _)) = parse_version(range_version) {
-                v_major == r_major && (v_minor > r_minor || (v_minor == r_minor && v_patch >= 0))
-            } else {
-                false
-            }
-        } else if range.starts_with('~') {
-            let range_version = range.trim_start_matches('~');
-            if let Some((r_major, r_minor, r_patch)) = parse_version(range_version) {
-                v_major == r_major && v_minor == r_minor && v_patch >= r_patch
-            } else {
-                false
-            }
-        } else {
-            version == range
+/// Path an error of the ignore walker is about, if it records one.
+fn walk_error_path(err: &ignore::Error) -> Option<&Path> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => walk_error_path(err),
+        _ => None,
+    }
+}
+
+/// Which directories `find_dirs` returns.
+struct Discovery {
+    /// Files marking a project of the selected ecosystems, see
+    /// `ecosystem::is_project_file`
+    files: Vec<&'static str>,
+    root_only: bool,
+    /// Also walk paths excluded by `.gitignore`, `.ignore` and git exclude files
+    no_ignore: bool,
+    /// Globs on the path relative to the start path, directories matching none
+    /// of them are skipped
+    include: Option<GlobSet>,
+    /// Globs on the relative path of directories pruned from the walk
+    exclude: GlobSet,
+}
+
+/// Compile `--include` / `--exclude` patterns, where `*` stays within one
+/// path component and `**` crosses them.
+fn build_globs(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(GlobBuilder::new(pattern.trim_end_matches('/')).literal_separator(true).build()?);
+    }
+    builder.build()
+}
+
+/// Path of `path` relative to `root` for matching globs, `.` for the root.
+fn relative_path(root: &Path, path: &Path) -> PathBuf {
+    match path.strip_prefix(root) {
+        Ok(rel) if rel.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(rel) => rel.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Projects committed at `rev` under `root`, labelled like `find_dirs` does,
+/// with the `tarball::FILES` each holds as (file name, content). The
+/// discovery globs apply as to the working tree; ignore files do not, since
+/// nothing ignored is committed.
+fn git_tree_dirs(root: &Path, rev: &str, discovery: &Discovery) -> io::Result<BTreeMap<String, Vec<(String, String)>>> {
+    let mut dirs: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    for (path, content) in git::tree_files(root, rev, &tarball::FILES)? {
+        let parent = path.parent().unwrap_or(Path::new(""));
+        let relative = relative_path(Path::new(""), parent);
+        let excluded = relative.ancestors().any(|a| !a.as_os_str().is_empty() && a != Path::new(".") && discovery.exclude.is_match(a));
+        if excluded
+            || discovery.root_only && !parent.as_os_str().is_empty()
+            || discovery.include.as_ref().is_some_and(|i| !i.is_match(&relative))
+        {
+            continue;
         }
-    } else {
-        false
+        let dir = if parent.as_os_str().is_empty() { root.to_path_buf() } else { root.join(parent) };
+        let file_name = path.file_name().and_then(|f| f.to_str()).unwrap_or("").to_string();
+        dirs.entry(dir.to_str().unwrap_or(".").to_string()).or_default().push((file_name, content));
     }
+    for files in dirs.values_mut() {
+        files.sort_by_key(|(file, _)| tarball::FILES.iter().position(|f| f == file));
+    }
+    Ok(dirs)
 }
 
-fn find_dirs(root: &Path, root_only: bool) -> Vec<String> {
-    let patterns = vec!["package.json"];
-    let exclude_dirs = vec![".nx"];
+/// Find directories containing a project file of the selected ecosystems,
+/// such as a package.json or Cargo.toml. Paths that could not be read
+/// because of missing permissions are returned separately so they can be
+/// reported instead of silently shrinking the scan. Unless `no_ignore` is set,
+/// anything excluded by `.gitignore`, `.ignore` or git's exclude files is
+/// skipped as well.
+fn find_dirs(root: &Path, discovery: &Discovery) -> (Vec<String>, Vec<PathBuf>) {
+    let patterns = &discovery.files;
+    let ignore = load_ignore(root);
     let mut dirs: HashSet<String> = HashSet::new();
+    let mut unreadable: Vec<PathBuf> = Vec::new();
 
-    for entry in WalkDir::new(root)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| {
-            !e.path()
-                .components()
-                .any(|c| exclude_dirs.contains(&c.as_os_str().to_str().unwrap_or("")))
+    let exclude = discovery.exclude.clone();
+    let walk_root = root.to_path_buf();
+    let walker = WalkBuilder::new(root)
+        .standard_filters(!discovery.no_ignore)
+        .hidden(false)
+        .max_depth(discovery.root_only.then_some(0))
+        .filter_entry(move |e| {
+            let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+            let matched = ignore.matched(e.path(), is_dir);
+            // `.nx` folders are skipped unless the ignore file re-includes them with `!`
+            e.depth() == 0
+                || !(matched.is_ignore()
+                    || is_dir && !matched.is_whitelist() && nx::is_cache_dir(e.path())
+                    || is_dir && exclude.is_match(relative_path(&walk_root, e.path())))
         })
-    {
-        if entry.file_type().is_file() {
+        .build();
+    for entry in walker.filter_map(|e| match e {
+        Ok(entry) => Some(entry),
+        Err(err) => {
+            if err.io_error().is_some_and(|io| io.kind() == io::ErrorKind::PermissionDenied) {
+                unreadable.push(walk_error_path(&err).unwrap_or(root).to_path_buf());
+            }
+            None
+        }
+    }) {
+        if entry.file_type().is_some_and(|t| t.is_file()) {
             let file_name = entry.file_name().to_str().unwrap_or("");
-            if patterns.contains(&file_name) {
-                if let Some(parent) = entry.path().parent() {
-                    let dir_str = parent.to_str().unwrap_or(".").to_string();
-                    dirs.insert(dir_str);
-                }
+            if ecosystem::is_project_file(patterns, file_name)
+                && let Some(parent) = entry.path().parent()
+                && discovery.include.as_ref().is_none_or(|i| i.is_match(relative_path(root, parent)))
+            {
+                let dir_str = parent.to_str().unwrap_or(".").to_string();
+                dirs.insert(dir_str);
             }
         }
     }
 
-    if root_only {
-        let root_str = root.to_str().unwrap_or(".").to_string();
-        let root_path = Path::new(&root_str);
-        let has_relevant_file = patterns.iter().any(|p| root_path.join(p).is_file());
-        if has_relevant_file {
-            dirs.insert(root_str);
+    // The root itself, the walk only finds its project files when it is readable
+    let root_str = root.to_str().unwrap_or(".").to_string();
+    let has_relevant_file = fs::read_dir(root).into_iter().flatten().filter_map(Result::ok).any(|e| {
+        e.path().is_file() && e.file_name().to_str().is_some_and(|f| ecosystem::is_project_file(patterns, f))
+    });
+    if has_relevant_file && discovery.include.as_ref().is_none_or(|i| i.is_match(".")) {
+        dirs.insert(root_str);
+    }
+
+    let mut sorted_dirs: Vec<String> = dirs.into_iter().collect();
+    sorted_dirs.sort();
+    unreadable.sort();
+    (sorted_dirs, unreadable)
+}
+
+/// Narrow the directories found under `root` to the projects of its Nx
+/// workspace, or with `base` to the projects affected since that ref. The
+/// workspace root holds the lockfile and is kept unless the affected projects
+/// all install through a package.json of their own.
+fn nx_scope(root: &Path, found: Vec<String>, base: Option<&str>, verbose: bool) -> Vec<String> {
+    if !root.join("nx.json").is_file() {
+        eprintln!("[warning] {} has no nx.json, scanning all of its projects", root.display());
+        return found;
+    }
+    let canonical_root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let projects = nx::projects(root);
+    let (keep_root, selected) = match base.map(|b| nx::affected(root, &projects, b)) {
+        None => (true, projects.iter().collect()),
+        Some(Ok((global, affected))) => {
+            eprintln!(
+                "{} of {} Nx projects affected since {}{}",
+                affected.len(),
+                projects.len(),
+                base.unwrap_or_default(),
+                if global { " (a change outside the projects affects all of them)" } else { "" }
+            );
+            let through_root = affected.iter().any(|p| !p.has_manifest() || p.root == canonical_root);
+            (global || through_root, affected)
         }
+        Some(Err(e)) => {
+            eprintln!("[warning] Could not determine the affected Nx projects, scanning all of them: {}", e);
+            (true, projects.iter().collect())
+        }
+    };
+    let roots: HashSet<PathBuf> = selected.iter().map(|p| p.root.clone()).collect();
+    let total = found.len();
+    let kept: Vec<String> = found
+        .into_iter()
+        .filter(|d| {
+            Path::new(d)
+                .canonicalize()
+                .is_ok_and(|dir| roots.contains(&dir) || keep_root && dir == canonical_root)
+        })
+        .collect();
+    if verbose {
+        eprintln!("[debug] Nx scope keeps {} of {} directories under {}", kept.len(), total, root.display());
+    }
+    kept
+}
+
+/// Narrow the directories found under `root` to the packages of its
+/// Turborepo, or to the ones `filters` select.
+fn turbo_scope(root: &Path, found: Vec<String>, filters: &[String], verbose: bool) -> io::Result<Vec<String>> {
+    if !turbo::is_repo(root) {
+        eprintln!("[warning] {} has no turbo.json, scanning all of its projects", root.display());
+        return Ok(found);
+    }
+    let packages = turbo::packages(root);
+    let selected = if filters.is_empty() {
+        packages.iter().collect()
     } else {
-        let root_str = root.to_str().unwrap_or(".").to_string();
-        let root_path = Path::new(&root_str);
-        let has_relevant_file = patterns.iter().any(|p| root_path.join(p).is_file());
-        if has_relevant_file {
-            dirs.insert(root_str);
+        let selected = turbo::select(root, &packages, filters)?;
+        let names: Vec<&str> = selected.iter().map(|p| p.name.as_str()).collect();
+        eprintln!("{} of {} turbo packages selected: {}", selected.len(), packages.len(), names.join(", "));
+        selected
+    };
+    let roots: HashSet<&Path> = selected.iter().map(|p| p.root.as_path()).collect();
+    let total = found.len();
+    let kept: Vec<String> = found
+        .into_iter()
+        .filter(|d| Path::new(d).canonicalize().is_ok_and(|dir| roots.contains(dir.as_path())))
+        .collect();
+    if verbose {
+        eprintln!("[debug] Turbo scope keeps {} of {} directories under {}", kept.len(), total, root.display());
+    }
+    Ok(kept)
+}
+
+/// Print unreadable paths grouped by the top-level subtree of `root` they are
+/// in, with a count and a few example paths per subtree.
+fn report_unreadable(root: &Path, unreadable: &[PathBuf]) {
+    let mut subtrees: BTreeMap<PathBuf, Vec<&PathBuf>> = BTreeMap::new();
+    for path in unreadable {
+        let subtree = match path.strip_prefix(root).ok().and_then(|rel| rel.components().next()) {
+            Some(first) => root.join(first),
+            None => root.to_path_buf(),
+        };
+        subtrees.entry(subtree).or_default().push(path);
+    }
+    eprintln!("[warning] {} unreadable paths skipped (permission denied):", unreadable.len());
+    for (subtree, paths) in &subtrees {
+        let examples: Vec<String> = paths.iter().take(3).map(|p| p.display().to_string()).collect();
+        eprintln!("  {}: {} (e.g. {})", subtree.display(), paths.len(), examples.join(", "));
+    }
+}
+
+/// package.json dependency sections, with the `dependency` column of their
+/// rows.
+const SECTIONS: [(&str, &str); 5] = [
+    ("dependencies", "yes"),
+    ("devDependencies", "dev"),
+    ("optionalDependencies", "optional"),
+    ("peerDependencies", "peer"),
+    ("bundledDependencies", "bundled"),
+];
+
+/// Sections that map names to specs, in the order a spec is looked up.
+const SPEC_SECTIONS: [&str; 4] = ["dependencies", "devDependencies", "optionalDependencies", "peerDependencies"];
+
+/// The sections of `--section` without those of `--skip-section`. Both accept
+/// the full name or the `dependency` column label (`dev`, `optional`, `peer`,
+/// `bundled`), and npm's `bundleDependencies` spelling.
+fn selected_sections(sections: &[String], skip: &[String]) -> Vec<String> {
+    let full_name = |s: &str| match SECTIONS.iter().find(|(name, label)| *name == s || *label == s) {
+        Some((name, _)) => name.to_string(),
+        None if s == "bundleDependencies" => "bundledDependencies".to_string(),
+        None => s.to_string(),
+    };
+    let skip: Vec<String> = skip.iter().map(|s| full_name(s)).collect();
+    let mut selected: Vec<String> = Vec::new();
+    for section in sections.iter().map(|s| full_name(s)) {
+        if !skip.contains(&section) && !selected.contains(&section) {
+            selected.push(section);
         }
     }
+    selected
+}
 
-    let mut sorted_dirs: Vec<String> = dirs.into_iter().collect();
-    sorted_dirs.sort();
-    sorted_dirs
+/// The `name: spec` entries of a package.json section. Bundled dependencies
+/// are a list of names, or `true` for all of `dependencies`, whose specs come
+/// from the other sections.
+fn section_entries(data: &Value, section: &str) -> Option<serde_json::Map<String, Value>> {
+    if section != "bundledDependencies" {
+        return data.get(section)?.as_object().cloned();
+    }
+    let bundled = data.get("bundledDependencies").or_else(|| data.get("bundleDependencies"))?;
+    let names: Vec<String> = match bundled {
+        Value::Bool(true) => data.get("dependencies")?.as_object()?.keys().cloned().collect(),
+        Value::Array(names) => names.iter().filter_map(|n| n.as_str().map(|n| n.to_string())).collect(),
+        _ => return None,
+    };
+    let spec = |name: &str| {
+        SPEC_SECTIONS
+            .iter()
+            .find_map(|s| data.get(s).and_then(|d| d.get(name)).cloned())
+            .unwrap_or_else(|| Value::String(String::new()))
+    };
+    Some(names.into_iter().map(|n| (n.clone(), spec(&n))).collect())
 }
 
-fn get_pkg_range(name: &str, pkg_json: Option<&Value>) -> String {
-    if let Some(data) = pkg_json {
-        for section in ["dependencies", "devDependencies"] {
-            if let Some(deps) = data.get(section).and_then(|d| d.as_object()) {
-                if let Some(r) = deps.get(name).and_then(|r| r.as_str()) {
-                    return r.to_string();
-                }
+/// The package a package.json entry installs and its range: the target of an
+/// npm alias (`"foo": "npm:real@^1.0.0"`), else the entry itself.
+fn declared_package<'a>(name: &'a str, spec: &'a str) -> (&'a str, &'a str) {
+    packages::npm_alias(spec).unwrap_or((name, spec))
+}
+
+fn get_pkg_range(name: &str, preload: &Preload) -> String {
+    if let Some(data) = &preload.pkg_json {
+        for section in SPEC_SECTIONS {
+            let deps = data.get(section).and_then(|d| d.as_object()).into_iter().flatten();
+            let found = deps
+                .filter_map(|(n, r)| Some(declared_package(n, r.as_str()?)))
+                .find(|(n, _)| *n == name);
+            if let Some((_, r)) = found {
+                return r.to_string();
             }
         }
     }
+    if let Some(project) = &preload.deno
+        && let Some((_, r)) = project.imports.iter().find(|(n, _)| n == name)
+    {
+        return r.clone();
+    }
+    if let Some(project) = &preload.cargo
+        && let Some(dependency) = project.dependencies.iter().find(|d| d.name == name)
+    {
+        return dependency.requirement.clone();
+    }
+    if let Some(module) = &preload.go
+        && let Some(require) = module.requires.iter().find(|r| r.name == name)
+    {
+        return require.version.clone();
+    }
+    if let Some(project) = &preload.composer
+        && let Some(require) = project.requires.iter().find(|r| r.name == name)
+    {
+        return require.constraint.clone();
+    }
+    if let Some(bundle) = &preload.bundler
+        && let Some((_, requirement)) = bundle.dependencies.iter().find(|(n, _)| n == name)
+    {
+        return requirement.clone();
+    }
+    if let Some(project) = &preload.nuget
+        && let Some((_, range)) = project.direct.iter().find(|(n, _)| n == name)
+    {
+        return range.clone();
+    }
     String::new()
 }
 
-fn get_yarn_versions(name: &str, content: &str) -> HashSet<String> {
-    let mut versions: HashSet<String> = HashSet::new();
-    let record_re = Regex::new(r"\n\s*\n").unwrap();
-    let records: Vec<&str> = record_re.split(content).collect();
-    let ver_re = Regex::new(r#"version "(\d+\.\d+\.\d+)"#).unwrap();
-    for rec in records {
-        if rec.contains(&format!("{}@", name)) {
-            if let Some(cap) = ver_re.captures(rec) {
-                versions.insert(cap[1].to_string());
+/// Collect every package name mentioned by the manifest and lockfiles of a
+/// directory, used to resolve pattern entries to concrete names.
+fn project_names(preload: &Preload) -> HashSet<String> {
+    let mut names: HashSet<String> = HashSet::new();
+    if let Some(data) = &preload.pkg_json {
+        if let Some(name) = data.get("name").and_then(|n| n.as_str()) {
+            names.insert(name.to_string());
+        }
+        for section in SPEC_SECTIONS {
+            if let Some(deps) = data.get(section).and_then(|d| d.as_object()) {
+                names.extend(deps.iter().map(|(n, r)| declared_package(n, r.as_str().unwrap_or("")).0.to_string()));
             }
         }
     }
-    versions
+    let github = |resolved: Option<&str>| resolved.is_some_and(|r| registry::is_from(r, matcher::GITHUB_PACKAGES_HOST));
+    if let Some(lockfile) = &preload.yarn {
+        for entry in lockfile.entries.iter().filter(|e| !e.is_workspace()) {
+            for name in entry.names() {
+                if github(entry.resolved.as_deref()) {
+                    names.insert(format!("gpr:{}", name));
+                }
+                names.insert(name.to_string());
+            }
+        }
+    }
+    if let Some(plock) = &preload.plock {
+        if let Some(packages) = plock.get("packages").and_then(|p| p.as_object()) {
+            for (key, entry) in packages {
+                if let Some(name) = package_lock::key_name(key, entry) {
+                    if github(entry.get("resolved").and_then(|r| r.as_str())) {
+                        names.insert(format!("gpr:{}", name));
+                    }
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        if let Some(deps) = plock.get("dependencies").and_then(|d| d.as_object()) {
+            collect_plock_names(deps, &mut names);
+        }
+    }
+    if let Some(lockfile) = &preload.pnpm {
+        names.extend(lockfile.packages.iter().map(|(n, _)| n.clone()));
+    }
+    if let Some(lockfile) = &preload.bun {
+        names.extend(lockfile.packages.iter().map(|(n, _)| n.clone()));
+    }
+    if let Some(project) = &preload.deno {
+        names.extend(project.imports.iter().chain(&project.locked).map(|(n, _)| n.clone()));
+    }
+    if let Some(project) = &preload.cargo {
+        names.extend(project.name.iter().cloned());
+        names.extend(project.dependencies.iter().map(|d| d.name.clone()));
+        names.extend(project.locked.iter().map(|l| l.name.clone()));
+    }
+    if let Some(module) = &preload.go {
+        names.extend(module.path.iter().cloned());
+        names.extend(module.requires.iter().map(|r| r.name.clone()));
+        names.extend(module.sums.iter().map(|(n, _)| n.clone()));
+    }
+    if let Some(project) = &preload.composer {
+        names.extend(project.name.iter().cloned());
+        names.extend(project.requires.iter().map(|r| r.name.clone()));
+        names.extend(project.locked.iter().map(|l| l.name.clone()));
+    }
+    if let Some(bundle) = &preload.bundler {
+        names.extend(bundle.dependencies.iter().map(|(n, _)| n.clone()));
+        names.extend(bundle.specs.iter().map(|g| g.name.clone()));
+    }
+    if let Some(project) = &preload.maven {
+        names.extend(project.locked.iter().map(|l| l.name.clone()));
+    }
+    if let Some(project) = &preload.nuget {
+        names.extend(project.direct.iter().map(|(n, _)| n.clone()));
+        names.extend(project.packages.iter().map(|p| p.name.clone()));
+    }
+    for sbom in &preload.sboms {
+        names.extend(sbom.components.iter().map(|(n, _)| n.clone()));
+    }
+    if let Some(content) = &preload.deps {
+        let re = Regex::new(r#""name"\s*:\s*"(@?[^@"]+)@\d+\.\d+\.\d+"#).unwrap();
+        for cap in re.captures_iter(content) {
+            names.insert(cap[1].to_string());
+        }
+    }
+    names
 }
 
-fn get_package_lock_versions(name: &str, package_lock_json: &Value) -> HashSet<String> {
-    let mut versions: HashSet<String> = HashSet::new();
-    if let Some(deps) = package_lock_json.get("dependencies").and_then(|d| d.as_object()) {
-        if let Some(v) = deps.get(name).and_then(|v| v.get("version")).and_then(|v| v.as_str()) {
-            versions.insert(v.to_string());
+/// Names of a v1 `dependencies` tree, where aliases are keyed by their own
+/// name and pin `npm:<real name>@<version>`.
+fn collect_plock_names(deps: &serde_json::Map<String, Value>, names: &mut HashSet<String>) {
+    for (k, v) in deps {
+        names.insert(plock_v1_package(k, v).0.to_string());
+        if let Some(sub) = v.get("dependencies").and_then(|d| d.as_object()) {
+            collect_plock_names(sub, names);
         }
     }
+}
+
+fn get_package_lock_versions(name: &str, package_lock_json: &Value, registry: Option<&str>) -> HashSet<String> {
+    let mut versions: HashSet<String> = HashSet::new();
+    // Every install location, nested ones (`node_modules/a/node_modules/b`) included
     if let Some(packages) = package_lock_json.get("packages").and_then(|p| p.as_object()) {
-        let key = format!("node_modules/{}", name);
-        if let Some(v) = packages.get(&key).and_then(|v| v.get("version")).and_then(|v| v.as_str()) {
-            versions.insert(v.to_string());
+        for (key, entry) in packages {
+            if package_lock::key_name(key, entry) == Some(name)
+                && from_registry(entry, registry)
+                && let Some(v) = entry.get("version").and_then(|v| v.as_str())
+            {
+                versions.insert(v.to_string());
+            }
         }
     }
     if let Some(deps) = package_lock_json.get("dependencies").and_then(|d| d.as_object()) {
         for (k, v) in deps {
-            if k == name {
-                if let Some(ver) = v.get("version").and_then(|vv| vv.as_str()) {
-                    versions.insert(ver.to_string());
-                }
+            if let (dep, Some(ver)) = plock_v1_package(k, v)
+                && dep == name
+                && from_registry(v, registry)
+            {
+                versions.insert(ver.to_string());
             }
             if let Some(sub_obj) = v.as_object() {
-                walk_plock(sub_obj, name, &mut versions);
+                walk_plock(sub_obj, name, registry, &mut versions);
             }
         }
     }
     versions
 }
 
-fn walk_plock(obj: &serde_json::Map<String, Value>, name: &str, versions: &mut HashSet<String>) {
+fn walk_plock(obj: &serde_json::Map<String, Value>, name: &str, registry: Option<&str>, versions: &mut HashSet<String>) {
     if let Some(deps) = obj.get("dependencies").and_then(|d| d.as_object()) {
         for (k, v) in deps {
-            if k == name {
-                if let Some(ver) = v.get("version").and_then(|vv| vv.as_str()) {
-                    versions.insert(ver.to_string());
-                }
+            if let (dep, Some(ver)) = plock_v1_package(k, v)
+                && dep == name
+                && from_registry(v, registry)
+            {
+                versions.insert(ver.to_string());
             }
             if let Some(sub_obj) = v.as_object() {
-                walk_plock(sub_obj, name, versions);
+                walk_plock(sub_obj, name, registry, versions);
             }
         }
     }
 }
 
-fn get_pnpm_versions(name: &str, content: &str) -> HashSet<String> {
-    let mut versions: HashSet<String> = HashSet::new();
-    let pattern = Regex::new(&format!(r"/{}/(\d+\.\d+\.\d+)", regex::escape(name))).unwrap();
-    for cap in pattern.captures_iter(content) {
-        versions.insert(cap[1].to_string());
-    }
-    let pattern2 = Regex::new(&format!(r#""{}@(\d+\.\d+\.\d+)"#, regex::escape(name))).unwrap();
-    for cap in pattern2.captures_iter(content) {
-        versions.insert(cap[1].to_string());
+/// The package and version of a v1 `dependencies` entry, resolving aliases.
+fn plock_v1_package<'a>(key: &'a str, entry: &'a Value) -> (&'a str, Option<&'a str>) {
+    match entry.get("version").and_then(|v| v.as_str()) {
+        Some(version) => {
+            let (name, version) = declared_package(key, version);
+            (name, Some(version))
+        }
+        None => (key, None),
     }
-    versions
+}
+
+/// Whether a package-lock.json entry was resolved from `registry`, always true
+/// without one.
+fn from_registry(entry: &Value, registry: Option<&str>) -> bool {
+    registry.is_none_or(|host| {
+        entry
+            .get("resolved")
+            .and_then(|r| r.as_str())
+            .is_some_and(|r| registry::is_from(r, host))
+    })
 }
 
 fn get_dependencies_versions(name: &str, content: &str) -> HashSet<String> {
@@ -226,187 +1237,1962 @@ fn get_dependencies_versions(name: &str, content: &str) -> HashSet<String> {
     if let Ok(data) = serde_json::from_str::<Value>(content) {
         walk_deps(&data, name, &mut versions);
     }
-    versions
-}
+    versions
+}
+
+fn walk_deps(obj: &Value, name: &str, versions: &mut HashSet<String>) {
+    match obj {
+        Value::Object(map) => {
+            if let Some(nm) = map.get("name").and_then(|n| n.as_str())
+                && let (dep_name, Some(version)) = packages::split_spec(nm)
+                && dep_name == name
+                && Regex::new(r"^\d+\.\d+\.\d+$").unwrap().is_match(version)
+            {
+                versions.insert(version.to_string());
+            }
+            for (_, v) in map {
+                walk_deps(v, name, versions);
+            }
+        }
+        Value::Array(arr) => {
+            for item in arr {
+                walk_deps(item, name, versions);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The installed packages of `d`: what a project read in memory installs, or
+/// its node_modules on disk. Empty with --no-npm.
+fn installed_for<'a>(
+    d: &str,
+    in_memory_installs: &'a HashMap<String, HashMap<String, HashSet<String>>>,
+    no_npm: bool,
+) -> Cow<'a, HashMap<String, HashSet<String>>> {
+    match in_memory_installs.get(d) {
+        _ if no_npm => Cow::Owned(HashMap::new()),
+        Some(installed) => Cow::Borrowed(installed),
+        None => Cow::Owned(node_modules::installed(Path::new(d))),
+    }
+}
+
+/// Every package a directory knows of, with the versions its lockfiles pin
+/// and `installed` has, empty for packages that are only declared.
+fn known_versions(preload: &Preload, installed: &HashMap<String, HashSet<String>>) -> Vec<(String, HashSet<String>)> {
+    let mut names = project_names(preload);
+    names.extend(installed.keys().cloned());
+    names
+        .into_iter()
+        .map(|name| {
+            let mut versions: HashSet<String> = lockfile_versions(&name, preload).into_values().flatten().collect();
+            versions.extend(installed.get(&name).into_iter().flatten().cloned());
+            (name, versions)
+        })
+        .collect()
+}
+
+/// Versions of `name` pinned by each lockfile of a directory, keyed by file
+/// name.
+fn lockfile_versions(name: &str, preload: &Preload) -> HashMap<String, HashSet<String>> {
+    let mut versions_by_file: HashMap<String, HashSet<String>> = HashMap::new();
+    for sbom in &preload.sboms {
+        let versions: HashSet<String> = sbom.versions(name).map(|v| v.to_string()).collect();
+        if !versions.is_empty() {
+            versions_by_file.insert(sbom.file.clone(), versions);
+        }
+    }
+    // Crates are only in Cargo.lock, Go modules in go.sum, Composer packages
+    // in composer.lock, gems in the Bundler lockfile and Maven artifacts in
+    // the Gradle and Maven lockfiles, NuGet packages in packages.lock.json or
+    // project.assets.json
+    let (namespace, bare) = matcher::split_namespace(name);
+    let own: Option<Vec<(&str, &str)>> = match namespace {
+        Namespace::Cargo => Some(preload.cargo.iter().flat_map(|p| p.versions(name)).map(|v| ("Cargo.lock", v)).collect()),
+        Namespace::Go => Some(preload.go.iter().flat_map(|m| m.versions(name)).map(|v| ("go.sum", v)).collect()),
+        Namespace::Composer => {
+            Some(preload.composer.iter().flat_map(|p| p.versions(name)).map(|v| ("composer.lock", v)).collect())
+        }
+        Namespace::RubyGems => Some(preload.bundler.iter().flat_map(|b| b.versions(name).map(|v| (b.file, v))).collect()),
+        Namespace::Maven => Some(preload.maven.iter().flat_map(|p| p.versions(name)).collect()),
+        Namespace::NuGet => Some(preload.nuget.iter().flat_map(|p| p.versions(name).map(|v| (p.file, v))).collect()),
+        _ => None,
+    };
+    if let Some(own) = own {
+        for (file, version) in own {
+            versions_by_file.entry(file.to_string()).or_default().insert(version.to_string());
+        }
+        return versions_by_file;
+    }
+    // npm lockfiles know JSR packages by their npm compatibility name, and
+    // GitHub Packages ones by their plain name and the registry they came from
+    let npm_name = match namespace {
+        Namespace::Jsr => matcher::jsr_npm_name(bare).unwrap_or_default(),
+        _ => bare.to_string(),
+    };
+    let registry = (namespace == Namespace::GitHub).then_some(matcher::GITHUB_PACKAGES_HOST);
+    let found = [
+        ("yarn.lock", preload.yarn.as_ref().map(|l| l.versions(&npm_name, registry).map(|v| v.to_string()).collect())),
+        (preload.plock_file, preload.plock.as_ref().map(|p| get_package_lock_versions(&npm_name, p, registry))),
+        // pnpm-lock.yaml, bun.lock and DEPENDENCIES.json do not record the registry
+        (
+            "pnpm-lock.yaml",
+            preload
+                .pnpm
+                .as_ref()
+                .filter(|_| registry.is_none())
+                .map(|l| l.versions(&npm_name).map(|v| v.to_string()).collect()),
+        ),
+        (
+            preload.bun.as_ref().map_or("bun.lock", |l| l.file),
+            preload
+                .bun
+                .as_ref()
+                .filter(|_| registry.is_none())
+                .map(|l| l.versions(&npm_name).map(|v| v.to_string()).collect()),
+        ),
+        ("deno.lock", preload.deno.as_ref().map(|p| p.versions(name).map(|v| v.to_string()).collect())),
+        (
+            "DEPENDENCIES.json",
+            preload.deps.as_ref().filter(|_| registry.is_none()).map(|c| get_dependencies_versions(&npm_name, c)),
+        ),
+    ];
+    for (file, versions) in found {
+        if let Some(versions) = versions.filter(|v| !v.is_empty()) {
+            versions_by_file.insert(file.to_string(), versions);
+        }
+    }
+    versions_by_file
+}
+
+/// What a fresh install of a direct dependency would pull compared with the
+/// lockfile.
+struct FreshResolution {
+    version: String,
+    pinned: Vec<String>,
+}
+
+/// Packuments fetched during the scan, None when the lookup failed.
+type PackumentCache = Mutex<HashMap<String, Option<registry::Packument>>>;
+
+/// Versions of git dependencies by specifier, None when it could not be read.
+type GitVersionCache = Mutex<HashMap<String, Option<String>>>;
+
+/// The version in the package.json a git dependency points at, fetched once
+/// per specifier.
+fn git_dependency_version(spec: &str, cache: &GitVersionCache) -> Option<String> {
+    if let Some(version) = cache.lock().unwrap().get(spec) {
+        return version.clone();
+    }
+    let version = match specifier::git_source(spec) {
+        Some((url, rev)) => match git::remote_file(&url, rev.as_deref(), "package.json") {
+            Ok(content) => serde_json::from_str::<Value>(&content)
+                .ok()
+                .and_then(|manifest| manifest.get("version")?.as_str().map(|v| v.to_string())),
+            Err(e) => {
+                eprintln!("[warning] Could not read the package.json of git dependency {}: {}", spec, e);
+                None
+            }
+        },
+        None => None,
+    };
+    cache.lock().unwrap().insert(spec.to_string(), version.clone());
+    version
+}
+
+/// Make sure `cache` holds the packument of `name`, fetching it once.
+fn fetch_packument(name: &str, cache: &PackumentCache, npmrc: &Npmrc, verbose: bool) {
+    if !cache.lock().unwrap().contains_key(name) {
+        let packument = match registry::fetch(name, npmrc) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                if verbose {
+                    eprintln!("[debug] Registry lookup of {} failed: {}", name, e);
+                }
+                None
+            }
+        };
+        cache.lock().unwrap().insert(name.to_string(), packument);
+    }
+}
+
+/// The version the dist-tag `tag` of `name` points at in the registry. None
+/// when the registry cannot be reached or has no such tag.
+fn dist_tag_version(name: &str, tag: &str, cache: &PackumentCache, npmrc: &Npmrc, verbose: bool) -> Option<String> {
+    fetch_packument(name, cache, npmrc, verbose);
+    let cache = cache.lock().unwrap();
+    let packument = cache.get(name)?.as_ref()?;
+    let version = packument.dist_tags.get(tag.trim()).cloned();
+    if version.is_none() {
+        eprintln!("[warning] {} has no dist-tag {} in the registry", name, tag);
+    }
+    version
+}
+
+/// Resolve `range` of `name` against the registry. Returns None when the
+/// registry cannot be reached or nothing satisfies the range. Packuments are
+/// shared between directories through `cache`.
+fn fresh_resolution(
+    name: &str,
+    range: &str,
+    preload: &Preload,
+    cache: &PackumentCache,
+    npmrc: &Npmrc,
+    verbose: bool,
+) -> Option<FreshResolution> {
+    fetch_packument(name, cache, npmrc, verbose);
+    let cache = cache.lock().unwrap();
+    let version = cache.get(name)?.as_ref()?.resolve(range)?.to_string();
+    let mut pinned: Vec<String> = lockfile_versions(name, preload).into_values().flatten().collect();
+    pinned.sort();
+    pinned.dedup();
+    Some(FreshResolution { version, pinned })
+}
+
+const PACKAGE_LOCK: &str = "package-lock.json";
+/// Published packages ship this instead of a package-lock.json, npm prefers
+/// it when both exist.
+const SHRINKWRAP: &str = "npm-shrinkwrap.json";
+
+/// Lockfiles compared by `--trusted-lock`.
+const LOCKFILES: [&str; 4] = ["yarn.lock", PACKAGE_LOCK, SHRINKWRAP, "pnpm-lock.yaml"];
+
+/// The `LOCKFILES` a directory has.
+fn present_lockfiles(preload: &Preload) -> Vec<&'static str> {
+    let present = [
+        preload.yarn.is_some(),
+        preload.plock.is_some() && preload.plock_file == PACKAGE_LOCK,
+        preload.plock.is_some() && preload.plock_file == SHRINKWRAP,
+        preload.pnpm.is_some(),
+    ];
+    LOCKFILES.into_iter().zip(present).filter(|(_, p)| *p).map(|(f, _)| f).collect()
+}
+
+/// Read the manifests and lockfiles of the project in `dir` for the selected
+/// ecosystems.
+fn read_preload(dir: &str, sections: Vec<String>, ecosystems: &[Ecosystem]) -> Preload {
+    let mut preload = Preload {
+        yarn: None,
+        plock: None,
+        plock_file: PACKAGE_LOCK,
+        pnpm: None,
+        bun: None,
+        deno: None,
+        cargo: None,
+        go: None,
+        composer: None,
+        bundler: None,
+        maven: None,
+        nuget: None,
+        sboms: Vec::new(),
+        deps: None,
+        pkg_json: None,
+        sections,
+    };
+    let dir_path = Path::new(dir);
+    if ecosystems.contains(&Ecosystem::Cargo) {
+        match cargo::load(dir_path) {
+            Some(Ok(project)) => preload.cargo = Some(project),
+            Some(Err(e)) => eprintln!("[warning] Could not read the Cargo manifest or lockfile of {}: {}", dir, e),
+            None => {}
+        }
+    }
+    if ecosystems.contains(&Ecosystem::Go) {
+        match go::load(dir_path) {
+            Some(Ok(module)) => preload.go = Some(module),
+            Some(Err(e)) => eprintln!("[warning] Could not read {}: {}", dir_path.join("go.mod").display(), e),
+            None => {}
+        }
+    }
+    if ecosystems.contains(&Ecosystem::Composer) {
+        match composer::load(dir_path) {
+            Some(Ok(project)) => preload.composer = Some(project),
+            Some(Err(e)) => eprintln!("[warning] Could not read the Composer manifest or lockfile of {}: {}", dir, e),
+            None => {}
+        }
+    }
+    if ecosystems.contains(&Ecosystem::Bundler) {
+        match bundler::load(dir_path) {
+            Some(Ok(bundle)) => preload.bundler = Some(bundle),
+            Some(Err(e)) => eprintln!("[warning] Could not read the Bundler lockfile of {}: {}", dir, e),
+            None => {}
+        }
+    }
+    if ecosystems.contains(&Ecosystem::Maven) {
+        match maven::load(dir_path) {
+            Some(Ok(project)) => preload.maven = Some(project),
+            Some(Err(e)) => eprintln!("[warning] Could not read the Gradle or Maven lockfiles of {}: {}", dir, e),
+            None => {}
+        }
+    }
+    if ecosystems.contains(&Ecosystem::NuGet) {
+        match nuget::load(dir_path) {
+            Some(Ok(project)) => preload.nuget = Some(project),
+            Some(Err(e)) => eprintln!("[warning] Could not read the NuGet lockfile of {}: {}", dir, e),
+            None => {}
+        }
+    }
+    if !ecosystems.contains(&Ecosystem::Npm) {
+        return preload;
+    }
+    if let Ok(content) = fs::read_to_string(dir_path.join("yarn.lock")) {
+        preload.yarn = Some(yarn::parse(&content));
+    }
+    let plock_file = [SHRINKWRAP, PACKAGE_LOCK].into_iter().find(|f| dir_path.join(f).is_file());
+    if let Some(plock_file) = plock_file
+        && let Ok(file) = File::open(dir_path.join(plock_file))
+        && let Ok(value) = serde_json::from_reader(file)
+    {
+        preload.plock = Some(value);
+        preload.plock_file = plock_file;
+    }
+    if let Ok(content) = fs::read_to_string(dir_path.join("pnpm-lock.yaml")) {
+        preload.pnpm = pnpm::parse(&content);
+        if preload.pnpm.is_none() {
+            eprintln!("[warning] Could not parse {}", dir_path.join("pnpm-lock.yaml").display());
+        }
+    }
+    match bun::load(dir_path) {
+        Some(Ok(lockfile)) => preload.bun = Some(lockfile),
+        Some(Err(e)) => eprintln!("[warning] Could not read the Bun lockfile of {}: {}", dir, e),
+        None => {}
+    }
+    preload.deno = deno::load(dir_path);
+    if let Ok(content) = fs::read_to_string(dir_path.join("DEPENDENCIES.json")) {
+        preload.deps = Some(content);
+    }
+    let pj_path = dir_path.join("package.json");
+    if pj_path.is_file()
+        && let Ok(file) = File::open(&pj_path)
+        && let Ok(value) = serde_json::from_reader(file)
+    {
+        preload.pkg_json = Some(value);
+    }
+    preload
+}
+
+/// The lockfiles of `preload` that workspace members resolve through.
+fn workspace_lockfiles(preload: &Preload) -> workspaces::Lockfiles<'_> {
+    workspaces::Lockfiles {
+        plock: preload.plock.as_ref(),
+        yarn: preload.yarn.as_ref(),
+        pnpm: preload.pnpm.as_ref(),
+    }
+}
+
+/// Input files of `dir` that exist but could not be read into `preload`.
+fn input_errors(dir: &str, preload: &Preload) -> Vec<String> {
+    let dir_path = Path::new(dir);
+    let plock_present = [SHRINKWRAP, PACKAGE_LOCK].into_iter().find(|f| dir_path.join(f).is_file());
+    let bun_present = ["bun.lock", "bun.lockb"].into_iter().find(|f| dir_path.join(f).is_file());
+    let checks = [
+        (dir_path.join("package.json").is_file().then_some("package.json"), preload.pkg_json.is_some()),
+        (dir_path.join("yarn.lock").is_file().then_some("yarn.lock"), preload.yarn.is_some()),
+        (plock_present, preload.plock.is_some()),
+        (dir_path.join("pnpm-lock.yaml").is_file().then_some("pnpm-lock.yaml"), preload.pnpm.is_some()),
+        (bun_present, preload.bun.is_some()),
+    ];
+    checks
+        .into_iter()
+        .filter_map(|(file, loaded)| file.filter(|_| !loaded))
+        .map(|file| format!("{} could not be read", file))
+        .collect()
+}
+
+/// Preload holding only `file`, one of `package.json` and the `LOCKFILES`,
+/// read from `content`.
+fn file_preload(file: &str, content: &str) -> Preload {
+    let mut preload = Preload {
+        yarn: None,
+        plock: None,
+        plock_file: PACKAGE_LOCK,
+        pnpm: None,
+        bun: None,
+        deno: None,
+        cargo: None,
+        go: None,
+        composer: None,
+        bundler: None,
+        maven: None,
+        nuget: None,
+        sboms: Vec::new(),
+        deps: None,
+        pkg_json: None,
+        sections: Vec::new(),
+    };
+    match file {
+        "package.json" => preload.pkg_json = serde_json::from_str(content).ok(),
+        "yarn.lock" => preload.yarn = Some(yarn::parse(content)),
+        PACKAGE_LOCK | SHRINKWRAP => {
+            preload.plock = serde_json::from_str(content).ok();
+            preload.plock_file = if file == SHRINKWRAP { SHRINKWRAP } else { PACKAGE_LOCK };
+        }
+        _ => preload.pnpm = pnpm::parse(content),
+    }
+    preload
+}
+
+/// Preload of a project held in memory, a packed package or a folder of a git
+/// tree or source archive, from the `tarball::FILES` it holds in their order of preference.
+fn content_preload(files: &[(String, String)], sections: Vec<String>) -> Preload {
+    let mut preload = file_preload("package.json", "");
+    preload.sections = sections;
+    for (file, content) in files {
+        let single = file_preload(file, content);
+        preload.pkg_json = preload.pkg_json.or(single.pkg_json);
+        preload.yarn = preload.yarn.or(single.yarn);
+        preload.pnpm = preload.pnpm.or(single.pnpm);
+        if preload.plock.is_none() && single.plock.is_some() {
+            preload.plock = single.plock;
+            preload.plock_file = single.plock_file;
+        }
+    }
+    preload
+}
+
+/// A package.json dependency a lockfile does not honour.
+struct ManifestDrift {
+    lockfile: &'static str,
+    name: String,
+    range: String,
+    /// Versions the lockfile pins, empty when the dependency is missing
+    locked: Vec<String>,
+}
+
+/// Compare every dependency range declared in the checked package.json
+/// sections with what each lockfile of the directory resolves. Specs that are
+/// not semver ranges (dist-tags, git, `file:` and `workspace:` specs, aliases)
+/// and peer dependencies, which the project does not install itself, are
+/// skipped.
+fn manifest_drift(preload: &Preload) -> Vec<ManifestDrift> {
+    let mut lockfiles = present_lockfiles(preload);
+    lockfiles.extend(preload.bun.as_ref().map(|l| l.file));
+    let Some(data) = preload.pkg_json.as_ref().filter(|_| !lockfiles.is_empty()) else {
+        return Vec::new();
+    };
+    let mut drift: Vec<ManifestDrift> = Vec::new();
+    for section in preload.sections.iter().filter(|s| *s != "peerDependencies") {
+        let Some(deps) = data.get(section).and_then(|d| d.as_object()) else {
+            continue;
+        };
+        for (name, range) in deps {
+            let Some((name, range)) = range.as_str().map(|r| declared_package(name, r)).filter(|(_, r)| !r.trim().is_empty()) else {
+                continue;
+            };
+            let Some(parsed) = Range::parse(range) else {
+                continue;
+            };
+            let mut by_file = lockfile_versions(name, preload);
+            for lockfile in &lockfiles {
+                let mut locked: Vec<String> = by_file.remove(*lockfile).unwrap_or_default().into_iter().collect();
+                locked.sort();
+                if locked.iter().any(|v| parse_version(v).is_some_and(|v| parsed.matches(v))) {
+                    continue;
+                }
+                drift.push(ManifestDrift {
+                    lockfile,
+                    name: name.to_string(),
+                    range: range.to_string(),
+                    locked,
+                });
+            }
+        }
+    }
+    drift
+}
+
+/// Where npm has to run for the project in `dirpath`. A workspace member
+/// without its own node_modules has its dependencies installed at the
+/// workspace root, where `npm ls` is run with `--workspace <name>` instead;
+/// run in the member itself npm finds nothing.
+struct NpmTarget {
+    dir: PathBuf,
+    workspace: Option<String>,
+}
+
+impl NpmTarget {
+    fn new(dirpath: &str, pkg_json: Option<&Value>) -> Self {
+        let dir = Path::new(dirpath);
+        let name = pkg_json.and_then(|p| p.get("name")).and_then(|n| n.as_str());
+        if let Some(name) = name
+            && !dir.join("node_modules").is_dir()
+            && let Some(root) = workspaces::root(dir)
+        {
+            return NpmTarget {
+                dir: root,
+                workspace: Some(name.to_string()),
+            };
+        }
+        NpmTarget {
+            dir: dir.to_path_buf(),
+            workspace: None,
+        }
+    }
+
+    fn command<'a>(&self, args: impl IntoIterator<Item = &'a str>) -> Command {
+        let mut command = Command::new("npm");
+        command.args(args).current_dir(&self.dir);
+        if let Some(workspace) = &self.workspace {
+            command.args(["--workspace", workspace]);
+        }
+        command
+    }
+}
+
+/// Installed versions per package name from `npm sbom` (npm 9.5+). Returns
+/// None when the command is unavailable or fails so callers can fall back to
+/// `npm ls`.
+fn get_npm_sbom(target: &NpmTarget) -> Option<HashMap<String, HashSet<String>>> {
+    let output = target
+        .command(["sbom", "--sbom-format", "cyclonedx"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let data: Value = serde_json::from_slice(&output.stdout).ok()?;
+    let mut installed: HashMap<String, HashSet<String>> = HashMap::new();
+    for component in data.get("components")?.as_array()? {
+        let (Some(name), Some(version)) = (
+            component.get("name").and_then(|n| n.as_str()),
+            component.get("version").and_then(|v| v.as_str()),
+        ) else {
+            continue;
+        };
+        let name = match component.get("group").and_then(|g| g.as_str()) {
+            Some(group) if !group.is_empty() => format!("{}/{}", group, name),
+            _ => name.to_string(),
+        };
+        installed.entry(name).or_default().insert(version.to_string());
+    }
+    Some(installed)
+}
+
+/// Run `npm audit --json` for the target. npm exits non-zero when it finds
+/// vulnerabilities, so only its output is looked at.
+fn run_npm_audit(target: &NpmTarget) -> io::Result<Vec<packages::ListEntry>> {
+    let output = target.command(["audit", "--json"]).output()?;
+    let data: Value = serde_json::from_slice(&output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    npm_audit::parse(&data)
+}
+
+fn get_npm_versions(target: &NpmTarget, name: &str) -> HashSet<String> {
+    let mut versions: HashSet<String> = HashSet::new();
+    let output = match target.command(["ls", "--json", name, "--depth=Infinity"]).output()
+    {
+        Ok(o) if o.status.success() => o.stdout,
+        _ => return versions,
+    };
+    let output_str = match std::str::from_utf8(&output) {
+        Ok(s) => s,
+        _ => return versions,
+    };
+    let data: Value = match serde_json::from_str(output_str) {
+        Ok(d) => d,
+        _ => return versions,
+    };
+    walk_npm(&data, name, &mut versions);
+    versions
+}
+
+fn walk_npm(obj: &Value, name: &str, versions: &mut HashSet<String>) {
+    if let Value::Object(map) = obj
+        && let Some(deps) = map.get("dependencies").and_then(|d| d.as_object())
+    {
+        for (k, v) in deps {
+            if k == name
+                && let Some(ver) = v.get("version").and_then(|vv| vv.as_str())
+            {
+                versions.insert(ver.to_string());
+            }
+            walk_npm(v, name, versions);
+        }
+    }
+}
+
+/// Parse the command line, filling in options it does not set from the config
+/// file.
+fn parse_args() -> Args {
+    let argv: Vec<OsString> = std::env::args_os().collect();
+    let cmd = Args::command();
+    let matches = cmd.clone().get_matches_from(&argv);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let explicit = args.config.is_some();
+    let trust_project_npmrc = args.trust_project_npmrc;
+    let Some(path) = args
+        .config
+        .as_ref()
+        .map(PathBuf::from)
+        .or_else(|| config::discover(Path::new(&args.start_path)))
+    else {
+        return args;
+    };
+    match config::to_args(&path, &cmd, &matches).and_then(|extra| Ok((extra, config::hooks(&path)?))) {
+        Ok((extra, hooks)) => {
+            let mut full: Vec<OsString> = argv[..1].to_vec();
+            full.extend(extra.into_iter().map(OsString::from));
+            full.extend(argv[1..].iter().cloned());
+            let mut args = Args::parse_from(full);
+            if args.verbose {
+                eprintln!("[debug] Using config {}", path.display());
+            }
+            // A config found in the scanned tree is as untrusted as the tree
+            // itself, so only one given with --config may run commands or
+            // trust the tree's .npmrc files
+            if explicit {
+                args.hooks = hooks;
+            } else {
+                if !hooks.is_empty() {
+                    eprintln!(
+                        "[warning] Ignoring the hooks of {}, hooks only run from a config passed with --config",
+                        path.display()
+                    );
+                }
+                if args.trust_project_npmrc && !trust_project_npmrc {
+                    eprintln!(
+                        "[warning] Ignoring trust-project-npmrc of {}, it is only taken from a config passed with --config",
+                        path.display()
+                    );
+                    args.trust_project_npmrc = false;
+                }
+            }
+            args
+        }
+        Err(e) => {
+            eprintln!("[error] Invalid config {}: {}", path.display(), e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Severity whose rows are never dropped by `--max-rows-per-severity`.
+const KEEP_SEVERITY: &str = "critical";
+
+/// Keep at most `max` rows per severity label, preferring findings over
+/// inventory rows. Critical rows are always kept. Returns the kept rows in
+/// their original order and the number of dropped rows per severity.
+fn sample_rows(rows: &[Row], max: usize) -> (Vec<&Row>, BTreeMap<String, usize>) {
+    let mut by_severity: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        by_severity.entry(row.severity.as_str()).or_default().push(i);
+    }
+    let mut keep = vec![false; rows.len()];
+    let mut dropped: BTreeMap<String, usize> = BTreeMap::new();
+    for (severity, mut indexes) in by_severity {
+        if severity != KEEP_SEVERITY && indexes.len() > max {
+            indexes.sort_by_key(|&i| !(rows[i].match_package && rows[i].match_version));
+            dropped.insert(severity.to_string(), indexes.len() - max);
+            indexes.truncate(max);
+        }
+        for i in indexes {
+            keep[i] = true;
+        }
+    }
+    let kept = rows.iter().zip(keep).filter(|(_, k)| *k).map(|(r, _)| r).collect();
+    (kept, dropped)
+}
+
+/// Sidecar written next to each package.json with findings.
+const SIDECAR_FILE: &str = "package-checker.findings.json";
+
+/// Write the findings of every scanned directory to a sidecar next to its
+/// package.json, and remove stale sidecars from directories that are clean now.
+fn write_sidecars(dirs: &[String], rows: &[Row], verbose: bool) {
+    let mut by_dir: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+    for row in rows.iter().filter(|r| r.match_package && r.match_version && r.allowed.is_empty()) {
+        by_dir.entry(row.location.as_str()).or_default().push(json!({
+            "package": row.package,
+            "version": row.version,
+            "dependency": row.dependency,
+            "depended_by": row.depended_by,
+            "source": row.source,
+            "reason": row.reason,
+            "severity": row.severity,
+            "match_type": row.match_type.label(),
+        }));
+    }
+    for d in dirs {
+        let path = Path::new(d).join(SIDECAR_FILE);
+        let result = match by_dir.get(d.as_str()) {
+            Some(findings) => {
+                let sidecar = json!({
+                    "generated_by": format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+                    "findings": findings,
+                });
+                File::create(&path).and_then(|f| serde_json::to_writer_pretty(f, &sidecar).map_err(io::Error::from))
+            }
+            None if path.is_file() => fs::remove_file(&path),
+            None => continue,
+        };
+        match result {
+            Ok(()) if verbose => eprintln!("[debug] Updated {}", path.display()),
+            Ok(()) => {}
+            Err(e) => eprintln!("[warning] Failed to write {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Names of the optional features used by this run, for telemetry.
+fn used_features(args: &Args, packages: &PackageList) -> Vec<&'static str> {
+    let mut features = Vec::new();
+    let flags = [
+        (args.no_npm, "no_npm"),
+        (args.use_npm, "use_npm"),
+        (args.no_npm_sbom, "no_npm_sbom"),
+        (args.no_sbom, "no_sbom"),
+        (args.git_ref.is_some(), "git_ref"),
+        (!args.scan_archive.is_empty(), "scan_archive"),
+        (matches!(args.command, Some(Commands::Image { .. })), "image"),
+        (matches!(args.command, Some(Commands::ScanRemote { .. })), "scan_remote"),
+        (matches!(args.command, Some(Commands::Outdated)), "outdated"),
+        (args.root_only, "root_only"),
+        (args.nx || args.nx_affected, "nx"),
+        (args.turbo || !args.turbo_filter.is_empty(), "turbo"),
+        (args.ecosystem.contains(&Ecosystem::Cargo), "cargo"),
+        (args.ecosystem.contains(&Ecosystem::Go), "go"),
+        (args.ecosystem.contains(&Ecosystem::Composer), "composer"),
+        (args.ecosystem.contains(&Ecosystem::Bundler), "bundler"),
+        (args.ecosystem.contains(&Ecosystem::Maven), "maven"),
+        (args.ecosystem.contains(&Ecosystem::NuGet), "nuget"),
+        (args.use_mvn, "use_mvn"),
+        (args.no_builtin_db, "no_builtin_db"),
+        (args.fail_on_unreadable, "fail_on_unreadable"),
+        (args.fail_on.is_some(), "fail_on"),
+        (args.start_paths_file.is_some(), "start_paths_file"),
+        (!packages.exemptions().is_empty(), "exemptions"),
+        (args.severity_map.is_some(), "severity_map"),
+        (args.fresh_resolve, "fresh_resolve"),
+        (args.resolve_git_deps, "resolve_git_deps"),
+        (args.resolve_dist_tags, "resolve_dist_tags"),
+        (args.include_prerelease, "include_prerelease"),
+        (args.loose_versions, "loose_versions"),
+        (args.match_mode.is_some(), "match_mode"),
+        (args.verify_integrity, "verify_integrity"),
+        (args.verify_cached_tarballs, "verify_cached_tarballs"),
+        (args.proxy.is_some(), "proxy"),
+        (args.offline, "offline"),
+        (args.check_lockfile, "check_lockfile"),
+        (args.previous_report.is_some(), "previous_report"),
+        (args.project_filter.is_some(), "project_filter"),
+        (!args.skip_section.is_empty(), "skip_section"),
+        (args.write_sidecars, "write_sidecars"),
+        (args.verdicts, "verdicts"),
+        (args.no_ignore, "no_ignore"),
+        (!args.include.is_empty() || !args.exclude.is_empty(), "include_exclude"),
+        (args.max_rows_per_severity.is_some(), "max_rows_per_severity"),
+        (args.trusted_lock.is_some(), "trusted_lock"),
+        (args.evidence, "evidence"),
+        (args.blame, "blame"),
+        (args.footprint, "footprint"),
+        (args.package_file.len() > 1, "multiple_package_files"),
+        (args.package_file.iter().any(|f| http::is_url(f)), "package_file_url"),
+        (!args.osv_query.is_empty(), "osv_query"),
+        (!args.ghsa_id.is_empty(), "ghsa_id"),
+        (args.audit.contains(&Audit::Osv), "audit_osv"),
+        (args.audit.contains(&Audit::Ghsa), "audit_ghsa"),
+        (args.with_npm_audit, "with_npm_audit"),
+        (args.check_deprecated || args.fail_on_deprecated, "check_deprecated"),
+        (args.max_release_age_check.is_some(), "max_release_age_check"),
+        (args.check_maintainers, "check_maintainers"),
+        (args.detect_typosquats, "detect_typosquats"),
+        (packages.iter().any(|p| p.matcher.exact_name().is_none()), "pattern_entries"),
+        (packages.iter().any(|p| p.version == packages::ANY_VERSION), "any_version_entries"),
+    ];
+    for (used, name) in flags {
+        if used {
+            features.push(name);
+        }
+    }
+    features
+}
+
+fn main() -> io::Result<ExitCode> {
+    let started = Instant::now();
+    let mut args = parse_args();
+    args.section = selected_sections(&args.section, &args.skip_section);
+    let cache_dir = args.cache_dir.as_ref().map(PathBuf::from).unwrap_or_else(http::default_cache_dir);
+    if let Some(proxy) = &args.proxy {
+        http::set_proxy(proxy);
+    }
+    http::set_cache(cache_dir.join("responses"), Duration::from_secs(args.cache_ttl * 60), args.offline);
+
+    if let Some(Commands::UpdateDb { url }) = &args.command {
+        return match db::update(url, &args.package_file_headers, &cache_dir) {
+            Ok(count) => {
+                println!("Updated known packages database with {} entries", count);
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(e) => {
+                eprintln!("[error] Updating known packages from {} failed: {}", url, e);
+                Ok(ExitCode::FAILURE)
+            }
+        };
+    }
+
+    if let Some(Commands::Push {
+        report,
+        endpoint,
+        token,
+        headers,
+        retries,
+    }) = &args.command
+    {
+        let mut headers = headers.clone();
+        if let Some(token) = token {
+            headers.push(format!("Authorization: Bearer {}", token));
+        }
+        return match push::push(report, endpoint, &headers, *retries) {
+            Ok(()) => {
+                println!("Uploaded {} to {}", report.display(), endpoint);
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(e) => {
+                eprintln!("[error] Uploading {} to {} failed: {}", report.display(), endpoint, e);
+                Ok(ExitCode::FAILURE)
+            }
+        };
+    }
+
+    if let Some(Commands::Search {
+        terms,
+        report: Some(report),
+    }) = &args.command
+    {
+        return match search::in_report(report, terms) {
+            Ok(hits) => {
+                search::print(&hits);
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(e) => {
+                eprintln!("[error] Failed to read {}: {}", report.display(), e);
+                Ok(ExitCode::FAILURE)
+            }
+        };
+    }
+
+    if args.package_file.is_empty() && Path::new("packages.txt").is_file() {
+        args.package_file.push("packages.txt".to_string());
+    }
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs)
+        .build_global()
+        .unwrap();
+
+    if args.verbose {
+        eprintln!("[debug] Using {} threads", args.jobs);
+    }
+
+    // Record the toolchain and warn when features that shell out will be degraded
+    let tools = toolchain::detect();
+    if args.verbose {
+        for (tool, version) in &tools {
+            eprintln!("[debug] {}: {}", tool, version.as_deref().unwrap_or("not found"));
+        }
+    }
+    let mut warnings: Vec<String> = Vec::new();
+    let tool_version = |tool: &str| tools.get(tool).cloned().flatten();
+    if !args.no_npm && args.use_npm {
+        match tool_version("npm") {
+            None => warnings.push("npm not found on PATH, installed versions (npm sbom / npm ls) are not checked".to_string()),
+            Some(v) if !args.no_npm_sbom && parse_version(&v).is_some_and(|(major, minor, _)| (major, minor) < (9, 5)) => {
+                warnings.push(format!("npm {} has no sbom command, falling back to the slower npm ls", v))
+            }
+            _ => {}
+        }
+    }
+    if args.with_npm_audit && tool_version("npm").is_none() {
+        warnings.push("npm not found on PATH, --with-npm-audit adds no advisories".to_string());
+    }
+    let needs_curl = args.package_file.iter().any(|f| http::is_url(f))
+        || !args.osv_query.is_empty()
+        || !args.ghsa_id.is_empty()
+        || !args.audit.is_empty()
+        || args.fresh_resolve
+        || args.resolve_dist_tags
+        || args.verify_integrity
+        || args.verify_cached_tarballs
+        || args.check_deprecated
+        || args.fail_on_deprecated
+        || args.max_release_age_check.is_some()
+        || args.check_maintainers
+        || matches!(args.command, Some(Commands::Outdated));
+    if needs_curl && !args.offline && tool_version("curl").is_none() {
+        warnings.push("curl not found on PATH, package file downloads and advisory queries will fail".to_string());
+    }
+    for warning in &warnings {
+        eprintln!("[warning] {}", warning);
+    }
+
+    if matches!(args.command, None | Some(Commands::Image { .. } | Commands::ScanRemote { .. })) {
+        println!("Checking for npm packages and lockfile/package.json/DEPENDENCIES.json compatibility in this project and subfolders...");
+    }
+
+    // An image is unpacked and its filesystem scanned as the only start path.
+    // No .gitignore hides its node_modules folders, so they are not walked
+    // for projects: the folders holding them are scanned for their installs.
+    let unpacked = match &args.command {
+        Some(Commands::Image { image }) => match image::unpack(image) {
+            Ok(unpacked) => Some((image.clone(), unpacked)),
+            Err(e) => {
+                eprintln!("[error] Could not unpack image {}: {}", image, e);
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        _ => None,
+    };
+    if unpacked.is_some() {
+        args.no_ignore = true;
+        args.exclude.push("**/node_modules".to_string());
+    }
+
+    // A remote repository is cloned and scanned as the only start path,
+    // labelled by its URL and the ref checked out
+    let checkout = match &args.command {
+        Some(Commands::ScanRemote { url, git_ref }) => match git::shallow_clone(url, git_ref.as_deref()) {
+            Ok(checkout) => {
+                let label = match git_ref {
+                    Some(git_ref) => format!("{}@{}", url, git_ref),
+                    None => url.clone(),
+                };
+                Some((label, checkout))
+            }
+            Err(e) => {
+                eprintln!("[error] Could not clone {}: {}", url, e);
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        _ => None,
+    };
+
+    let roots = match (&unpacked, &checkout, &args.start_paths_file) {
+        (Some((image, unpacked)), _, _) => vec![StartPath {
+            path: unpacked.rootfs.clone(),
+            label: image.clone(),
+        }],
+        (None, Some((label, checkout)), _) => vec![StartPath {
+            path: checkout.path().to_path_buf(),
+            label: label.clone(),
+        }],
+        (None, None, Some(file)) => match read_start_paths(Path::new(file)) {
+            Ok(roots) => roots,
+            Err(e) => {
+                eprintln!("[error] Failed to read {}: {}", file, e);
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        (None, None, None) => vec![StartPath {
+            path: PathBuf::from(&args.start_path),
+            label: args.start_path.clone(),
+        }],
+    };
+
+    // Registries and credentials of the user's .npmrc, and with
+    // --trust-project-npmrc those of the start paths
+    let npmrc = Npmrc::load(&roots.iter().map(|r| r.path.as_path()).collect::<Vec<_>>(), args.trust_project_npmrc);
+    if args.verbose {
+        for file in &npmrc.files {
+            eprintln!("[debug] Using registry settings from {}", file.display());
+        }
+    }
+    for file in &npmrc.ignored {
+        eprintln!("[warning] Ignoring {}, the .npmrc of scanned projects is only read with --trust-project-npmrc", file.display());
+    }
+
+    let discovery = match (build_globs(&args.include), build_globs(&args.exclude)) {
+        (Ok(include), Ok(exclude)) => Discovery {
+            files: args
+                .ecosystem
+                .iter()
+                .flat_map(|e| e.project_files())
+                .chain(if args.no_sbom { &[][..] } else { &sbom::FILES[..] })
+                .copied()
+                .collect(),
+            root_only: args.root_only,
+            no_ignore: args.no_ignore,
+            include: (!args.include.is_empty()).then_some(include),
+            exclude,
+        },
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("[error] Invalid --include/--exclude pattern: {}", e);
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    // Directories found under more than one start path keep the first label
+    let mut dirs: Vec<String> = Vec::new();
+    let mut projects: HashMap<String, String> = HashMap::new();
+    let mut any_unreadable = false;
+    // Projects read in memory, from a git revision or a source archive, with
+    // their `tarball::FILES` and what they install
+    let mut in_memory_files: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    let mut in_memory_installs: HashMap<String, HashMap<String, HashSet<String>>> = HashMap::new();
+    if args.git_ref.is_some() && args.ecosystem.iter().any(|e| *e != Ecosystem::Npm) {
+        eprintln!("[warning] --git-ref only reads npm manifests and lockfiles, other ecosystems are not checked");
+    }
+    for root in &roots {
+        if !root.path.exists() {
+            eprintln!("[warning] Start path {} does not exist", root.path.display());
+            continue;
+        }
+        let (found, unreadable) = match &args.git_ref {
+            Some(rev) => match git_tree_dirs(&root.path, rev, &discovery) {
+                Ok(trees) => {
+                    let found = trees.keys().cloned().collect();
+                    in_memory_installs.extend(trees.keys().map(|d| (d.clone(), HashMap::new())));
+                    in_memory_files.extend(trees);
+                    (found, Vec::new())
+                }
+                Err(e) => {
+                    eprintln!("[error] Could not read {} at {}: {}", root.path.display(), rev, e);
+                    return Ok(ExitCode::FAILURE);
+                }
+            },
+            None => find_dirs(&root.path, &discovery),
+        };
+        if !unreadable.is_empty() {
+            report_unreadable(&root.path, &unreadable);
+            any_unreadable = true;
+        }
+        let found = if args.nx || args.nx_affected {
+            nx_scope(&root.path, found, args.nx_affected.then_some(args.base.as_str()), args.verbose)
+        } else {
+            found
+        };
+        let found = if args.turbo || !args.turbo_filter.is_empty() {
+            match turbo_scope(&root.path, found, &args.turbo_filter, args.verbose) {
+                Ok(found) => found,
+                Err(e) => {
+                    eprintln!("[error] Invalid --turbo-filter: {}", e);
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        } else {
+            found
+        };
+        let label = match &args.git_ref {
+            Some(rev) => format!("{}@{}", root.label, rev),
+            None => root.label.clone(),
+        };
+        for d in found {
+            if !projects.contains_key(&d) {
+                projects.insert(d.clone(), label.clone());
+                dirs.push(d);
+            }
+        }
+    }
+    // Projects in a source archive are labelled by their folder in it, e.g. project.zip/web
+    for file in &args.scan_archive {
+        match archive::read(Path::new(file)) {
+            Ok(found) => {
+                for project in found {
+                    let d = Path::new(file).join(&project.dir).display().to_string();
+                    if !projects.contains_key(&d) {
+                        projects.insert(d.clone(), file.clone());
+                        in_memory_files.insert(d.clone(), project.files);
+                        in_memory_installs.insert(d.clone(), project.installed);
+                        dirs.push(d);
+                    }
+                }
+            }
+            Err(e) => eprintln!("[warning] Could not read archive {}: {}", file, e),
+        }
+    }
+    if let Some((image, unpacked)) = &unpacked {
+        for d in unpacked.install_roots.iter().map(|r| r.display().to_string()) {
+            if !projects.contains_key(&d) {
+                projects.insert(d.clone(), image.clone());
+                dirs.push(d);
+            }
+        }
+    }
+    dirs.sort();
+
+    // Packed packages are checked like projects, labelled by their path
+    let mut tarballs: Vec<(String, String)> = args.scan_tarball.iter().map(|f| (f.clone(), f.clone())).collect();
+    if !args.no_tarball_discovery && args.git_ref.is_none() && args.ecosystem.contains(&Ecosystem::Npm) {
+        for d in &dirs {
+            for path in tarball::discover(Path::new(d)) {
+                let label = path.display().to_string();
+                if !tarballs.iter().any(|(t, _)| *t == label) {
+                    tarballs.push((label, projects[d].clone()));
+                }
+            }
+        }
+    }
+
+    eprintln!("Directories to be checked:");
+    for d in &dirs {
+        eprintln!("  {}", d);
+    }
+    if !tarballs.is_empty() {
+        eprintln!("Tarballs to be checked:");
+        for (t, _) in &tarballs {
+            eprintln!("  {}", t);
+        }
+    }
+
+    if any_unreadable && args.fail_on_unreadable {
+        eprintln!("[error] Unreadable paths found and --fail-on-unreadable is set");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if args.list_dirs {
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if dirs.is_empty() && tarballs.is_empty() {
+        eprintln!("[warning] No project directories found");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Search the live inventory: every package the manifests, lockfiles and
+    // node_modules of the scanned projects know of
+    if let Some(Commands::Search { terms, .. }) = &args.command {
+        let hits: Vec<search::Hit> = dirs
+            .par_iter()
+            .flat_map_iter(|d| {
+                let preload = match in_memory_files.get(d) {
+                    Some(files) => content_preload(files, args.section.clone()),
+                    None => read_preload(d, args.section.clone(), &args.ecosystem),
+                };
+                let installed = installed_for(d, &in_memory_installs, args.no_npm);
+                let own = preload.pkg_json.as_ref().map(|p| (p.get("name"), p.get("version")));
+                let mut names = project_names(&preload);
+                names.extend(installed.keys().cloned());
+                let mut hits: Vec<search::Hit> = Vec::new();
+                for name in names.into_iter().filter(|n| search::matches(n, terms)) {
+                    let mut versions: HashSet<String> = lockfile_versions(&name, &preload).into_values().flatten().collect();
+                    versions.extend(installed.get(&name).into_iter().flatten().cloned());
+                    if versions.is_empty() {
+                        versions.insert(match own {
+                            Some((Some(own_name), Some(version))) if own_name == name.as_str() => {
+                                version.as_str().unwrap_or("").to_string()
+                            }
+                            _ => get_pkg_range(&name, &preload),
+                        });
+                    }
+                    hits.extend(versions.into_iter().map(|version| search::Hit {
+                        package: name.clone(),
+                        version,
+                        location: d.clone(),
+                    }));
+                }
+                hits
+            })
+            .collect();
+        search::print(&hits);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Compare the direct dependencies with the latest releases in the registry
+    if matches!(args.command, Some(Commands::Outdated)) {
+        // (location, package, declared range, highest locked or installed version)
+        let dependencies: Vec<(String, String, String, Option<String>)> = dirs
+            .par_iter()
+            .flat_map_iter(|d| {
+                let preload = match in_memory_files.get(d) {
+                    Some(files) => content_preload(files, args.section.clone()),
+                    None => read_preload(d, args.section.clone(), &args.ecosystem),
+                };
+                let installed = installed_for(d, &in_memory_installs, args.no_npm);
+                let mut declared: Vec<(String, String)> = Vec::new();
+                if let Some(data) = &preload.pkg_json {
+                    for section in &preload.sections {
+                        for (name, spec) in section_entries(data, section).into_iter().flatten() {
+                            let (name, range) = declared_package(&name, spec.as_str().unwrap_or(""));
+                            declared.push((name.to_string(), range.to_string()));
+                        }
+                    }
+                }
+                if let Some(deno_project) = &preload.deno {
+                    declared.extend(deno_project.imports.iter().cloned());
+                }
+                // Git, URL, file and workspace specs are not installed from the registry
+                declared.retain(|(_, range)| specifier::kind(range).is_none() && !range.contains(':'));
+                declared.sort();
+                declared.dedup();
+                declared
+                    .into_iter()
+                    .map(|(name, range)| {
+                        let mut versions: HashSet<String> = lockfile_versions(&name, &preload).into_values().flatten().collect();
+                        versions.extend(installed.get(&name).into_iter().flatten().cloned());
+                        let current = versions.into_iter().max_by_key(|v| parse_semver(v));
+                        (d.clone(), name, range, current)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let packuments: PackumentCache = Mutex::new(HashMap::new());
+        let names: HashSet<&str> = dependencies.iter().map(|(_, name, _, _)| name.as_str()).collect();
+        names.par_iter().for_each(|name| fetch_packument(name, &packuments, &npmrc, args.verbose));
+        let cache = packuments.lock().unwrap();
+        let unknown = names.iter().filter(|name| cache.get(**name).is_none_or(|p| p.is_none())).count();
+        if unknown > 0 {
+            eprintln!("[warning] {} of {} packages could not be looked up in the registry", unknown, names.len());
+        }
+        let entries: Vec<outdated::Entry> = dependencies
+            .iter()
+            .filter_map(|(location, name, range, current)| {
+                let packument = cache.get(name)?.as_ref()?;
+                outdated::Entry::new(name, location, range, current.as_deref(), packument)
+            })
+            .collect();
+        outdated::write(Path::new(outdated::FILE_NAME), &entries)?;
+        outdated::print(&entries);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    // Read and merge the package files, remembering what was read for the verdicts
+    let mut packages = PackageList::default();
+    let mut list_hashes: BTreeMap<String, String> = BTreeMap::new();
+    if !args.no_builtin_db {
+        list_hashes.insert(db::SOURCE.to_string(), db::sha256(&cache_dir));
+        let list = db::load(&cache_dir, args.verbose);
+        if args.verbose {
+            eprintln!("[debug] Loaded {} known compromised packages", list.len());
+        }
+        packages.extend(list, db::SOURCE);
+    }
+    for package_file in &args.package_file {
+        let packages_file_path = if http::is_url(package_file) {
+            match packages::fetch_package_file(package_file, &args.package_file_headers, &cache_dir, args.verbose) {
+                Ok(path) => path,
+                Err(e) => {
+                    eprintln!("[error] Failed to download {}: {}", package_file, e);
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+        } else {
+            PathBuf::from(package_file)
+        };
+        if let Ok(hash) = digest::sha256_file(&packages_file_path) {
+            list_hashes.insert(package_file.clone(), hash);
+        }
+        match packages::load_packages(&packages_file_path, &args.osv_ecosystem) {
+            Ok(list) => {
+                if args.verbose {
+                    eprintln!("[debug] Loaded {} packages from {}", list.len(), package_file);
+                }
+                packages.extend(list, package_file);
+            }
+            Err(e) => {
+                eprintln!("[error] Failed to read {} at {}: {}", package_file, packages_file_path.display(), e);
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    for q in &args.osv_query {
+        match osv::query(q, &args.osv_ecosystem) {
+            Ok(value) => {
+                let list = osv::parse(&value, &args.osv_ecosystem);
+                if args.verbose {
+                    eprintln!("[debug] Loaded {} packages from OSV query {}", list.len(), q);
+                }
+                packages.extend(list, &format!("osv:{}", q));
+            }
+            Err(e) => {
+                eprintln!("[error] OSV query {} failed: {}", q, e);
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    for id in &args.ghsa_id {
+        match ghsa::fetch(id) {
+            Ok(value) => {
+                let list = ghsa::parse(&value, &args.osv_ecosystem);
+                if args.verbose {
+                    eprintln!("[debug] Loaded {} packages from GitHub advisory {}", list.len(), id);
+                }
+                packages.extend(list, &format!("ghsa:{}", id));
+            }
+            Err(e) => {
+                eprintln!("[error] Fetching GitHub advisory {} failed: {}", id, e);
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    let severity_map = match &args.severity_map {
+        Some(file) => match SeverityMap::load(Path::new(file)) {
+            Ok(map) => map,
+            Err(e) => {
+                eprintln!("[error] Failed to read {}: {}", file, e);
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        None => SeverityMap::default(),
+    };
+
+    for allow_file in &args.allow_file {
+        if let Ok(hash) = digest::sha256_file(Path::new(allow_file)) {
+            list_hashes.insert(allow_file.clone(), hash);
+        }
+        match packages::load_allow_file(Path::new(allow_file)) {
+            Ok(list) => {
+                if args.verbose {
+                    eprintln!("[debug] Loaded {} exemptions from {}", list.len(), allow_file);
+                }
+                packages.extend(list, allow_file);
+            }
+            Err(e) => {
+                eprintln!("[error] Failed to read {}: {}", allow_file, e);
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    if packages.is_empty() && args.audit.is_empty() && !args.with_npm_audit {
+        eprintln!("[error] No valid packages found, pass --package-file or --audit, or drop --no-builtin-db");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if args.verbose && args.package_file.len() > 1 {
+        eprintln!("[debug] Merged {} packages from {} package files", packages.len(), args.package_file.len());
+    }
+
+    // Read the previous report now, the scan overwrites it when it is output.csv
+    let previous = match &args.previous_report {
+        Some(file) => match stale::previous_findings(Path::new(file)) {
+            Ok(findings) => findings,
+            Err(e) => {
+                eprintln!("[error] Failed to read {}: {}", file, e);
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        None => Vec::new(),
+    };
+
+    // Preload lock files and package.json
+    let mut preloads: HashMap<String, Preload> = HashMap::new();
+    let mut project_tools: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    let mut excludes: Vec<(PathBuf, Box<dyn matcher::Matcher>)> = Vec::new();
+    for d in &dirs {
+        if let Some(files) = in_memory_files.get(d) {
+            preloads.insert(d.clone(), content_preload(files, args.section.clone()));
+            continue;
+        }
+        let mut preload = read_preload(d, args.section.clone(), &args.ecosystem);
+        let dir_path = Path::new(d);
+        if args.use_mvn
+            && let Some(project) = &mut preload.maven
+            && project.needs_mvn()
+        {
+            match maven::dependency_list(dir_path) {
+                Ok(artifacts) => project.locked.extend(artifacts.into_iter().map(|(name, version)| maven::Locked {
+                    name,
+                    version,
+                    file: maven::DEPENDENCY_LIST.to_string(),
+                })),
+                Err(e) => eprintln!("[warning] mvn dependency:list failed in {}: {}", d, e),
+            }
+        }
+        if !args.no_sbom {
+            for result in sbom::load(dir_path) {
+                match result {
+                    Ok(sbom) => preload.sboms.push(sbom),
+                    Err(e) => eprintln!("[warning] Could not read an SBOM of {}: {}", d, e),
+                }
+            }
+        }
+        let pinned = toolchain::project_versions(dir_path);
+        if !pinned.is_empty() {
+            project_tools.insert(d.clone(), pinned);
+        }
+        match config::load_dir(dir_path) {
+            Ok(Some(overrides)) => {
+                let source = dir_path.join(config::DIR_FILE_NAME).display().to_string();
+                if args.verbose {
+                    eprintln!("[debug] Applying overrides from {}", source);
+                }
+                if let Some(sections) = overrides.sections {
+                    preload.sections = selected_sections(&sections, &[]);
+                }
+                for pattern in &overrides.exclude {
+                    match matcher::parse(&format!("glob:{}", pattern)) {
+                        Ok(m) => excludes.push((dir_path.to_path_buf(), m)),
+                        Err(e) => eprintln!("[warning] Skipping exclude in {}: {}", source, e),
+                    }
+                }
+                // Suppressions only apply inside the project that declares them
+                let mut allow = packages::parse_text(overrides.allow.join("\n").as_bytes(), &source);
+                allow.retain_mut(|entry| {
+                    let sub = entry.exempt.take().flatten();
+                    match config::allow_path(dir_path, sub.as_deref()) {
+                        Some(path) => {
+                            entry.exempt = Some(Some(path.display().to_string()));
+                            true
+                        }
+                        None => {
+                            let sub = sub.unwrap_or_default();
+                            eprintln!("[warning] Skipping allow entry for {} in {}: {} is outside the project", entry.name, source, sub);
+                            false
+                        }
+                    }
+                });
+                packages.extend(allow, &source);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("[warning] Ignoring {}: {}", dir_path.join(config::DIR_FILE_NAME).display(), e),
+        }
+        preloads.insert(d.clone(), preload);
+    }
+    for (label, project) in tarballs {
+        match tarball::read(Path::new(&label)) {
+            Ok(packed) => {
+                preloads.insert(label.clone(), content_preload(&packed.files, args.section.clone()));
+                in_memory_installs.insert(label.clone(), packed.bundled);
+                projects.insert(label.clone(), project);
+                dirs.push(label);
+            }
+            Err(e) => eprintln!("[warning] Could not read tarball {}: {}", label, e),
+        }
+    }
+
+    // Drop directories excluded by the overrides of a project they are in
+    let mut skipped: BTreeMap<String, &str> = BTreeMap::new();
+    dirs.retain(|d| {
+        let excluded = excludes.iter().any(|(base, m)| {
+            Path::new(d)
+                .strip_prefix(base)
+                .ok()
+                .and_then(|rel| rel.to_str())
+                .is_some_and(|rel| !rel.is_empty() && m.matches(rel))
+        });
+        if excluded {
+            skipped.insert(d.clone(), "excluded by a parent project's .package-checker.toml");
+        }
+        !excluded
+    });
+
+    if args.verbose {
+        eprintln!("[debug] Preloaded lockfiles and package.json for {} directories", preloads.len());
+    }
+
+    if let Some(filter) = &args.project_filter {
+        let project_matcher = match matcher::parse(filter) {
+            Ok(m) => m,
+            Err(e) => {
+                eprintln!("[error] Invalid --project-filter: {}", e);
+                return Ok(ExitCode::FAILURE);
+            }
+        };
+        dirs.retain(|d| {
+            let name = preloads[d]
+                .pkg_json
+                .as_ref()
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+                .or_else(|| preloads[d].cargo.as_ref().and_then(|p| p.name.as_deref()))
+                .or_else(|| preloads[d].go.as_ref().and_then(|m| m.path.as_deref()))
+                .or_else(|| preloads[d].composer.as_ref().and_then(|p| p.name.as_deref()));
+            let keep = name.is_some_and(|n| project_matcher.matches(n));
+            if !keep {
+                skipped.insert(d.clone(), "does not match --project-filter");
+            }
+            keep
+        });
+        if args.verbose {
+            eprintln!("[debug] {} directories match --project-filter {}", dirs.len(), filter);
+        }
+        if dirs.is_empty() {
+            // Still scanned, so the report is rewritten without findings
+            // instead of leaving the one of a previous run in place
+            eprintln!("[warning] No projects match --project-filter {}", filter);
+        }
+    }
+
+    // Look up the packages of the projects in the vulnerability databases:
+    // OSV by locked and installed version, GitHub by name
+    if !args.audit.is_empty() {
+        let known: Vec<(String, HashSet<String>)> = dirs
+            .par_iter()
+            .flat_map_iter(|d| {
+                let installed = installed_for(d, &in_memory_installs, args.no_npm);
+                known_versions(&preloads[d], &installed)
+            })
+            .collect();
+        audit::extend(&mut packages, &args.audit, known, args.verbose);
+    }
 
-fn walk_deps(obj: &Value, name: &str, versions: &mut HashSet<String>) {
-    match obj {
-        Value::Object(map) => {
-            if let Some(nm) = map.get("name").and_then(|n| n.as_str()) {
-                if nm.starts_with(&format!("{}@", name)) {
-                    let parts: Vec<&str> = nm.split('@').collect();
-                    if parts.len() == 2 && Regex::new(r"^\d+\.\d+\.\d+$").unwrap().is_match(parts[1]) {
-                        versions.insert(parts[1].to_string());
+    // Add the advisories npm audit reports for the npm projects
+    if args.with_npm_audit {
+        let audited: Vec<(String, io::Result<Vec<packages::ListEntry>>)> = dirs
+            .par_iter()
+            .filter(|d| !in_memory_installs.contains_key(*d) && preloads[*d].pkg_json.is_some())
+            .map(|d| (d.clone(), run_npm_audit(&NpmTarget::new(d, preloads[d].pkg_json.as_ref()))))
+            .collect();
+        for (d, result) in audited {
+            match result {
+                Ok(list) => {
+                    if args.verbose {
+                        eprintln!("[debug] npm audit reported {} vulnerable ranges in {}", list.len(), d);
                     }
+                    packages.extend(list, npm_audit::SOURCE);
                 }
+                Err(e) => eprintln!("[warning] npm audit failed in {}: {}", d, e),
             }
-            for (_, v) in map {
-                walk_deps(v, name, versions);
+        }
+    }
+
+    // npm, yarn, pnpm and Lerna workspace members are installed through the
+    // lockfile of their root: matches found there are attributed to the
+    // members using them, and members scanned without their root resolve
+    // through it. A pnpm root lists its members as importers in the lockfile.
+    let has_importers = |d: &str| preloads[d].pnpm.as_ref().is_some_and(|l| l.importers.keys().any(|k| k != "."));
+    let mut workspace_members: HashMap<String, Vec<workspaces::Member>> = HashMap::new();
+    let mut hoisted: HashMap<String, workspaces::Member> = HashMap::new();
+    let mut covered_members: HashSet<String> = HashSet::new();
+    let mut root_installs: HashMap<PathBuf, HashMap<String, HashSet<String>>> = HashMap::new();
+    // Package name of the workspace every member directory belongs to
+    let mut owners: HashMap<String, String> = HashMap::new();
+    for d in dirs.iter().filter(|d| !in_memory_installs.contains_key(*d)) {
+        let Some(manifest) = preloads[d].pkg_json.as_ref() else {
+            continue;
+        };
+        let dir_path = Path::new(d);
+        let Some(root) = workspaces::root(dir_path)
+            .or_else(|| workspaces::pnpm_root(dir_path))
+            .or_else(|| workspaces::lerna_root(dir_path))
+        else {
+            continue;
+        };
+        if let Some(name) = manifest.get("name").and_then(|n| n.as_str()) {
+            owners.insert(d.clone(), name.to_string());
+        }
+        // A member with a lockfile of its own is installed from it, as Lerna
+        // packages bootstrapped without hoisting are
+        if !present_lockfiles(&preloads[d]).is_empty() || preloads[d].bun.is_some() {
+            if args.verbose {
+                eprintln!("[debug] {} is in the workspace of {} but has its own lockfile", d, root.display());
             }
+            continue;
         }
-        Value::Array(arr) => {
-            for item in arr {
-                walk_deps(item, name, versions);
+        let Ok(dir) = dir_path.canonicalize() else {
+            continue;
+        };
+        let scanned_root = dirs.iter().find(|r| Path::new(r).canonicalize().is_ok_and(|r| r == root));
+        if scanned_root.is_some_and(|r| has_importers(r)) {
+            covered_members.insert(d.clone());
+            continue;
+        }
+        let unscanned;
+        let root_preload = match scanned_root {
+            Some(r) => &preloads[r],
+            None => {
+                unscanned = read_preload(&root.display().to_string(), Vec::new(), &[Ecosystem::Npm]);
+                &unscanned
+            }
+        };
+        let mut member = workspaces::Member::new(&root, &dir, manifest, workspace_lockfiles(root_preload));
+        // Lerna's hoisting installs the dependencies of its packages at the root
+        if !args.no_npm && workspaces::lerna_hoists(&root) {
+            let installed = root_installs.entry(root.clone()).or_insert_with(|| node_modules::installed(&root));
+            member.add_hoisted(manifest, installed);
+        }
+        match scanned_root {
+            Some(r) => {
+                workspace_members.entry(r.clone()).or_default().push(member);
+                covered_members.insert(d.clone());
+            }
+            None => {
+                if args.verbose {
+                    eprintln!(
+                        "[debug] {} is workspace {} of {}, which is not scanned: resolving {} package(s) through it",
+                        d,
+                        member.name,
+                        root.display(),
+                        member.resolved.len()
+                    );
+                }
+                hoisted.insert(d.clone(), member);
             }
         }
-        _ => {}
     }
-}
+    for d in dirs.iter().filter(|d| has_importers(d)) {
+        let Ok(root) = Path::new(d).canonicalize() else {
+            continue;
+        };
+        let lockfiles = workspace_lockfiles(&preloads[d]);
+        let members = preloads[d].pnpm.iter().flat_map(|l| l.importers.keys()).filter(|path| *path != ".").map(|path| {
+            let dir = root.join(path);
+            let manifest: Value = File::open(dir.join("package.json"))
+                .ok()
+                .and_then(|f| serde_json::from_reader(f).ok())
+                .unwrap_or_default();
+            workspaces::Member::new(&root, &dir, &manifest, lockfiles)
+        });
+        workspace_members.entry(d.clone()).or_default().extend(members);
+    }
 
-fn get_npm_versions(dirpath: &str, name: &str) -> HashSet<String> {
-    let mut versions: HashSet<String> = HashSet::new();
-    let output = match Command::new("npm")
-        .args(["ls", "--json", name, "--depth=Infinity"])
-        .current_dir(dirpath)
-        .output()
-    {
-        Ok(o) if o.status.success() => o.stdout,
-        _ => return versions,
-    };
-    let output_str = match std::str::from_utf8(&output) {
-        Ok(s) => s,
-        _ => return versions,
+    // Lockfiles of several package managers make installs depend on who runs them
+    let mut mixed: Vec<Value> = Vec::new();
+    for d in &dirs {
+        let Some(found) = hygiene::mixed_lockfiles(Path::new(d), preloads[d].pkg_json.as_ref()) else {
+            continue;
+        };
+        let ci = match &found.ci {
+            Some((manager, source)) => format!("CI uses {} ({})", manager, source),
+            None => "no CI install command found".to_string(),
+        };
+        eprintln!("[warning] {} has lockfiles of several package managers: {}; {}", d, found.lockfiles.join(", "), ci);
+        mixed.push(json!({
+            "location": d,
+            "lockfiles": found.lockfiles,
+            "ci_manager": found.ci.as_ref().map(|(m, _)| m),
+            "ci_source": found.ci.as_ref().map(|(_, s)| s),
+        }));
+    }
+
+    let unparseable: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+    let matching = Matching {
+        include_prerelease: args.include_prerelease,
+        loose: args.loose_versions,
+        mode: args.match_mode,
+        unparseable: &unparseable,
     };
-    let data: Value = match serde_json::from_str(output_str) {
-        Ok(d) => d,
-        _ => return versions,
+    let checks = Checks {
+        packages: &packages,
+        matching,
+        severity_map: &severity_map,
     };
-    walk_npm(&data, name, &mut versions);
-    versions
-}
 
-fn walk_npm(obj: &Value, name: &str, versions: &mut HashSet<String>) {
-    if let Value::Object(map) = obj {
-        if let Some(deps) = map.get("dependencies").and_then(|d| d.as_object()) {
-            for (k, v) in deps {
-                if k == name {
-                    if let Some(ver) = v.get("version").and_then(|vv| vv.as_str()) {
-                        versions.insert(ver.to_string());
-                    }
+    // Compare the lockfiles with their reviewed copies
+    let mut drift_rows: Vec<Row> = Vec::new();
+    if let Some(spec) = &args.trusted_lock {
+        let mut drift_lines: Vec<String> = Vec::new();
+        for d in &dirs {
+            let is_start_path = roots.iter().any(|r| r.path == Path::new(d));
+            match trusted_lock::check(spec, d, &projects[d], &preloads[d], is_start_path, checks) {
+                Ok((lines, rows)) => {
+                    drift_lines.extend(lines);
+                    drift_rows.extend(rows);
+                }
+                Err(e) => {
+                    eprintln!("[error] Could not read the trusted lockfile {} for {}: {}", spec, d, e);
+                    return Ok(ExitCode::FAILURE);
                 }
-                walk_npm(v, name, versions);
             }
         }
-    }
-}
-
-fn main() -> io::Result<()> {
-    let args = Args::parse();
-
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(args.jobs)
-        .build_global()
-        .unwrap();
-
-    if args.verbose {
-        eprintln!("[debug] Using {} threads", args.jobs);
+        if drift_lines.is_empty() {
+            println!("Lockfiles match the trusted lockfile {}", spec);
+        } else {
+            println!("Lockfile changes compared with {}:", spec);
+            for line in &drift_lines {
+                println!("  {}", line);
+            }
+        }
     }
 
-    println!("Checking for npm packages and lockfile/package.json/DEPENDENCIES.json compatibility in this project and subfolders...");
-
-    let start_path = Path::new(&args.start_path);
-    let dirs = find_dirs(start_path, args.root_only);
-
-    eprintln!("Directories to be checked:");
-    for d in &dirs {
-        eprintln!("  {}", d);
+    // Compare the declared ranges with what the lockfiles resolve
+    if args.check_lockfile {
+        let mut drift_lines: Vec<String> = Vec::new();
+        for d in &dirs {
+            for drift in manifest_drift(&preloads[d]) {
+                let reason = if drift.locked.is_empty() {
+                    format!("missing from {}", drift.lockfile)
+                } else {
+                    format!("{} pins {}, outside {}", drift.lockfile, drift.locked.join(", "), drift.range)
+                };
+                drift_lines.push(format!("{}: {} {}", d, drift.name, reason));
+                drift_rows.push(Row {
+                    package: drift.name.clone(),
+                    version: drift.range.clone(),
+                    location: d.clone(),
+                    match_package: packages.has_name(&drift.name),
+                    match_version: false,
+                    dependency: "lockfile-drift".to_string(),
+                    depended_by: String::new(),
+                    source: String::new(),
+                    reason,
+                    project: projects[d].clone(),
+                    allowed: String::new(),
+                    severity: String::new(),
+                    installed_size: None,
+                    transitive_deps: None,
+                    section: String::new(),
+                    workspace: String::new(),
+                    match_type: MatchType::Declared,
+                });
+            }
+        }
+        if drift_lines.is_empty() {
+            println!("Lockfiles match the ranges declared in package.json");
+        } else {
+            println!("package.json dependencies the lockfiles do not honour:");
+            for line in &drift_lines {
+                println!("  {}", line);
+            }
+        }
     }
 
-    if args.list_dirs {
-        return Ok(());
+    // Compare the tarballs pinned by the lockfiles, and with
+    // --verify-cached-tarballs those in the npm cache, with the registry
+    let packuments: PackumentCache = Mutex::new(HashMap::new());
+    let git_versions: GitVersionCache = Mutex::new(HashMap::new());
+    let mut tampered: Vec<(String, MatchType)> = Vec::new();
+    if args.verify_integrity || args.verify_cached_tarballs {
+        let npm_cache = args.verify_cached_tarballs.then(integrity::npm_cache_dir).flatten();
+        if args.verbose
+            && let Some(dir) = &npm_cache
+        {
+            eprintln!("[debug] Hashing cached tarballs in {}", dir.display());
+        }
+        let mut locked: Vec<(&String, integrity::Locked)> = Vec::new();
+        for d in &dirs {
+            let preload = &preloads[d];
+            let entries = preload
+                .plock
+                .iter()
+                .flat_map(|lock| integrity::package_lock(preload.plock_file, lock))
+                .chain(preload.yarn.iter().flat_map(integrity::yarn));
+            locked.extend(entries.filter(|l| l.is_checkable(&npmrc.hosts())).map(|l| (d, l)));
+        }
+        let names: HashSet<&str> = locked.iter().map(|(_, l)| l.name.as_str()).collect();
+        if args.verbose {
+            eprintln!("[debug] Verifying {} lockfile entries of {} packages", locked.len(), names.len());
+        }
+        names.par_iter().for_each(|name| fetch_packument(name, &packuments, &npmrc, args.verbose));
+        let cache = packuments.lock().unwrap();
+        let mut mismatch_lines: Vec<String> = Vec::new();
+        for (d, entry) in &locked {
+            let Some(packument) = cache.get(&entry.name).and_then(|p| p.as_ref()) else {
+                continue;
+            };
+            let (source, mismatch) = match integrity::mismatch(entry, packument) {
+                Some(mismatch) => (entry.lockfile, mismatch),
+                None => match npm_cache.as_deref().and_then(|dir| integrity::cache_mismatch(entry, packument, dir)) {
+                    Some(mismatch) => ("the npm cache", mismatch),
+                    None => continue,
+                },
+            };
+            mismatch_lines.push(format!("{}: {}@{} in {}: {}", d, entry.name, entry.version, source, mismatch));
+            tampered.push((
+                format!("{}:{}@{} ({} does not match the registry)", d, entry.name, entry.version, source),
+                MatchType::Locked,
+            ));
+            drift_rows.push(checks.finding_row(Finding {
+                name: &entry.name,
+                versions: std::slice::from_ref(&entry.version),
+                location: d,
+                project: &projects[*d],
+                check: "integrity",
+                reason: format!("{}: {}", source, mismatch),
+                match_type: MatchType::Locked,
+            }));
+        }
+        if mismatch_lines.is_empty() {
+            println!("Lockfile integrity matches the registry for {} entries", locked.len());
+        } else {
+            println!("Lockfile entries that do not match the registry:");
+            for line in &mismatch_lines {
+                println!("  {}", line);
+            }
+        }
     }
 
-    if dirs.is_empty() {
-        eprintln!("[warning] No directories found with package.json");
-        return Ok(());
+    // (location, name, version) of the locked and installed packages of npm registries
+    let npm_inventory = || {
+        let mut inventory: BTreeSet<(&String, String, String)> = BTreeSet::new();
+        for d in &dirs {
+            let installed = installed_for(d, &in_memory_installs, args.no_npm);
+            for (name, versions) in known_versions(&preloads[d], &installed) {
+                if matches!(matcher::split_namespace(&name).0, Namespace::Npm | Namespace::Jsr | Namespace::GitHub) {
+                    inventory.extend(versions.into_iter().map(|version| (d, name.clone(), version)));
+                }
+            }
+        }
+        inventory
+    };
+    // Look up the locked and installed npm versions in the registry for deprecations
+    let mut deprecated_count = 0;
+    if args.check_deprecated || args.fail_on_deprecated {
+        let inventory = npm_inventory();
+        let names: HashSet<&str> = inventory.iter().map(|(_, name, _)| name.as_str()).collect();
+        if args.verbose {
+            eprintln!("[debug] Looking up deprecations of {} versions of {} packages", inventory.len(), names.len());
+        }
+        names.par_iter().for_each(|name| fetch_packument(name, &packuments, &npmrc, args.verbose));
+        let cache = packuments.lock().unwrap();
+        let unknown = names.iter().filter(|name| cache.get(**name).is_none_or(|p| p.is_none())).count();
+        if unknown > 0 {
+            eprintln!("[warning] {} of {} packages could not be looked up in the registry", unknown, names.len());
+        }
+        let mut deprecated_lines: Vec<String> = Vec::new();
+        for (d, name, version) in &inventory {
+            let Some(message) = cache.get(name).and_then(|p| p.as_ref()).and_then(|p| p.deprecated.get(version)) else {
+                continue;
+            };
+            deprecated_lines.push(format!("{}: {}@{}: {}", d, name, version, message));
+            drift_rows.push(checks.finding_row(Finding {
+                name,
+                versions: std::slice::from_ref(version),
+                location: d,
+                project: &projects[*d],
+                check: "deprecated",
+                reason: format!("deprecated: {}", message),
+                match_type: MatchType::Locked,
+            }));
+        }
+        deprecated_count = deprecated_lines.len();
+        if deprecated_lines.is_empty() {
+            println!("No deprecated versions among {} locked and installed versions", inventory.len());
+        } else {
+            println!("Deprecated versions:");
+            for line in &deprecated_lines {
+                println!("  {}", line);
+            }
+        }
     }
 
-    // Read package file from start_path
-    let packages_file_path = Path::new(&args.package_file);
-    let packages_file = match File::open(&packages_file_path) {
-        Ok(file) => file,
-        Err(e) => {
-            eprintln!("[error] Failed to open {} at {}: {}", args.package_file, packages_file_path.display(), e);
-            return Ok(());
+    // Full packuments of the locked and installed npm packages, for their
+    // publish dates and maintainers
+    let history_inventory = (args.max_release_age_check.is_some() || args.check_maintainers)
+        .then(npm_inventory)
+        .unwrap_or_default();
+    let histories: HashMap<&str, registry::History> = {
+        let names: BTreeSet<&str> = history_inventory.iter().map(|(_, name, _)| name.as_str()).collect();
+        if args.verbose && !names.is_empty() {
+            eprintln!("[debug] Looking up the history of {} versions of {} packages", history_inventory.len(), names.len());
         }
-    };
-    let packages: HashSet<(String, String)> = BufReader::new(packages_file)
-        .lines()
-        .filter_map(|line| {
-            if let Ok(l) = line {
-                let parts: Vec<&str> = l.trim().split('@').collect();
-                if parts.len() == 2 {
-                    Some((parts[0].to_string(), parts[1].to_string()))
-                } else {
+        let histories: HashMap<&str, registry::History> = names
+            .par_iter()
+            .filter_map(|name| match registry::history(name, &npmrc) {
+                Ok(history) => Some((*name, history)),
+                Err(e) => {
                     if args.verbose {
-                        eprintln!("[warning] Invalid line in {}: {}", args.package_file, l);
+                        eprintln!("[debug] Could not look up the history of {}: {}", name, e);
                     }
                     None
                 }
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    if packages.is_empty() {
-        eprintln!("[error] No valid packages found in {} at {}", args.package_file, packages_file_path.display());
-        return Ok(());
-    }
-
-    if args.verbose {
-        eprintln!("[debug] Loaded {} packages from {}", packages.len(), args.package_file);
-    }
+            })
+            .collect();
+        if histories.len() < names.len() {
+            eprintln!("[warning] {} of {} packages could not be looked up in the registry", names.len() - histories.len(), names.len());
+        }
+        histories
+    };
 
-    // Preload lock files and package.json
-    let mut preloads: HashMap<String, Preload> = HashMap::new();
-    for d in &dirs {
-        let mut preload = Preload {
-            yarn: None,
-            plock: None,
-            pnpm: None,
-            deps: None,
-            pkg_json: None,
-        };
-        let dir_path = Path::new(d);
-        if let Ok(content) = fs::read_to_string(dir_path.join("yarn.lock")) {
-            preload.yarn = Some(content);
+    // Versions published very recently are how hijacked accounts spread
+    // malware before it is known, so they are flagged whether listed or not
+    let mut recent: Vec<(String, MatchType)> = Vec::new();
+    if let Some(max_age) = args.max_release_age_check {
+        let mut recent_lines: Vec<String> = Vec::new();
+        for (d, name, version) in &history_inventory {
+            let Some(published) = histories.get(name.as_str()).and_then(|h| h.times.get(version)) else {
+                continue;
+            };
+            let Some(age) = release_age::age_days(published).filter(|age| *age < max_age) else {
+                continue;
+            };
+            recent_lines.push(format!("{}: {}@{} published {} days ago ({})", d, name, version, age, published));
+            recent.push((format!("{}:{}@{} (published {} days ago)", d, name, version, age), MatchType::Locked));
+            drift_rows.push(checks.finding_row(Finding {
+                name,
+                versions: std::slice::from_ref(version),
+                location: d,
+                project: &projects[*d],
+                check: "recent-release",
+                reason: format!("published {}", published),
+                match_type: MatchType::Locked,
+            }));
         }
-        let plock_path = dir_path.join("package-lock.json");
-        if plock_path.is_file() {
-            if let Ok(file) = File::open(&plock_path) {
-                if let Ok(value) = serde_json::from_reader(file) {
-                    preload.plock = Some(value);
-                }
+        if recent_lines.is_empty() {
+            println!(
+                "No versions published in the last {} days among {} locked and installed versions",
+                max_age,
+                history_inventory.len()
+            );
+        } else {
+            println!("Versions published in the last {} days:", max_age);
+            for line in &recent_lines {
+                println!("  {}", line);
             }
         }
-        if let Ok(content) = fs::read_to_string(dir_path.join("pnpm-lock.yaml")) {
-            preload.pnpm = Some(content);
+    }
+
+    // A release by someone who did not maintain the previous one is how a
+    // hijacked or handed over package usually shows
+    if args.check_maintainers {
+        let mut changed_lines: Vec<String> = Vec::new();
+        for (d, name, version) in &history_inventory {
+            let Some(history) = histories.get(name.as_str()) else {
+                continue;
+            };
+            let Some(previous) = history.previous(version) else {
+                continue;
+            };
+            let (Some(before), Some(now)) = (history.maintainers.get(previous), history.maintainers.get(version)) else {
+                continue;
+            };
+            let mut changes: Vec<String> = Vec::new();
+            if let Some(publisher) = history.publishers.get(version).filter(|p| !before.contains(p)) {
+                changes.push(format!("published by {}, who did not maintain {}", publisher, previous));
+            }
+            let added: Vec<&str> = now.iter().filter(|m| !before.contains(m)).map(|m| m.as_str()).collect();
+            if !added.is_empty() {
+                changes.push(format!("added {}", added.join(", ")));
+            }
+            let removed: Vec<&str> = before.iter().filter(|m| !now.contains(m)).map(|m| m.as_str()).collect();
+            if !removed.is_empty() {
+                changes.push(format!("removed {}", removed.join(", ")));
+            }
+            if changes.is_empty() {
+                continue;
+            }
+            let note = format!("maintainers changed since {}: {}", previous, changes.join("; "));
+            changed_lines.push(format!("{}: {}@{}: {}", d, name, version, note));
+            drift_rows.push(checks.finding_row(Finding {
+                name,
+                versions: std::slice::from_ref(version),
+                location: d,
+                project: &projects[*d],
+                check: "maintainer-change",
+                reason: note,
+                match_type: MatchType::Locked,
+            }));
         }
-        if let Ok(content) = fs::read_to_string(dir_path.join("DEPENDENCIES.json")) {
-            preload.deps = Some(content);
+        for line in &changed_lines {
+            eprintln!("[warning] {}", line);
         }
-        let pj_path = dir_path.join("package.json");
-        if pj_path.is_file() {
-            if let Ok(file) = File::open(&pj_path) {
-                if let Ok(value) = serde_json::from_reader(file) {
-                    preload.pkg_json = Some(value);
-                }
-            }
+        if changed_lines.is_empty() {
+            println!("No maintainer changes among {} locked and installed versions", history_inventory.len());
         }
-        preloads.insert(d.clone(), preload);
     }
 
-    if args.verbose {
-        eprintln!("[debug] Preloaded lockfiles and package.json for {} directories", preloads.len());
+    // Names one typo away from a popular package
+    if args.detect_typosquats {
+        let popular = typosquat::popular();
+        let mut squat_lines: Vec<String> = Vec::new();
+        for d in &dirs {
+            let installed = installed_for(d, &in_memory_installs, args.no_npm);
+            let known = known_versions(&preloads[d], &installed);
+            let (lines, rows) = typosquat::check(d, &projects[d], &preloads[d], known, &popular, checks);
+            squat_lines.extend(lines);
+            drift_rows.extend(rows);
+        }
+        if squat_lines.is_empty() {
+            println!("No dependency names resemble the {} bundled popular packages", popular.len());
+        } else {
+            println!("Possible typosquats:");
+            for line in &squat_lines {
+                println!("  {}", line);
+            }
+        }
     }
 
     // Prepare for parallel processing
-    let rows_mutex: Mutex<Vec<(String, String, String, bool, bool, String, String)>> = Mutex::new(Vec::new());
-    let found_mutex: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    let rows_mutex: Mutex<Vec<Row>> = Mutex::new(drift_rows);
+    tampered.extend(recent);
+    let found_mutex: Mutex<Vec<(String, MatchType)>> = Mutex::new(tampered);
+    let evidence_mutex: Mutex<Vec<Value>> = Mutex::new(Vec::new());
+    let blame_mutex: Mutex<Vec<Value>> = Mutex::new(Vec::new());
 
     dirs.par_iter().for_each(|d| {
         let preload = preloads.get(d).unwrap();
+        let project = &projects[d];
         let pkg_json = preload.pkg_json.as_ref();
 
         // Process main package from package.json
@@ -414,166 +3200,567 @@ fn main() -> io::Result<()> {
             let name = data.get("name").and_then(|n| n.as_str()).unwrap_or("");
             let version = data.get("version").and_then(|v| v.as_str()).unwrap_or("");
             if !name.is_empty() && !version.is_empty() {
-                let match_package = packages.iter().any(|(pkg_name, _)| pkg_name == name);
-                let match_version = packages.contains(&(name.to_string(), version.to_string()));
+                let match_package = packages.has_name(name);
+                let match_version = packages.iter().any(|p| p.matches(name) && satisfies_range(version, &p.version, matching));
+                let listed = || {
+                    packages
+                        .iter()
+                        .filter(|p| p.matches(name) && (!match_version || satisfies_range(version, &p.version, matching)))
+                };
+                let source = packages::join_sources(listed());
+                let reason = packages::join_reasons(listed());
+                let severity = severity_label(&severity_map, listed());
+                let allowed = match_version
+                    .then(|| exemption_reason(&packages, name, version, d, matching))
+                    .flatten()
+                    .unwrap_or_default();
 
-                rows_mutex.lock().unwrap().push((
-                    name.to_string(),
-                    version.to_string(),
-                    d.to_string(),
+                rows_mutex.lock().unwrap().push(Row {
+                    package: name.to_string(),
+                    version: version.to_string(),
+                    location: d.to_string(),
                     match_package,
                     match_version,
-                    String::new(),
-                    String::new(),
-                ));
+                    dependency: String::new(),
+                    depended_by: String::new(),
+                    source,
+                    reason,
+                    project: project.clone(),
+                    allowed: allowed.clone(),
+                    severity,
+                    installed_size: None,
+                    transitive_deps: None,
+                    section: String::new(),
+                    workspace: String::new(),
+                    match_type: MatchType::Declared,
+                });
 
-                if match_package && match_version {
+                if match_package && match_version && allowed.is_empty() {
                     found_mutex
                         .lock()
                         .unwrap()
-                        .push(format!("{}:{}@{}", d, name, version));
+                        .push((format!("{}:{}@{}", d, name, version), MatchType::Declared));
                 }
 
-                // Process dependencies
-                if let Some(deps) = data.get("dependencies").and_then(|d| d.as_object()) {
-                    for (dep_name, dep_version) in deps {
-                        let dep_version = dep_version.as_str().unwrap_or("");
-                        let dep_version_clean = dep_version.trim_start_matches('^').trim_start_matches('~');
-                        let match_package = packages.iter().any(|(pkg_name, _)| pkg_name == dep_name);
-                        let match_version = packages.iter().any(|(pkg_name, pkg_version)| {
-                            pkg_name == dep_name && satisfies_range(dep_version_clean, pkg_version)
-                        });
+                // Process the dependency sections of package.json
+                let overrides = overrides::load(data);
+                for section in &preload.sections {
+                    let dependency = SECTIONS.iter().find(|(s, _)| s == section).map_or(section.as_str(), |(_, l)| l);
+                    let Some(deps) = section_entries(data, section) else {
+                        continue;
+                    };
+                    for (installed_as, dep_version) in &deps {
+                        // An alias installs the package it points at
+                        let (dep_name, declared) = declared_package(installed_as, dep_version.as_str().unwrap_or(""));
+                        // An override decides what gets installed instead of the declared range
+                        let overridden = overrides::for_dependency(&overrides, dep_name, declared);
+                        let dep_version = overridden.map_or(declared, |o| o.version.as_str());
+                        // Git, URL and file specifiers have no version to match, unless a
+                        // git dependency is resolved to the version of its package.json
+                        let unresolvable = specifier::kind(dep_version);
+                        let git_version = unresolvable
+                            .filter(|k| *k == specifier::Kind::Git && args.resolve_git_deps)
+                            .and_then(|_| git_dependency_version(dep_version, &git_versions));
+                        // A dist-tag such as `next` installs what the registry points it at today
+                        let tag_version = (args.resolve_dist_tags && specifier::is_dist_tag(dep_version))
+                            .then(|| dist_tag_version(dep_name, dep_version, &packuments, &npmrc, args.verbose))
+                            .flatten();
+                        // The range is matched with its operator, `--match-mode` tells `^1.2.0` from `1.2.0`
+                        let checked = git_version.as_deref().or(tag_version.as_deref()).unwrap_or(dep_version);
+                        let dep_version_clean = checked.trim_start_matches('^').trim_start_matches('~');
+                        let match_package = packages.has_name(dep_name);
+                        let mut matched: Vec<&Package> = packages
+                            .iter()
+                            .filter(|p| p.matches(dep_name) && satisfies_range(checked, &p.version, matching))
+                            .collect();
+                        let match_version = !matched.is_empty();
+                        if !match_version {
+                            matched = packages.iter().filter(|p| p.matches(dep_name)).collect();
+                        }
+                        let source = packages::join_sources(matched.iter().copied());
+                        let mut reason = packages::join_reasons(matched.iter().copied());
+                        if let Some(o) = overridden {
+                            let note = format!("{} pins {} instead of {}", o.field, o.version, declared);
+                            reason = if reason.is_empty() { note } else { format!("{}; {}", reason, note) };
+                        }
+                        if let Some(kind) = unresolvable {
+                            let note = match &git_version {
+                                Some(version) => format!("git dependency {} is at {}", dep_version, version),
+                                None => format!("unresolvable specifier ({})", kind.label()),
+                            };
+                            reason = if reason.is_empty() { note } else { format!("{}; {}", reason, note) };
+                        }
+                        if let Some(version) = &tag_version {
+                            let note = format!("dist-tag {} is at {}", dep_version, version);
+                            reason = if reason.is_empty() { note } else { format!("{}; {}", reason, note) };
+                        }
+                        let severity = severity_label(&severity_map, matched.iter().copied());
+                        let allowed = match_version
+                            .then(|| exemption_reason(&packages, dep_name, checked, d, matching))
+                            .flatten()
+                            .unwrap_or_default();
+                        let footprint = args
+                            .footprint
+                            .then(|| footprint::measure(Path::new(d), installed_as))
+                            .flatten();
 
-                        rows_mutex.lock().unwrap().push((
-                            dep_name.to_string(),
-                            dep_version_clean.to_string(),
-                            d.to_string(),
+                        rows_mutex.lock().unwrap().push(Row {
+                            package: dep_name.to_string(),
+                            version: dep_version_clean.to_string(),
+                            location: d.to_string(),
                             match_package,
                             match_version,
-                            "yes".to_string(),
-                            format!("{}@{}", name, version),
-                        ));
+                            dependency: dependency.to_string(),
+                            depended_by: format!("{}@{}", name, version),
+                            source,
+                            reason,
+                            project: project.clone(),
+                            allowed: allowed.clone(),
+                            severity,
+                            installed_size: footprint.as_ref().map(|f| f.size),
+                            transitive_deps: footprint.as_ref().map(|f| f.transitive),
+                            section: section.clone(),
+                            workspace: String::new(),
+                            match_type: MatchType::Declared,
+                        });
 
-                        if match_package && match_version {
+                        if match_package && match_version && allowed.is_empty() {
                             found_mutex
                                 .lock()
                                 .unwrap()
-                                .push(format!("{}:{}@{}", d, dep_name, dep_version_clean));
+                                .push((format!("{}:{}@{}", d, dep_name, dep_version_clean), MatchType::Declared));
+                            if args.blame {
+                                blame_mutex.lock().unwrap().extend(blame::commits(d, dep_name, dep_version_clean, preload));
+                            }
                         }
-                    }
-                }
 
-                // Process devDependencies
-                if let Some(deps) = data.get("devDependencies").and_then(|d| d.as_object()) {
-                    for (dep_name, dep_version) in deps {
-                        let dep_version = dep_version.as_str().unwrap_or("");
-                        let dep_version_clean = dep_version.trim_start_matches('^').trim_start_matches('~');
-                        let match_package = packages.iter().any(|(pkg_name, _)| pkg_name == dep_name);
-                        let match_version = packages.iter().any(|(pkg_name, pkg_version)| {
-                            pkg_name == dep_name && satisfies_range(dep_version_clean, pkg_version)
-                        });
-
-                        rows_mutex.lock().unwrap().push((
-                            dep_name.to_string(),
-                            dep_version_clean.to_string(),
-                            d.to_string(),
+                        // A fresh install without the lockfile may resolve the range differently
+                        if !args.fresh_resolve {
+                            continue;
+                        }
+                        let Some(fresh) = fresh_resolution(dep_name, dep_version, preload, &packuments, &npmrc, args.verbose)
+                        else {
+                            continue;
+                        };
+                        let flagged: Vec<&Package> = packages
+                            .iter()
+                            .filter(|p| p.matches(dep_name) && satisfies_range(&fresh.version, &p.version, matching))
+                            .collect();
+                        let newer = fresh
+                            .pinned
+                            .iter()
+                            .filter_map(|v| parse_version(v))
+                            .max()
+                            .is_none_or(|pin| parse_version(&fresh.version).is_some_and(|f| f > pin));
+                        if flagged.is_empty() && !newer {
+                            continue;
+                        }
+                        let pins = if fresh.pinned.is_empty() { "nothing".to_string() } else { fresh.pinned.join(", ") };
+                        let drift = format!("fresh install resolves {} to {}, lockfile pins {}", dep_version, fresh.version, pins);
+                        let reason = match packages::join_reasons(flagged.iter().copied()) {
+                            r if r.is_empty() => drift,
+                            r => format!("{}; {}", r, drift),
+                        };
+                        let allowed = exemption_reason(&packages, dep_name, &fresh.version, d, matching).filter(|_| !flagged.is_empty());
+                        rows_mutex.lock().unwrap().push(Row {
+                            package: dep_name.to_string(),
+                            version: fresh.version.clone(),
+                            location: d.to_string(),
                             match_package,
-                            match_version,
-                            "dev".to_string(),
-                            format!("{}@{}", name, version),
-                        ));
-
-                        if match_package && match_version {
+                            match_version: !flagged.is_empty(),
+                            dependency: "fresh".to_string(),
+                            depended_by: format!("{}@{}", name, version),
+                            source: packages::join_sources(flagged.iter().copied()),
+                            reason,
+                            project: project.clone(),
+                            allowed: allowed.clone().unwrap_or_default(),
+                            severity: severity_label(&severity_map, flagged.iter().copied()),
+                            installed_size: None,
+                            transitive_deps: None,
+                            section: section.clone(),
+                            workspace: String::new(),
+                            match_type: MatchType::Declared,
+                        });
+                        if !flagged.is_empty() && allowed.is_none() {
                             found_mutex
                                 .lock()
                                 .unwrap()
-                                .push(format!("{}:{}@{}", d, dep_name, dep_version_clean));
+                                .push((format!("{}:{}@{} (fresh install of {})", d, dep_name, fresh.version, dep_version), MatchType::Declared));
                         }
                     }
                 }
             }
         }
 
-        // Process lockfiles and npm ls for additional versions
-        for (name, version) in &packages {
-            let rng = get_pkg_range(name, pkg_json);
-            let mut versions_by_file: HashMap<String, HashSet<String>> = HashMap::new();
-
-            if let Some(content) = &preload.yarn {
-                let yv = get_yarn_versions(name, content);
-                if !yv.is_empty() {
-                    versions_by_file.insert("yarn.lock".to_string(), yv);
-                }
+        // Process the npm: and jsr: imports of a Deno import map
+        if let Some(deno_project) = &preload.deno {
+            let depended_by = deno_project.name.clone().unwrap_or_default();
+            for (dep_name, dep_range) in &deno_project.imports {
+                let dependency = Dependency {
+                    name: dep_name,
+                    version: dep_range,
+                    match_type: MatchType::Declared,
+                    location: d,
+                    project,
+                    kind: "import",
+                    depended_by: &depended_by,
+                    section: "",
+                };
+                push_dependency_row(checks, dependency, &rows_mutex, &found_mutex);
             }
-            if let Some(plock) = &preload.plock {
-                let plv = get_package_lock_versions(name, plock);
-                if !plv.is_empty() {
-                    versions_by_file.insert("package-lock.json".to_string(), plv);
-                }
+        }
+
+        // Dependencies of the other ecosystems are checked with the section
+        // their `dependency` label stands for, build dependencies always
+        let selected = |kind: &str| {
+            kind == "build" || SECTIONS.iter().any(|(s, l)| *l == kind && preload.sections.iter().any(|x| x == s))
+        };
+
+        // Process the dependencies of a Cargo.toml at the version Cargo.lock
+        // resolves them to, else the lowest version their requirement admits
+        if let Some(crate_project) = &preload.cargo {
+            let depended_by = match (&crate_project.name, &crate_project.version) {
+                (Some(name), Some(version)) => format!("{}@{}", name.trim_start_matches(cargo::PREFIX), version),
+                (Some(name), None) => name.trim_start_matches(cargo::PREFIX).to_string(),
+                _ => String::new(),
+            };
+            for dependency in crate_project.dependencies.iter().filter(|dep| selected(dep.kind)) {
+                let (dep_version, match_type) = match crate_project.resolve(dependency) {
+                    Some(locked) => (locked.to_string(), MatchType::Locked),
+                    None => (cargo::lowest(&dependency.requirement), MatchType::Declared),
+                };
+                let dependency = Dependency {
+                    name: &dependency.name,
+                    version: &dep_version,
+                    match_type,
+                    location: d,
+                    project,
+                    kind: dependency.kind,
+                    depended_by: &depended_by,
+                    section: &dependency.section,
+                };
+                push_dependency_row(checks, dependency, &rows_mutex, &found_mutex);
             }
-            if let Some(content) = &preload.pnpm {
-                let pnv = get_pnpm_versions(name, content);
-                if !pnv.is_empty() {
-                    versions_by_file.insert("pnpm-lock.yaml".to_string(), pnv);
-                }
+        }
+
+        // Process the requirements of a go.mod
+        if let Some(module) = &preload.go {
+            let depended_by = module.path.as_deref().unwrap_or_default().trim_start_matches(go::PREFIX);
+            for require in &module.requires {
+                let dependency = Dependency {
+                    name: &require.name,
+                    version: &require.version,
+                    match_type: MatchType::Locked,
+                    location: d,
+                    project,
+                    kind: if require.indirect { "indirect" } else { "yes" },
+                    depended_by,
+                    section: "require",
+                };
+                push_dependency_row(checks, dependency, &rows_mutex, &found_mutex);
             }
-            if let Some(content) = &preload.deps {
-                let dev = get_dependencies_versions(name, content);
-                if !dev.is_empty() {
-                    versions_by_file.insert("DEPENDENCIES.json".to_string(), dev);
-                }
+        }
+
+        // Process the requirements of a composer.json at the version
+        // composer.lock installs, else the lowest version their constraint admits
+        if let Some(composer_project) = &preload.composer {
+            let depended_by = match (&composer_project.name, &composer_project.version) {
+                (Some(name), Some(version)) => format!("{}@{}", name.trim_start_matches(composer::PREFIX), version),
+                (Some(name), None) => name.trim_start_matches(composer::PREFIX).to_string(),
+                _ => String::new(),
+            };
+            for require in composer_project.requires.iter().filter(|r| selected(r.kind)) {
+                let (dep_version, match_type) = match composer_project.versions(&require.name).next() {
+                    Some(locked) => (locked.to_string(), MatchType::Locked),
+                    None => (composer::lowest(&require.constraint), MatchType::Declared),
+                };
+                let dependency = Dependency {
+                    name: &require.name,
+                    version: &dep_version,
+                    match_type,
+                    location: d,
+                    project,
+                    kind: require.kind,
+                    depended_by: &depended_by,
+                    section: require.section,
+                };
+                push_dependency_row(checks, dependency, &rows_mutex, &found_mutex);
             }
+        }
 
-            let mut nv: HashSet<String> = HashSet::new();
-            if !args.no_npm {
-                nv = get_npm_versions(d, name);
-                if !nv.is_empty() {
-                    versions_by_file.insert("npm_installed".to_string(), nv.clone());
-                }
+        // Process the gems the Gemfile declares at the version the lockfile
+        // resolves them to, else the lowest version their requirement admits
+        if let Some(bundle) = &preload.bundler {
+            for (dep_name, requirement) in &bundle.dependencies {
+                let (dep_version, match_type) = match bundle.versions(dep_name).next() {
+                    Some(locked) => (locked.to_string(), MatchType::Locked),
+                    None => (bundler::lowest(requirement), MatchType::Declared),
+                };
+                let dependency = Dependency {
+                    name: dep_name,
+                    version: &dep_version,
+                    match_type,
+                    location: d,
+                    project,
+                    kind: "yes",
+                    depended_by: "",
+                    section: "DEPENDENCIES",
+                };
+                push_dependency_row(checks, dependency, &rows_mutex, &found_mutex);
             }
+        }
 
-            let mut all_versions: HashSet<String> = HashSet::new();
-            for versions in versions_by_file.values() {
-                all_versions.extend(versions.iter().cloned());
+        // Process the packages a .NET project references at the version NuGet
+        // restores them to, else the lowest version their range admits
+        if let Some(nuget_project) = &preload.nuget {
+            for (dep_name, range) in &nuget_project.direct {
+                let (dep_version, match_type) = match nuget_project.versions(dep_name).next() {
+                    Some(locked) => (locked.to_string(), MatchType::Locked),
+                    None => (nuget::lowest(range), MatchType::Declared),
+                };
+                let dependency = Dependency {
+                    name: dep_name,
+                    version: &dep_version,
+                    match_type,
+                    location: d,
+                    project,
+                    kind: "yes",
+                    depended_by: "",
+                    section: nuget::SECTION,
+                };
+                push_dependency_row(checks, dependency, &rows_mutex, &found_mutex);
             }
-            all_versions.extend(nv.iter().cloned());
+        }
 
-            let match_package = !rng.is_empty() || !all_versions.is_empty();
-            let match_version = all_versions.iter().any(|v| satisfies_range(v, version));
+        // Deno projects without a package.json have nothing for npm to list,
+        // and npm would report the enclosing project instead. Members of a
+        // scanned workspace root are listed by the npm run at the root, which
+        // would otherwise count their hoisted installs once more per member.
+        // Yarn Plug'n'Play projects have no node_modules for npm to list.
+        let in_memory = in_memory_installs.get(d);
+        let is_pnp = !args.no_npm && in_memory.is_none() && pnp::is_pnp(Path::new(d));
+        if is_pnp && args.use_npm && args.verbose {
+            eprintln!("[debug] {} uses Yarn Plug'n'Play, reading .pnp.cjs instead of running npm", d);
+        }
+        let run_npm = !args.no_npm
+            && args.use_npm
+            && !is_pnp
+            && in_memory.is_none()
+            && pkg_json.is_some()
+            && !covered_members.contains(d);
+        let npm_target = run_npm.then(|| NpmTarget::new(d, pkg_json));
+        if args.verbose
+            && let Some(NpmTarget {
+                dir,
+                workspace: Some(workspace),
+            }) = &npm_target
+        {
+            eprintln!(
+                "[debug] {} is workspace {} of {}, running npm there",
+                d,
+                workspace,
+                dir.display()
+            );
+        }
 
-            if !match_package && !match_version {
-                continue;
+        // Installed versions come from walking node_modules (or .pnp.cjs), or
+        // with --use-npm from a single `npm sbom` run, falling back to one
+        // `npm ls` per package. A tarball installs what it bundles, an
+        // archive what was archived with it and a git tree nothing.
+        let installed = if args.no_npm {
+            None
+        } else if let Some(in_memory) = in_memory {
+            Some(in_memory.clone())
+        } else if !args.use_npm || is_pnp {
+            Some(node_modules::installed(Path::new(d)))
+        } else if let Some(target) = npm_target.as_ref().filter(|_| !args.no_npm_sbom) {
+            let sbom = get_npm_sbom(target);
+            if sbom.is_none() && args.verbose {
+                eprintln!("[debug] npm sbom unavailable in {}, falling back to npm ls", d);
             }
+            sbom
+        } else {
+            None
+        };
 
-            rows_mutex.lock().unwrap().push((
-                name.clone(),
-                version.clone(),
-                d.to_string(),
-                match_package,
-                match_version,
-                String::new(),
-                String::new(),
-            ));
+        // Entries with a pattern matcher are looked up by every package name known
+        // to the project, exact entries by their own name
+        let inventory = if packages.iter().any(|p| p.matcher.exact_name().is_none()) {
+            let mut names = project_names(preload);
+            if let Some(installed) = &installed {
+                names.extend(installed.keys().cloned());
+            }
+            if let Some(member) = hoisted.get(d) {
+                names.extend(member.resolved.iter().map(|(n, _)| n.clone()));
+            }
+            names
+        } else {
+            HashSet::new()
+        };
+
+        // Process lockfiles and installed packages for additional versions
+        for package in packages.iter() {
+            let version = &package.version;
+            let names: Vec<&str> = match package.matcher.exact_name() {
+                Some(name) => vec![name],
+                None => {
+                    let mut names: Vec<&str> =
+                        inventory.iter().map(|n| n.as_str()).filter(|n| package.matches(n)).collect();
+                    names.sort();
+                    if args.verbose && !names.is_empty() {
+                        eprintln!("[debug] {}: {} matches {}", d, package.name, names.join(", "));
+                    }
+                    names
+                }
+            };
+            for name in names {
+                let rng = get_pkg_range(name, preload);
+                let mut versions_by_file = lockfile_versions(name, preload);
+
+                let nv: HashSet<String> = match &installed {
+                    Some(installed) => installed.get(name).cloned().unwrap_or_default(),
+                    None => match &npm_target {
+                        Some(target) => get_npm_versions(target, name),
+                        None => HashSet::new(),
+                    },
+                };
+                if !nv.is_empty() {
+                    versions_by_file.insert("installed".to_string(), nv.clone());
+                }
+                if let Some(member) = hoisted.get(d) {
+                    let resolved: HashSet<String> =
+                        member.resolved.iter().filter(|(n, _)| n == name).map(|(_, v)| v.clone()).collect();
+                    if !resolved.is_empty() {
+                        versions_by_file.insert("workspace root".to_string(), resolved);
+                    }
+                }
+
+                let mut all_versions: HashSet<String> = HashSet::new();
+                for versions in versions_by_file.values() {
+                    all_versions.extend(versions.iter().cloned());
+                }
+                all_versions.extend(nv.iter().cloned());
+
+                let match_package = !rng.is_empty() || !all_versions.is_empty();
+                let matched_versions: Vec<&String> = all_versions.iter().filter(|v| satisfies_range(v, version, matching)).collect();
+                let match_version = !matched_versions.is_empty();
+                // Only allowed when every flagged version found is exempted
+                let allowed = matched_versions
+                    .iter()
+                    .map(|v| exemption_reason(&packages, name, v, d, matching))
+                    .collect::<Option<Vec<String>>>()
+                    .and_then(|reasons| reasons.into_iter().next())
+                    .unwrap_or_default();
+
+                if !match_package && !match_version {
+                    continue;
+                }
+
+                // How the flagged versions got in: dependency paths in
+                // package-lock.json, declared ranges resolving to them in yarn.lock
+                let mut paths: Vec<String> = Vec::new();
+                for v in &matched_versions {
+                    if let Some(plock) = &preload.plock {
+                        paths.extend(package_lock::dependency_paths(plock, name, v));
+                    }
+                    if let Some(lockfile) = &preload.yarn {
+                        paths.extend(lockfile.requested_ranges(name, v));
+                    }
+                    if let Some(crate_project) = &preload.cargo {
+                        paths.extend(crate_project.depended_by(name, v).cloned());
+                    }
+                    if let Some(composer_project) = &preload.composer {
+                        paths.extend(composer_project.depended_by(name, v).cloned());
+                    }
+                    if let Some(bundle) = &preload.bundler {
+                        paths.extend(bundle.depended_by(name, v).cloned());
+                    }
+                    if let Some(nuget_project) = &preload.nuget {
+                        paths.extend(nuget_project.depended_by(name, v).cloned());
+                    }
+                }
+                paths.sort();
+                paths.dedup();
 
-            if match_package && match_version {
-                found_mutex
-                    .lock()
-                    .unwrap()
-                    .push(format!("{}:{}@{}", d, name, version));
+                // Workspace members pulling in the flagged versions, so a
+                // hoisted install is reported once at the root
+                let mut members: Vec<&str> = workspace_members
+                    .get(d)
+                    .map(|m| m.as_slice())
+                    .into_iter()
+                    .chain(hoisted.get(d).map(std::slice::from_ref))
+                    .flatten()
+                    .filter(|m| m.uses(name, matched_versions.iter().copied()))
+                    .map(|m| m.name.as_str())
+                    .collect();
+                members.sort();
+
+                rows_mutex.lock().unwrap().push(Row {
+                    package: name.to_string(),
+                    version: version.clone(),
+                    location: d.to_string(),
+                    match_package,
+                    match_version,
+                    dependency: String::new(),
+                    depended_by: paths.join("; "),
+                    source: package.sources.join(";"),
+                    reason: package.reasons.join("; "),
+                    project: project.clone(),
+                    allowed: allowed.clone(),
+                    severity: severity_label(&severity_map, std::iter::once(package)),
+                    installed_size: None,
+                    transitive_deps: None,
+                    section: String::new(),
+                    workspace: members.join(";"),
+                    match_type: MatchType::Locked,
+                });
+
+                if match_package && match_version && allowed.is_empty() {
+                    found_mutex
+                        .lock()
+                        .unwrap()
+                        .push((format!("{}:{}@{}", d, name, version), MatchType::Locked));
+                    if args.evidence {
+                        let versions: Vec<&str> = matched_versions.iter().map(|v| v.as_str()).collect();
+                        evidence_mutex.lock().unwrap().extend(evidence::collect(d, name, &versions, preload));
+                    }
+                    if args.blame {
+                        for v in &matched_versions {
+                            blame_mutex.lock().unwrap().extend(blame::commits(d, name, v, preload));
+                        }
+                    }
+                }
             }
         }
     });
 
+    let unparsed = unparseable.lock().unwrap().clone();
+    if !unparsed.is_empty() {
+        eprintln!(
+            "[warning] {} versions of listed packages are not valid semver and only match exact entries, pass --loose-versions to read them leniently:",
+            unparsed.len()
+        );
+        for version in &unparsed {
+            eprintln!("  {}", version);
+        }
+    }
+
     // Sort and print found
     let mut found = found_mutex.into_inner().unwrap();
     found.sort();
-    for item in found {
+    // A bundled dependency is also declared in another section, and a locked
+    // finding sorts before the same one declared
+    found.dedup_by(|a, b| a.0 == b.0);
+    let failing = args.fail_on.map_or(0, |f| found.iter().filter(|(_, t)| f.covers(*t)).count());
+    let found: Vec<String> = found.into_iter().map(|(f, _)| f).collect();
+    let findings = found.len();
+    for item in &found {
         println!("{}", item);
     }
 
     // Write CSV
     let mut csv_writer = csv::Writer::from_path("output.csv")?;
-    csv_writer.write_record(&[
+    csv_writer.write_record([
         "package",
         "version",
         "location",
@@ -581,23 +3768,195 @@ fn main() -> io::Result<()> {
         "match_version",
         "dependency",
         "depended_by",
+        "source",
+        "reason",
+        "project",
+        "allowed",
+        "severity",
+        "installed_size",
+        "transitive_deps",
+        "section",
+        "workspace",
+        "match_type",
     ])?;
 
     let mut rows = rows_mutex.into_inner().unwrap();
-    rows.sort_by_key(|r| (r.0.clone(), r.1.clone(), r.2.clone()));
-    for (pkg, ver, loc, mp, mv, dep, dep_by) in rows {
-        csv_writer.write_record(&[
-            pkg,
-            ver,
-            loc,
-            mp.to_string(),
-            mv.to_string(),
-            dep,
-            dep_by,
+    // Rows of a workspace member belong to that workspace
+    for row in rows.iter_mut().filter(|r| r.workspace.is_empty()) {
+        if let Some(owner) = owners.get(&row.location) {
+            row.workspace = owner.clone();
+        }
+    }
+    let row_count = rows.len();
+    rows.sort_by(|a, b| (&a.package, &a.version, &a.location).cmp(&(&b.package, &b.version, &b.location)));
+    let (written, dropped) = match args.max_rows_per_severity {
+        Some(max) => sample_rows(&rows, max),
+        None => (rows.iter().collect(), BTreeMap::new()),
+    };
+    if !dropped.is_empty() {
+        let counts: Vec<String> = dropped
+            .iter()
+            .map(|(severity, n)| format!("{} {}", n, if severity.is_empty() { "unrated" } else { severity }))
+            .collect();
+        let note = format!(
+            "output.csv truncated by --max-rows-per-severity {}: dropped {}",
+            args.max_rows_per_severity.unwrap_or_default(),
+            counts.join(", ")
+        );
+        eprintln!("[warning] {}", note);
+        warnings.push(note);
+    }
+    for row in written {
+        csv_writer.write_record([
+            row.package.as_str(),
+            &row.version,
+            &row.location,
+            &row.match_package.to_string(),
+            &row.match_version.to_string(),
+            &row.dependency,
+            &row.depended_by,
+            &row.source,
+            &row.reason,
+            &row.project,
+            &row.allowed,
+            &row.severity,
+            &row.installed_size.map(|s| s.to_string()).unwrap_or_default(),
+            &row.transitive_deps.map(|t| t.to_string()).unwrap_or_default(),
+            &row.section,
+            &row.workspace,
+            if row.match_package && row.match_version { row.match_type.label() } else { "" },
         ])?;
     }
+    csv_writer.flush()?;
+
+    if args.write_sidecars {
+        write_sidecars(&dirs, &rows, args.verbose);
+    }
+
+    // Per-directory verdicts for release automation
+    if args.verdicts {
+        let mut directories: Vec<Value> = dirs
+            .iter()
+            .map(|d| {
+                let prefix = format!("{}:", d);
+                let findings: Vec<&str> = found.iter().filter_map(|f| f.strip_prefix(&prefix)).collect();
+                verdict::scanned(d, &projects[d], findings, input_errors(d, &preloads[d]))
+            })
+            .collect();
+        directories.extend(skipped.iter().map(|(d, reason)| verdict::skipped(d, &projects[d], reason)));
+        directories.sort_by_key(|v| v["location"].to_string());
+        let verdicts = json!({
+            "lists": list_hashes,
+            "directories": directories,
+        });
+        serde_json::to_writer_pretty(File::create(verdict::FILE_NAME)?, &verdicts)?;
+    }
+
+    // Previous findings whose advisory was withdrawn from every list
+    let stale: Vec<&stale::Finding> = previous
+        .iter()
+        .filter(|f| !packages.iter().any(|p| p.matches(&f.package) && satisfies_range(&f.version, &p.version, matching)))
+        .collect();
+    if args.previous_report.is_some() {
+        stale::write(Path::new(stale::FILE_NAME), &stale)?;
+        if !stale.is_empty() {
+            println!(
+                "{} previously flagged findings are no longer covered by any list entry, see {}",
+                stale.len(),
+                stale::FILE_NAME
+            );
+        }
+    }
+
+    // Write report metadata
+    let mut evidence = evidence_mutex.into_inner().unwrap();
+    evidence.sort_by_key(|e| (e["location"].to_string(), e["package"].to_string(), e["install_path"].to_string()));
+    let mut metadata = json!({
+        "toolchain": tools,
+        "projects": project_tools,
+        "warnings": warnings,
+        "mixed_lockfiles": mixed,
+    });
+    if args.evidence {
+        metadata["evidence"] = json!(evidence);
+    }
+    if args.previous_report.is_some() {
+        metadata["stale_findings"] = json!(stale.len());
+    }
+    if args.blame {
+        let mut blame = blame_mutex.into_inner().unwrap();
+        blame.sort_by_key(|b| (b["location"].to_string(), b["package"].to_string(), b["file"].to_string()));
+        blame.dedup();
+        metadata["blame"] = json!(blame);
+    }
+    serde_json::to_writer_pretty(File::create("output.meta.json")?, &metadata)?;
+
+    if args.telemetry {
+        match &args.telemetry_endpoint {
+            Some(endpoint) => {
+                let stats = telemetry::ScanStats {
+                    duration_ms: started.elapsed().as_millis(),
+                    directories: dirs.len(),
+                    list_entries: packages.len(),
+                    rows: row_count,
+                    findings,
+                    features: used_features(&args, &packages),
+                };
+                if let Err(e) = telemetry::report(endpoint, &stats)
+                    && args.verbose
+                {
+                    eprintln!("[debug] Sending telemetry failed: {}", e);
+                }
+            }
+            None => eprintln!("[warning] --telemetry is set but no --telemetry-endpoint is configured"),
+        }
+    }
+
+    // Hand the finished report to the configured hooks
+    let mut hook_failed = false;
+    if !args.hooks.is_empty() {
+        let absolute = |file: &str| fs::canonicalize(file).map(|p| p.display().to_string()).unwrap_or(file.to_string());
+        let summary = json!({
+            "report": absolute("output.csv"),
+            "meta": absolute("output.meta.json"),
+            "directories": dirs.len(),
+            "rows": row_count,
+            "findings": found,
+        });
+        let env = [
+            ("PACKAGE_CHECKER_REPORT", absolute("output.csv")),
+            ("PACKAGE_CHECKER_META", absolute("output.meta.json")),
+            ("PACKAGE_CHECKER_FINDINGS", findings.to_string()),
+        ];
+        for hook in &args.hooks {
+            if args.verbose {
+                eprintln!("[debug] Running hook {}", hook.command);
+            }
+            let Err(e) = hooks::run(hook, &summary, &env) else {
+                continue;
+            };
+            match hook.on_failure {
+                config::OnFailure::Ignore => {}
+                config::OnFailure::Warn => eprintln!("[warning] Hook {} failed: {}", hook.command, e),
+                config::OnFailure::Fail => {
+                    eprintln!("[error] Hook {} failed: {}", hook.command, e);
+                    hook_failed = true;
+                }
+            }
+        }
+    }
 
     println!("Scan complete.");
 
-    Ok(())
+    if failing > 0 {
+        eprintln!("[error] {} findings are covered by --fail-on", failing);
+    }
+    let deprecated_failing = args.fail_on_deprecated && deprecated_count > 0;
+    if deprecated_failing {
+        eprintln!("[error] {} deprecated versions found with --fail-on-deprecated", deprecated_count);
+    }
+    if hook_failed || failing > 0 || deprecated_failing {
+        return Ok(ExitCode::FAILURE);
+    }
+    Ok(ExitCode::SUCCESS)
 }
\ No newline at end of file
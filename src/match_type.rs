@@ -0,0 +1,52 @@
+use clap::ValueEnum;
+
+/// Where a matched version was found, which tells how certain it is to be
+/// what gets run.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchType {
+    /// Pinned in a lockfile or installed
+    Locked,
+    /// Declared in a manifest, as a range that could resolve to the version
+    /// or a version a fresh install would pull
+    Declared,
+}
+
+impl MatchType {
+    pub fn label(self) -> &'static str {
+        match self {
+            MatchType::Locked => "locked",
+            MatchType::Declared => "declared",
+        }
+    }
+}
+
+/// Findings that fail the scan, selected with `--fail-on`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum FailOn {
+    /// Versions pinned in a lockfile or installed
+    Locked,
+    /// Declared ranges resolving to a listed version as well
+    Any,
+}
+
+impl FailOn {
+    pub fn covers(self, match_type: MatchType) -> bool {
+        match self {
+            FailOn::Locked => match_type == MatchType::Locked,
+            FailOn::Any => true,
+        }
+    }
+}
+
+/// How a found version has to relate to a listed entry to match, selected
+/// with `--match-mode`. Without it, a declared range is checked at the lowest
+/// version it admits.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+pub enum MatchMode {
+    /// Only versions that are pinned or installed, never a declared range
+    Exact,
+    /// Declared ranges admitting any listed version as well
+    Range,
+    /// Any version on the major line of a listed version
+    Major,
+}
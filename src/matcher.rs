@@ -0,0 +1,181 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use crate::{bundler, cargo, composer, go, maven, nuget};
+
+/// Decides whether a package name found in a project matches a package list
+/// entry.
+pub trait Matcher: Send + Sync {
+    fn matches(&self, name: &str) -> bool;
+
+    /// The single package name this matcher accepts, if any. Used to look
+    /// names up directly in lockfiles instead of testing every known name.
+    fn exact_name(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Plain name equality, the default for list entries.
+pub struct Exact(String);
+
+impl Matcher for Exact {
+    fn matches(&self, name: &str) -> bool {
+        self.0 == canonical(name)
+    }
+
+    fn exact_name(&self) -> Option<&str> {
+        Some(&self.0)
+    }
+}
+
+/// Shell style glob where `*` matches any run of characters and `?` a single
+/// one, e.g. `@evil-scope/*`.
+pub struct Glob(Regex);
+
+impl Glob {
+    fn new(pattern: &str) -> Result<Self, regex::Error> {
+        let mut re = String::from("^");
+        for c in pattern.chars() {
+            match c {
+                '*' => re.push_str(".*"),
+                '?' => re.push('.'),
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        re.push('$');
+        Regex::new(&re).map(Glob)
+    }
+}
+
+impl Matcher for Glob {
+    fn matches(&self, name: &str) -> bool {
+        self.0.is_match(name) || self.0.is_match(&canonical(name))
+    }
+}
+
+/// Regular expression searched anywhere in the name, anchor it to match the
+/// whole name.
+pub struct Pattern(Regex);
+
+impl Matcher for Pattern {
+    fn matches(&self, name: &str) -> bool {
+        self.0.is_match(name) || self.0.is_match(&canonical(name))
+    }
+}
+
+/// Same package published under a different scope, e.g. a mirrored
+/// `@corp-mirror/lodash` matches an entry for `lodash`.
+pub struct Mirror(String);
+
+impl Matcher for Mirror {
+    fn matches(&self, name: &str) -> bool {
+        unscoped(&canonical(name)) == unscoped(&canonical(&self.0))
+    }
+}
+
+fn unscoped(name: &str) -> &str {
+    let (_, name) = split_namespace(name);
+    match name.strip_prefix('@') {
+        Some(rest) => rest.split_once('/').map(|(_, n)| n).unwrap_or(rest),
+        None => name,
+    }
+}
+
+/// Prefixes selecting a matching strategy in list entry names.
+pub const PREFIXES: [&str; 4] = ["exact:", "glob:", "regex:", "mirror:"];
+
+/// Build the matcher for a list entry name. The strategy is selected with a
+/// `glob:`, `regex:`, `mirror:` or `exact:` prefix. Without a prefix a name
+/// wrapped in slashes (`/^eslint-config-internal-/`) is a regex, a name with
+/// `*` or `?` (`@evil-scope/*`) a glob, and anything else is matched exactly.
+pub fn parse(spec: &str) -> Result<Box<dyn Matcher>, String> {
+    if !PREFIXES.iter().any(|p| spec.starts_with(p)) {
+        if let Some(re) = spec.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            return parse(&format!("regex:{}", re));
+        }
+        if spec.contains(['*', '?']) {
+            return parse(&format!("glob:{}", spec));
+        }
+    }
+    if let Some(p) = spec.strip_prefix("glob:") {
+        Glob::new(p)
+            .map(|m| Box::new(m) as Box<dyn Matcher>)
+            .map_err(|e| format!("invalid glob {}: {}", p, e))
+    } else if let Some(p) = spec.strip_prefix("regex:") {
+        Regex::new(p)
+            .map(|re| Box::new(Pattern(re)) as Box<dyn Matcher>)
+            .map_err(|e| format!("invalid regex {}: {}", p, e))
+    } else if let Some(p) = spec.strip_prefix("mirror:") {
+        Ok(Box::new(Mirror(p.to_string())))
+    } else {
+        let name = spec.strip_prefix("exact:").unwrap_or(spec);
+        Ok(Box::new(Exact(canonical(name).into_owned())))
+    }
+}
+
+/// Registry a package name belongs to. Names without a namespace prefix are
+/// npm packages.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Namespace {
+    Npm,
+    /// JSR, `jsr:@scope/name`
+    Jsr,
+    /// GitHub Packages (npm.pkg.github.com), `gpr:@owner/name`
+    GitHub,
+    /// crates.io, `cargo:name`
+    Cargo,
+    /// Go modules, `go:module/path`
+    Go,
+    /// Packagist, `composer:vendor/name`
+    Composer,
+    /// RubyGems, `gem:name`
+    RubyGems,
+    /// Maven repositories, `maven:group:artifact`
+    Maven,
+    /// NuGet, `nuget:name`
+    NuGet,
+}
+
+/// Host of the GitHub Packages npm registry.
+pub const GITHUB_PACKAGES_HOST: &str = "npm.pkg.github.com";
+
+/// Split the `npm:`, `jsr:`, `gpr:`, `cargo:`, `go:`, `composer:`, `gem:`,
+/// `maven:` or `nuget:` namespace off a package name.
+pub fn split_namespace(name: &str) -> (Namespace, &str) {
+    if let Some(rest) = name.strip_prefix("jsr:") {
+        (Namespace::Jsr, rest)
+    } else if let Some(rest) = name.strip_prefix("gpr:") {
+        (Namespace::GitHub, rest)
+    } else if let Some(rest) = name.strip_prefix(cargo::PREFIX) {
+        (Namespace::Cargo, rest)
+    } else if let Some(rest) = name.strip_prefix(go::PREFIX) {
+        (Namespace::Go, rest)
+    } else if let Some(rest) = name.strip_prefix(composer::PREFIX) {
+        (Namespace::Composer, rest)
+    } else if let Some(rest) = name.strip_prefix(bundler::PREFIX) {
+        (Namespace::RubyGems, rest)
+    } else if let Some(rest) = name.strip_prefix(maven::PREFIX) {
+        (Namespace::Maven, rest)
+    } else if let Some(rest) = name.strip_prefix(nuget::PREFIX) {
+        (Namespace::NuGet, rest)
+    } else {
+        (Namespace::Npm, name.strip_prefix("npm:").unwrap_or(name))
+    }
+}
+
+/// The name a package is known under in list entries and reports. JSR
+/// packages installed through its npm compatibility registry as
+/// `@jsr/scope__name` become `jsr:@scope/name`, an explicit `npm:` is dropped.
+pub fn canonical(name: &str) -> Cow<'_, str> {
+    if let Some((scope, pkg)) = name.strip_prefix("@jsr/").and_then(|n| n.split_once("__")) {
+        return Cow::Owned(format!("jsr:@{}/{}", scope, pkg));
+    }
+    Cow::Borrowed(name.strip_prefix("npm:").unwrap_or(name))
+}
+
+/// The name npm lockfiles use for `jsr:@scope/name`: `@jsr/scope__name`.
+pub fn jsr_npm_name(name: &str) -> Option<String> {
+    let (scope, pkg) = name.strip_prefix('@')?.split_once('/')?;
+    Some(format!("@jsr/{}__{}", scope, pkg))
+}
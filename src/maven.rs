@@ -0,0 +1,173 @@
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::process::{self, Command};
+
+use serde_json::Value;
+
+use crate::digest;
+
+/// Prefix of Maven coordinates, e.g. `maven:org.apache.logging.log4j:log4j-core`,
+/// so they never collide with npm names.
+pub const PREFIX: &str = "maven:";
+
+/// Gradle's dependency lockfiles, for the build and for its buildscript.
+const GRADLE_LOCKFILES: [&str; 2] = ["gradle.lockfile", "buildscript-gradle.lockfile"];
+
+/// Per-configuration lockfiles of Gradle before 6.4.
+const LEGACY_LOCK_DIR: &str = "gradle/dependency-locks";
+
+/// Lockfile of the dependency-lock-maven-plugin.
+const MAVEN_LOCKFILE: &str = "dependencies-lock.json";
+
+/// Source of the artifacts `mvn dependency:list` resolves, in place of a
+/// lockfile name.
+pub const DEPENDENCY_LIST: &str = "mvn dependency:list";
+
+/// Files marking a Maven or Gradle project.
+pub const PROJECT_FILES: [&str; 5] = ["pom.xml", "build.gradle", "build.gradle.kts", GRADLE_LOCKFILES[0], MAVEN_LOCKFILE];
+
+/// A locked artifact as `maven:group:artifact`, with the lockfile it is in.
+pub struct Locked {
+    pub name: String,
+    pub version: String,
+    pub file: String,
+}
+
+/// The artifacts the Gradle or Maven lockfiles of a project pin.
+pub struct Project {
+    /// Whether the project is built by Maven, so `mvn dependency:list` can
+    /// resolve it
+    pub has_pom: bool,
+    pub locked: Vec<Locked>,
+}
+
+impl Project {
+    /// Versions of `name` as (lockfile, version).
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = (&str, &str)> {
+        self.locked
+            .iter()
+            .filter(move |l| l.name == name)
+            .map(|l| (l.file.as_str(), l.version.as_str()))
+    }
+
+    /// Whether the artifacts of a pom.xml are unknown without asking Maven:
+    /// there is no dependencies-lock.json.
+    pub fn needs_mvn(&self) -> bool {
+        self.has_pom && !self.locked.iter().any(|l| l.file == MAVEN_LOCKFILE)
+    }
+}
+
+/// Read the Gradle and Maven lockfiles of `dir`. Returns None when the
+/// directory is not a Maven or Gradle project.
+pub fn load(dir: &Path) -> Option<io::Result<Project>> {
+    if !PROJECT_FILES.iter().any(|f| dir.join(f).is_file()) {
+        return None;
+    }
+    let mut locked: Vec<Locked> = Vec::new();
+    let mut gradle_files: Vec<String> = GRADLE_LOCKFILES.iter().map(|f| f.to_string()).collect();
+    if let Ok(entries) = fs::read_dir(dir.join(LEGACY_LOCK_DIR)) {
+        let mut legacy: Vec<String> = entries
+            .filter_map(Result::ok)
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|f| f.ends_with(".lockfile"))
+            .map(|f| format!("{}/{}", LEGACY_LOCK_DIR, f))
+            .collect();
+        legacy.sort();
+        gradle_files.extend(legacy);
+    }
+    for file in gradle_files {
+        let path = dir.join(&file);
+        if !path.is_file() {
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(content) => locked.extend(parse_gradle(&content).into_iter().map(|(name, version)| Locked {
+                name,
+                version,
+                file: file.clone(),
+            })),
+            Err(e) => return Some(Err(e)),
+        }
+    }
+    let path = dir.join(MAVEN_LOCKFILE);
+    if path.is_file() {
+        let lock: Value = match File::open(&path).and_then(|f| serde_json::from_reader(f).map_err(io::Error::from)) {
+            Ok(lock) => lock,
+            Err(e) => return Some(Err(e)),
+        };
+        for dependency in lock.get("dependencies").and_then(|d| d.as_array()).into_iter().flatten() {
+            let field = |key: &str| dependency.get(key).and_then(|v| v.as_str()).unwrap_or("");
+            if field("groupId").is_empty() || field("artifactId").is_empty() || field("version").is_empty() {
+                continue;
+            }
+            locked.push(Locked {
+                name: format!("{}{}:{}", PREFIX, field("groupId"), field("artifactId")),
+                version: field("version").to_string(),
+                file: MAVEN_LOCKFILE.to_string(),
+            });
+        }
+    }
+    Some(Ok(Project {
+        has_pom: dir.join("pom.xml").is_file(),
+        locked,
+    }))
+}
+
+/// Entries of a Gradle lockfile: `group:artifact:version=configurations`.
+/// Comments and the `empty=` line of configurations without dependencies are
+/// skipped.
+fn parse_gradle(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let coordinates = line.split('=').next()?.trim();
+            let (name, version) = coordinates.rsplit_once(':')?;
+            name.contains(':').then(|| (format!("{}{}", PREFIX, name), version.to_string()))
+        })
+        .collect()
+}
+
+/// The artifacts Maven resolves for the project in `dir`, from
+/// `mvn dependency:list`. Its lines read
+/// `group:artifact:type[:classifier]:version:scope`.
+pub fn dependency_list(dir: &Path) -> io::Result<Vec<(String, String)>> {
+    let key = digest::sha256(dir.display().to_string().as_bytes());
+    let output_file = env::temp_dir().join(format!("package-checker-mvn-{}-{}.txt", process::id(), &key[..12]));
+    let _ = fs::remove_file(&output_file);
+    let output = Command::new("mvn")
+        .args(["-B", "-q", "dependency:list", "-DappendOutput=true"])
+        .arg(format!("-DoutputFile={}", output_file.display()))
+        .current_dir(dir)
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), "mvn not found on PATH"),
+            _ => e,
+        })?;
+    if !output.status.success() {
+        let _ = fs::remove_file(&output_file);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let error = stdout.lines().find(|l| l.contains("[ERROR]")).unwrap_or("mvn failed").trim().to_string();
+        return Err(io::Error::other(error));
+    }
+    let content = fs::read_to_string(&output_file);
+    let _ = fs::remove_file(&output_file);
+    let mut artifacts: Vec<(String, String)> = content?
+        .lines()
+        .filter_map(|line| {
+            let coordinates = line.split_whitespace().next()?;
+            let parts: Vec<&str> = coordinates.split(':').collect();
+            let version = match parts.len() {
+                5 => parts[3],
+                6 => parts[4],
+                _ => return None,
+            };
+            Some((format!("{}{}:{}", PREFIX, parts[0], parts[1]), version.to_string()))
+        })
+        .collect();
+    artifacts.sort();
+    artifacts.dedup();
+    Ok(artifacts)
+}
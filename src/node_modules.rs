@@ -0,0 +1,78 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::pnp;
+
+/// Installed versions per package name, read from the `package.json` of every
+/// package under `dir/node_modules`: nested `node_modules`, scoped folders and
+/// pnpm's `.pnpm` store included. Symlinked packages (workspaces, pnpm's top
+/// level) are read but not descended into, their contents are found through
+/// their real location or not at all, which avoids cycles. Yarn Plug'n'Play
+/// projects list their packages in `.pnp.cjs` instead.
+pub fn installed(dir: &Path) -> HashMap<String, HashSet<String>> {
+    let mut installed = pnp::installed(dir);
+    walk(&dir.join("node_modules"), &mut |_, name, version| {
+        installed.entry(name.to_string()).or_default().insert(version.to_string());
+    });
+    installed
+}
+
+/// Folders under `dir/node_modules` where `name` is installed at one of
+/// `versions`, found like `installed` finds packages.
+pub fn install_paths(dir: &Path, name: &str, versions: &[&str]) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    walk(&dir.join("node_modules"), &mut |path, installed_name, version| {
+        if installed_name == name && versions.contains(&version) {
+            paths.push(path.to_path_buf());
+        }
+    });
+    paths.sort();
+    paths
+}
+
+/// Call `visit` with the folder, name and version of every package under
+/// `node_modules`.
+fn walk(node_modules: &Path, visit: &mut impl FnMut(&Path, &str, &str)) {
+    let Ok(entries) = fs::read_dir(node_modules) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if name == ".pnpm" {
+            // .pnpm/<name>@<version>/node_modules/<name>
+            for store in fs::read_dir(&path).into_iter().flatten().filter_map(Result::ok) {
+                walk(&store.path().join("node_modules"), visit);
+            }
+        } else if name.starts_with('.') {
+            continue;
+        } else if name.starts_with('@') {
+            for scoped in fs::read_dir(&path).into_iter().flatten().filter_map(Result::ok) {
+                package(&scoped.path(), visit);
+            }
+        } else {
+            package(&path, visit);
+        }
+    }
+}
+
+fn package(path: &Path, visit: &mut impl FnMut(&Path, &str, &str)) {
+    let manifest: Option<Value> = File::open(path.join("package.json"))
+        .ok()
+        .and_then(|f| serde_json::from_reader(f).ok());
+    if let Some(manifest) = &manifest
+        && let (Some(name), Some(version)) = (
+            manifest.get("name").and_then(|n| n.as_str()),
+            manifest.get("version").and_then(|v| v.as_str()),
+        )
+    {
+        visit(path, name, version);
+    }
+    let is_link = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+    if !is_link {
+        walk(&path.join("node_modules"), visit);
+    }
+}
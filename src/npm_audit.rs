@@ -0,0 +1,52 @@
+use std::io;
+
+use serde_json::Value;
+
+use crate::packages::ListEntry;
+use crate::severity;
+
+/// List source of the entries `--with-npm-audit` adds.
+pub const SOURCE: &str = "npm-audit";
+
+/// Translate `npm audit --json` output into entries with the advisory's
+/// vulnerable range, its GHSA ID (or title) as reason and its CVSS score or
+/// severity. Both the npm 7+ `vulnerabilities` report and the npm 6
+/// `advisories` one are read. Fails with npm's summary when it reported an
+/// error instead, e.g. for a project without a lockfile.
+pub fn parse(value: &Value) -> io::Result<Vec<ListEntry>> {
+    if let Some(error) = value.get("error") {
+        let summary = error.get("summary").and_then(|s| s.as_str()).unwrap_or("npm audit failed");
+        return Err(io::Error::other(summary.to_string()));
+    }
+    let mut list: Vec<ListEntry> = Vec::new();
+    for vuln in value.get("vulnerabilities").and_then(|v| v.as_object()).into_iter().flat_map(|v| v.values()) {
+        // A string names the dependency the vulnerability comes through,
+        // which has an entry of its own
+        for via in vuln.get("via").and_then(|v| v.as_array()).into_iter().flatten() {
+            let (Some(name), Some(range)) = (via.get("name").and_then(|n| n.as_str()), via.get("range").and_then(|r| r.as_str()))
+            else {
+                continue;
+            };
+            list.push(ListEntry::new(name, range, advisory_id(via)).with_score(severity::ghsa_score(via)));
+        }
+    }
+    for advisory in value.get("advisories").and_then(|a| a.as_object()).into_iter().flat_map(|a| a.values()) {
+        let (Some(name), Some(range)) = (
+            advisory.get("module_name").and_then(|n| n.as_str()),
+            advisory.get("vulnerable_versions").and_then(|r| r.as_str()),
+        ) else {
+            continue;
+        };
+        list.push(ListEntry::new(name, range, advisory_id(advisory)).with_score(severity::ghsa_score(advisory)));
+    }
+    Ok(list)
+}
+
+/// The GHSA ID at the end of the advisory URL, else its title.
+fn advisory_id(advisory: &Value) -> Option<String> {
+    let url = advisory.get("url").and_then(|u| u.as_str()).unwrap_or("");
+    match url.rsplit('/').next().filter(|id| id.starts_with("GHSA-")) {
+        Some(id) => Some(id.to_string()),
+        None => advisory.get("title").and_then(|t| t.as_str()).map(|t| t.to_string()),
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Registry settings of `.npmrc` files: the default and per scope registry
+/// URLs, and the credentials of each registry.
+#[derive(Default)]
+pub struct Npmrc {
+    registry: Option<String>,
+    /// Registry URL by scope, e.g. `@myorg`
+    scopes: HashMap<String, String>,
+    /// Settings of `//host/path/:key=value` lines by `//host/path/`
+    credentials: HashMap<String, HashMap<String, String>>,
+    /// Files read, in order
+    pub files: Vec<PathBuf>,
+    /// Project files skipped because they are not trusted
+    pub ignored: Vec<PathBuf>,
+}
+
+impl Npmrc {
+    /// Read the user's `.npmrc` (`NPM_CONFIG_USERCONFIG`, else `~/.npmrc`)
+    /// and, when `trust_projects` is set, then the `.npmrc` of each of
+    /// `dirs`, later files overriding earlier ones. A scanned project could
+    /// otherwise point the registries, and the credentials expanded from the
+    /// environment, at any host. `npm_config_registry` overrides the default
+    /// registry of all of them, as it does for npm.
+    pub fn load(dirs: &[&Path], trust_projects: bool) -> Npmrc {
+        let mut npmrc = Npmrc::default();
+        let user = env::var_os("NPM_CONFIG_USERCONFIG")
+            .or_else(|| env::var_os("npm_config_userconfig"))
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".npmrc")));
+        let mut projects: Vec<PathBuf> = dirs.iter().map(|d| d.join(".npmrc")).filter(|p| Some(p) != user.as_ref()).collect();
+        if !trust_projects {
+            for path in projects.drain(..).filter(|p| p.is_file()) {
+                if !npmrc.ignored.contains(&path) {
+                    npmrc.ignored.push(path);
+                }
+            }
+        }
+        for path in user.into_iter().chain(projects) {
+            if npmrc.files.contains(&path) {
+                continue;
+            }
+            if let Ok(content) = fs::read_to_string(&path) {
+                npmrc.read(&content);
+                npmrc.files.push(path);
+            }
+        }
+        if let Ok(registry) = env::var("npm_config_registry").or_else(|_| env::var("NPM_CONFIG_REGISTRY")) {
+            npmrc.registry = Some(registry.trim_end_matches('/').to_string());
+        }
+        npmrc
+    }
+
+    fn read(&mut self, content: &str) {
+        for line in content.lines().map(|l| l.trim()) {
+            if line.starts_with([';', '#', '[']) {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), expand(value.trim().trim_matches('"'), |name| env::var(name).ok()));
+            if key == "registry" {
+                self.registry = Some(value.trim_end_matches('/').to_string());
+            } else if let Some(scope) = key.strip_suffix(":registry").filter(|s| s.starts_with('@')) {
+                self.scopes.insert(scope.to_string(), value.trim_end_matches('/').to_string());
+            } else if let Some((prefix, setting)) = key.rsplit_once(":").filter(|(p, _)| p.starts_with("//")) {
+                let prefix = format!("{}/", prefix.trim_end_matches('/'));
+                self.credentials.entry(prefix).or_default().insert(setting.to_string(), value);
+            }
+        }
+    }
+
+    /// The registry URL `name` is installed from when one is configured: the
+    /// one of its scope, else the default one. Without a trailing slash.
+    pub fn registry(&self, name: &str) -> Option<&str> {
+        let scope = name.split_once('/').map(|(scope, _)| scope).filter(|s| s.starts_with('@'));
+        scope.and_then(|s| self.scopes.get(s)).or(self.registry.as_ref()).map(|r| r.as_str())
+    }
+
+    /// Hosts of the configured registries.
+    pub fn hosts(&self) -> Vec<&str> {
+        self.registry
+            .iter()
+            .chain(self.scopes.values())
+            .filter_map(|url| url.split("://").nth(1)?.split('/').next())
+            .collect()
+    }
+
+    /// The `Authorization` header value for a request to `url`, from the
+    /// credentials of the longest registry path it is under: `_authToken` as
+    /// a bearer token, `_auth` or `username` with `_password` as basic auth.
+    pub fn authorization(&self, url: &str) -> Option<String> {
+        let path = format!("//{}", url.split_once("://")?.1);
+        let (_, settings) = self
+            .credentials
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())?;
+        if let Some(token) = settings.get("_authToken") {
+            return Some(format!("Bearer {}", token));
+        }
+        if let Some(auth) = settings.get("_auth") {
+            return Some(format!("Basic {}", auth));
+        }
+        let (username, password) = (settings.get("username")?, settings.get("_password")?);
+        let password = String::from_utf8(base64_decode(password)?).ok()?;
+        Some(format!("Basic {}", base64_encode(format!("{}:{}", username, password).as_bytes())))
+    }
+}
+
+/// Replace `${NAME}` with the value `var` gives it, left as is when it has
+/// none. Replacements are not expanded again.
+fn expand(value: &str, var: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}').map(|e| start + e) else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        match var(&rest[start + 2..end]) {
+            Some(replacement) => expanded.push_str(&replacement),
+            None => expanded.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+fn base64_decode(data: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in data.trim_end_matches('=').bytes() {
+        buffer = (buffer << 6) | BASE64.iter().position(|&a| a == c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let buffer = chunk.iter().enumerate().fold(0u32, |b, (i, byte)| b | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            encoded.push(if i <= chunk.len() { BASE64[(buffer >> (18 - 6 * i) & 0x3f) as usize] as char } else { '=' });
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(name: &str) -> Option<String> {
+        match name {
+            "TOKEN" => Some("secret".to_string()),
+            "LOOP" => Some("${LOOP}x".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_set_variables() {
+        assert_eq!(expand("${TOKEN}", vars), "secret");
+        assert_eq!(expand("a-${TOKEN}-${TOKEN}-b", vars), "a-secret-secret-b");
+        assert_eq!(expand("no variables", vars), "no variables");
+        assert_eq!(expand("${TOKEN", vars), "${TOKEN");
+    }
+
+    #[test]
+    fn keeps_unset_variables_and_expands_later_ones() {
+        assert_eq!(expand("${UNSET}:${TOKEN}", vars), "${UNSET}:secret");
+    }
+
+    #[test]
+    fn does_not_expand_replacements() {
+        assert_eq!(expand("${LOOP}", vars), "${LOOP}x");
+    }
+
+    #[test]
+    fn authorization_of_the_longest_registry_path() {
+        let mut npmrc = Npmrc::default();
+        npmrc.read(concat!(
+            "registry=https://registry.example.com/\n",
+            "//registry.example.com/:_authToken=abc\n",
+            "//registry.example.com/team/:username=ci\n",
+            "//registry.example.com/team/:_password=c2VjcmV0\n",
+        ));
+        assert_eq!(npmrc.registry("left-pad"), Some("https://registry.example.com"));
+        assert_eq!(npmrc.authorization("https://registry.example.com/left-pad").as_deref(), Some("Bearer abc"));
+        assert_eq!(npmrc.authorization("https://registry.example.com/team/pkg").as_deref(), Some("Basic Y2k6c2VjcmV0"));
+        assert_eq!(npmrc.authorization("https://other.example.com/left-pad"), None);
+    }
+}
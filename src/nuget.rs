@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use serde_json::Value;
+
+/// Prefix of NuGet package IDs, e.g. `nuget:newtonsoft.json`, so they never
+/// collide with npm names.
+pub const PREFIX: &str = "nuget:";
+
+/// Lockfile written with `RestorePackagesWithLockFile`.
+pub const LOCKFILE: &str = "packages.lock.json";
+
+/// Restore output of every SDK-style project, used when there is no lockfile.
+pub const ASSETS_FILE: &str = "obj/project.assets.json";
+
+/// Files marking a .NET project, `*` matching any file name.
+pub const PROJECT_FILES: [&str; 4] = [LOCKFILE, "*.csproj", "*.fsproj", "*.vbproj"];
+
+/// The `section` column of the packages a project references itself.
+pub const SECTION: &str = "PackageReference";
+
+/// A package restored for the project, with the packages depending on it as
+/// `name@version`.
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub depended_by: Vec<String>,
+}
+
+/// The packages NuGet restores for a project, from its lockfile or assets
+/// file.
+pub struct Project {
+    /// `packages.lock.json` or `obj/project.assets.json`
+    pub file: &'static str,
+    /// Packages the project references itself, with their requested range
+    pub direct: Vec<(String, String)>,
+    /// Packages restored for any target framework, transitive ones included
+    pub packages: Vec<Package>,
+}
+
+impl Project {
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.packages.iter().filter(move |p| p.name == name).map(|p| p.version.as_str())
+    }
+
+    /// The packages depending on `name` at `version`.
+    pub fn depended_by(&self, name: &str, version: &str) -> impl Iterator<Item = &String> {
+        self.packages
+            .iter()
+            .filter(move |p| p.name == name && p.version == version)
+            .flat_map(|p| &p.depended_by)
+    }
+}
+
+/// The lowest version a requested range admits, padded to
+/// `major.minor.patch`, e.g. `13.0.1` for `13.0.1`, `[13.0.1, )` or
+/// `[1.0,2.0)` and `6.0.0` for the floating `6.*`. Ranges without a lower
+/// bound, such as `(, 2.0]`, are returned as is.
+pub fn lowest(range: &str) -> String {
+    let lower = range.trim_start_matches(['[', '(']).split(',').next().unwrap_or("").trim().trim_end_matches([']', ')']);
+    if !lower.starts_with(|ch: char| ch.is_ascii_digit()) {
+        return range.to_string();
+    }
+    let core = lower.split(['-', '+']).next().unwrap_or(lower);
+    let mut parts: Vec<&str> = core.split('.').take_while(|p| p.chars().all(|c| c.is_ascii_digit()) && !p.is_empty()).collect();
+    parts.resize(3, "0");
+    parts.join(".")
+}
+
+/// Package IDs are case-insensitive, so they are compared in lowercase.
+fn package_name(id: &str) -> String {
+    format!("{}{}", PREFIX, id.to_lowercase())
+}
+
+/// Read packages.lock.json of `dir`, else its obj/project.assets.json.
+/// Returns None when it has neither.
+pub fn load(dir: &Path) -> Option<io::Result<Project>> {
+    let file = [LOCKFILE, ASSETS_FILE].into_iter().find(|f| dir.join(f).is_file())?;
+    let read = File::open(dir.join(file)).and_then(|f| serde_json::from_reader::<_, Value>(f).map_err(io::Error::from));
+    Some(read.map(|data| match file {
+        LOCKFILE => parse_lockfile(&data),
+        _ => parse_assets(&data),
+    }))
+}
+
+/// Collect (name, version, dependencies) entries into packages, with reverse
+/// edges. A package restored for several target frameworks is one package.
+fn collect(file: &'static str, direct: Vec<(String, String)>, entries: Vec<(String, String, Vec<String>)>) -> Project {
+    let mut parents: HashMap<String, Vec<String>> = HashMap::new();
+    let mut specs: Vec<(String, String)> = Vec::new();
+    for (name, version, dependencies) in entries {
+        for dependency in dependencies {
+            parents
+                .entry(dependency)
+                .or_default()
+                .push(format!("{}@{}", name.trim_start_matches(PREFIX), version));
+        }
+        specs.push((name, version));
+    }
+    specs.sort();
+    specs.dedup();
+    let packages = specs
+        .into_iter()
+        .map(|(name, version)| {
+            let mut depended_by = parents.get(&name).cloned().unwrap_or_default();
+            depended_by.sort();
+            depended_by.dedup();
+            Package {
+                name,
+                version,
+                depended_by,
+            }
+        })
+        .collect();
+    let mut direct = direct;
+    direct.sort();
+    direct.dedup();
+    Project { file, direct, packages }
+}
+
+fn dependency_names(entry: &Value) -> Vec<String> {
+    entry
+        .get("dependencies")
+        .and_then(|d| d.as_object())
+        .into_iter()
+        .flatten()
+        .map(|(id, _)| package_name(id))
+        .collect()
+}
+
+/// A packages.lock.json lists the packages of each target framework by ID,
+/// typed `Direct`, `Transitive`, `CentralTransitive` or `Project` for
+/// referenced projects, which are not packages.
+fn parse_lockfile(lock: &Value) -> Project {
+    let mut direct: Vec<(String, String)> = Vec::new();
+    let mut entries: Vec<(String, String, Vec<String>)> = Vec::new();
+    let frameworks = lock.get("dependencies").and_then(|d| d.as_object()).into_iter().flatten();
+    for (id, entry) in frameworks.flat_map(|(_, packages)| packages.as_object().into_iter().flatten()) {
+        let field = |key: &str| entry.get(key).and_then(|v| v.as_str()).unwrap_or("");
+        if field("type") == "Project" || field("resolved").is_empty() {
+            continue;
+        }
+        if field("type") == "Direct" {
+            direct.push((package_name(id), field("requested").to_string()));
+        }
+        entries.push((package_name(id), field("resolved").to_string(), dependency_names(entry)));
+    }
+    collect(LOCKFILE, direct, entries)
+}
+
+/// A project.assets.json keys the packages of each target as `id/version`,
+/// and the project's own references are under `project.frameworks`.
+fn parse_assets(assets: &Value) -> Project {
+    let frameworks = assets.pointer("/project/frameworks").and_then(|f| f.as_object()).into_iter().flatten();
+    let direct = frameworks
+        .flat_map(|(_, framework)| framework.get("dependencies").and_then(|d| d.as_object()).into_iter().flatten())
+        .filter(|(_, reference)| reference.get("target").and_then(|t| t.as_str()) == Some("Package"))
+        .map(|(id, reference)| {
+            let range = reference.get("version").and_then(|v| v.as_str()).unwrap_or("");
+            (package_name(id), range.to_string())
+        })
+        .collect();
+    let targets = assets.get("targets").and_then(|t| t.as_object()).into_iter().flatten();
+    let entries = targets
+        .flat_map(|(_, packages)| packages.as_object().into_iter().flatten())
+        .filter(|(_, entry)| entry.get("type").and_then(|t| t.as_str()) == Some("package"))
+        .filter_map(|(key, entry)| {
+            let (id, version) = key.split_once('/')?;
+            Some((package_name(id), version.to_string(), dependency_names(entry)))
+        })
+        .collect();
+    collect(ASSETS_FILE, direct, entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowest_of_ranges() {
+        assert_eq!(lowest("13.0.1"), "13.0.1");
+        assert_eq!(lowest("[13.0.1, )"), "13.0.1");
+        assert_eq!(lowest("[1.0,2.0)"), "1.0.0");
+        assert_eq!(lowest("(1.0,2.0]"), "1.0.0");
+        assert_eq!(lowest("[2.1.3]"), "2.1.3");
+        assert_eq!(lowest("6.*"), "6.0.0");
+        assert_eq!(lowest("8.0.0-preview.1"), "8.0.0");
+        assert_eq!(lowest("(, 2.0]"), "(, 2.0]");
+        assert_eq!(lowest(""), "");
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ignore::WalkBuilder;
+use serde_json::Value;
+
+use crate::{git, workspaces};
+
+/// An Nx project: a directory with a project.json, or a package.json that is
+/// a workspace member or has an `nx` section.
+pub struct Project {
+    pub name: String,
+    pub root: PathBuf,
+    /// Names of the projects and packages it depends on
+    dependencies: Vec<String>,
+    /// Name of its package.json, which other projects depend on it by
+    package: Option<String>,
+}
+
+impl Project {
+    /// Whether the project has a package.json to scan, projects of
+    /// integrated repositories only have a project.json and install through
+    /// the root.
+    pub fn has_manifest(&self) -> bool {
+        self.package.is_some()
+    }
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    File::open(path).ok().and_then(|f| serde_json::from_reader(f).ok())
+}
+
+/// Whether `dir` is the `.nx` folder, where Nx keeps its cache and workspace
+/// data, of an Nx workspace.
+pub fn is_cache_dir(dir: &Path) -> bool {
+    dir.file_name().is_some_and(|n| n == ".nx") && dir.parent().is_some_and(|p| p.join("nx.json").is_file())
+}
+
+/// The `cacheDirectory` the nx.json of `root` moves the cache to, relative
+/// to `root`.
+pub fn cache_directories(root: &Path) -> Vec<String> {
+    let Some(nx) = read_json(&root.join("nx.json")) else {
+        return Vec::new();
+    };
+    [nx.get("cacheDirectory"), nx.pointer("/tasksRunnerOptions/default/options/cacheDirectory")]
+        .into_iter()
+        .flatten()
+        .filter_map(|c| c.as_str())
+        .map(|c| c.trim_start_matches("./").trim_end_matches('/').to_string())
+        .collect()
+}
+
+/// The projects of the Nx workspace at `root`, found through their
+/// project.json and package.json files.
+pub fn projects(root: &Path) -> Vec<Project> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let walker = WalkBuilder::new(&root)
+        .hidden(false)
+        .filter_entry(|e| e.file_name() != "node_modules" && !is_cache_dir(e.path()))
+        .build();
+    let mut by_root: HashMap<PathBuf, (Option<Value>, Option<Value>)> = HashMap::new();
+    for entry in walker.filter_map(Result::ok) {
+        let Some(dir) = entry.path().parent() else {
+            continue;
+        };
+        match entry.file_name().to_str() {
+            Some("project.json") => by_root.entry(dir.to_path_buf()).or_default().0 = read_json(entry.path()),
+            Some("package.json") => by_root.entry(dir.to_path_buf()).or_default().1 = read_json(entry.path()),
+            _ => {}
+        }
+    }
+    let mut projects: Vec<Project> = by_root
+        .into_iter()
+        .filter(|(dir, (project, manifest))| {
+            project.is_some()
+                || manifest.as_ref().is_some_and(|m| m.get("nx").is_some())
+                || workspaces::root(dir).or_else(|| workspaces::pnpm_root(dir)).as_deref() == Some(root.as_path())
+        })
+        .map(|(dir, (project, manifest))| {
+            let package = manifest.as_ref().and_then(|m| m.get("name")).and_then(|n| n.as_str()).map(|n| n.to_string());
+            let mut dependencies: Vec<String> = Vec::new();
+            for config in [project.as_ref(), manifest.as_ref().and_then(|m| m.get("nx"))].into_iter().flatten() {
+                let implicit = config.get("implicitDependencies").and_then(|i| i.as_array()).into_iter().flatten();
+                // `!name` removes an inferred dependency
+                dependencies.extend(implicit.filter_map(|d| d.as_str()).filter(|d| !d.starts_with('!')).map(|d| d.to_string()));
+            }
+            if let Some(manifest) = &manifest {
+                for section in ["dependencies", "devDependencies", "optionalDependencies", "peerDependencies"] {
+                    dependencies.extend(manifest.get(section).and_then(|d| d.as_object()).into_iter().flatten().map(|(n, _)| n.clone()));
+                }
+            }
+            let name = project
+                .as_ref()
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+                .map(|n| n.to_string())
+                .or_else(|| package.clone())
+                .unwrap_or_else(|| dir.file_name().unwrap_or_default().to_string_lossy().into_owned());
+            Project {
+                name,
+                root: dir,
+                dependencies,
+                package,
+            }
+        })
+        .collect();
+    projects.sort_by(|a, b| a.root.cmp(&b.root));
+    projects
+}
+
+/// The projects affected by the changes since `base`, the way `nx affected`
+/// sees them: projects containing a changed file and the projects depending
+/// on those. A change outside every project, such as the root lockfile or
+/// nx.json, affects all projects. Returns whether such a global change was
+/// found, with the affected projects.
+pub fn affected<'a>(root: &Path, projects: &'a [Project], base: &str) -> io::Result<(bool, Vec<&'a Project>)> {
+    let root = root.canonicalize()?;
+    let changed = git::changed_files(&root, base)?;
+    let mut affected: HashSet<usize> = HashSet::new();
+    let mut global = false;
+    for file in &changed {
+        // The deepest project containing the file owns it
+        let owner = projects
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.root != root && file.starts_with(&p.root))
+            .max_by_key(|(_, p)| p.root.components().count());
+        match owner {
+            Some((i, _)) => {
+                affected.insert(i);
+            }
+            None => global = true,
+        }
+    }
+    if global {
+        return Ok((true, projects.iter().collect()));
+    }
+    // Projects depending on an affected project are affected too
+    loop {
+        let names: HashSet<&str> = affected
+            .iter()
+            .flat_map(|i| [Some(projects[*i].name.as_str()), projects[*i].package.as_deref()])
+            .flatten()
+            .collect();
+        let before = affected.len();
+        for (i, project) in projects.iter().enumerate() {
+            if project.dependencies.iter().any(|d| names.contains(d.as_str())) {
+                affected.insert(i);
+            }
+        }
+        if affected.len() == before {
+            break;
+        }
+    }
+    let mut affected: Vec<&Project> = affected.into_iter().map(|i| &projects[i]).collect();
+    affected.sort_by(|a, b| a.root.cmp(&b.root));
+    Ok((false, affected))
+}
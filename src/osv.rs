@@ -0,0 +1,255 @@
+use std::io;
+
+use serde_json::{Value, json};
+
+use crate::{ecosystem, http, severity};
+use crate::matcher::{Namespace, split_namespace};
+use crate::packages::ListEntry;
+
+const OSV_API: &str = "https://api.osv.dev/v1";
+
+/// True when the JSON looks like OSV data: a single advisory, a list of
+/// advisories or an API response with a `vulns` list.
+pub fn is_osv(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => map.contains_key("affected") || map.contains_key("vulns"),
+        Value::Array(items) => items.iter().any(|i| i.get("affected").is_some()),
+        _ => false,
+    }
+}
+
+/// Translate the affected packages of OSV advisories for `ecosystem` into
+/// entries with the advisory ID as reason and its CVSS score. Enumerated
+/// `versions` are used as is, `ranges` become comparator ranges such as
+/// `>=1.0.0 <1.2.3`.
+pub fn parse(value: &Value, ecosystem: &str) -> Vec<ListEntry> {
+    let mut packages: Vec<ListEntry> = Vec::new();
+    for vuln in advisories(value) {
+        let id = vuln.get("id").and_then(|i| i.as_str()).map(|i| i.to_string());
+        let score = severity::osv_score(vuln);
+        let Some(affected) = vuln.get("affected").and_then(|a| a.as_array()) else {
+            continue;
+        };
+        for entry in affected {
+            let Some(package) = entry.get("package") else {
+                continue;
+            };
+            let eco = package.get("ecosystem").and_then(|e| e.as_str()).unwrap_or("");
+            let Some(name) = package.get("name").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            if !eco.eq_ignore_ascii_case(ecosystem) {
+                continue;
+            }
+            if let Some(versions) = entry.get("versions").and_then(|v| v.as_array()) {
+                for v in versions.iter().filter_map(|v| v.as_str()) {
+                    packages.push(ListEntry::new(ecosystem::list_name(name, eco), v, id.clone()).with_score(score));
+                }
+            }
+            for range in entry.get("ranges").and_then(|r| r.as_array()).into_iter().flatten() {
+                let kind = range.get("type").and_then(|t| t.as_str()).unwrap_or("");
+                if kind != "SEMVER" && kind != "ECOSYSTEM" {
+                    continue;
+                }
+                let events = range.get("events").and_then(|e| e.as_array()).cloned().unwrap_or_default();
+                for r in event_ranges(&events) {
+                    packages.push(ListEntry::new(ecosystem::list_name(name, eco), r, id.clone()).with_score(score));
+                }
+            }
+        }
+    }
+    packages
+}
+
+fn advisories(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(map) => match map.get("vulns").and_then(|v| v.as_array()) {
+            Some(vulns) => vulns.iter().collect(),
+            None => vec![value],
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Turn an OSV events list into comparator ranges, one per introduced event.
+fn event_ranges(events: &[Value]) -> Vec<String> {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut lower: Option<String> = None;
+    for event in events {
+        if let Some(v) = event.get("introduced").and_then(|v| v.as_str()) {
+            if let Some(l) = lower.take() {
+                ranges.push(open_range(&l));
+            }
+            lower = Some(v.to_string());
+        } else if let Some(v) = event.get("fixed").and_then(|v| v.as_str()) {
+            if let Some(l) = lower.take() {
+                ranges.push(bounded_range(&l, &format!("<{}", v)));
+            }
+        } else if let Some(v) = event.get("last_affected").and_then(|v| v.as_str())
+            && let Some(l) = lower.take()
+        {
+            ranges.push(bounded_range(&l, &format!("<={}", v)));
+        }
+    }
+    if let Some(l) = lower {
+        ranges.push(open_range(&l));
+    }
+    ranges
+}
+
+fn open_range(lower: &str) -> String {
+    if lower == "0" { "*".to_string() } else { format!(">={}", lower) }
+}
+
+fn bounded_range(lower: &str, upper: &str) -> String {
+    if lower == "0" { upper.to_string() } else { format!(">={} {}", lower, upper) }
+}
+
+/// Query the OSV API for an advisory ID (`GHSA-…`, `CVE-…`, `MAL-…`) or for all
+/// advisories of a package name in `ecosystem`.
+pub fn query(q: &str, ecosystem: &str) -> io::Result<Value> {
+    let body = if looks_like_id(q) {
+        http::get_cached(&format!("{}/vulns/{}", OSV_API, q), &[])?
+    } else {
+        let request = json!({ "package": { "name": q, "ecosystem": ecosystem } });
+        http::post_json_cached(&format!("{}/query", OSV_API), &request.to_string(), &[])?
+    };
+    serde_json::from_slice(&body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn looks_like_id(q: &str) -> bool {
+    let prefix = q.split('-').next().unwrap_or("");
+    q.contains('-') && !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Queries per `querybatch` request, the most the API accepts.
+const BATCH_SIZE: usize = 1000;
+
+/// The OSV ecosystem and package name of a listed name, e.g. `crates.io` and
+/// `serde` for `cargo:serde`. None for JSR and GitHub Packages, which OSV
+/// does not cover.
+pub fn package(name: &str) -> Option<(&'static str, &str)> {
+    let (namespace, bare) = split_namespace(name);
+    let ecosystem = match namespace {
+        Namespace::Npm => "npm",
+        Namespace::Cargo => "crates.io",
+        Namespace::Go => "Go",
+        Namespace::Composer => "Packagist",
+        Namespace::RubyGems => "RubyGems",
+        Namespace::Maven => "Maven",
+        Namespace::NuGet => "NuGet",
+        Namespace::Jsr | Namespace::GitHub => return None,
+    };
+    Some((ecosystem, bare))
+}
+
+/// The IDs of the advisories affecting each (ecosystem, name, version),
+/// batched through the `querybatch` endpoint.
+pub fn query_batch(inventory: &[(&str, &str, &str)]) -> io::Result<Vec<Vec<String>>> {
+    let mut ids: Vec<Vec<String>> = Vec::new();
+    for chunk in inventory.chunks(BATCH_SIZE) {
+        let queries: Vec<Value> = chunk
+            .iter()
+            .map(|(eco, name, version)| json!({ "package": { "name": name, "ecosystem": eco }, "version": version }))
+            .collect();
+        let request = json!({ "queries": queries });
+        let body = http::post_json_cached(&format!("{}/querybatch", OSV_API), &request.to_string(), &[])?;
+        let response: Value = serde_json::from_slice(&body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let results = response.get("results").and_then(|r| r.as_array()).cloned().unwrap_or_default();
+        for i in 0..chunk.len() {
+            let vulns = results.get(i).and_then(|r| r.get("vulns")).and_then(|v| v.as_array());
+            ids.push(vulns.into_iter().flatten().filter_map(|v| Some(v.get("id")?.as_str()?.to_string())).collect());
+        }
+    }
+    Ok(ids)
+}
+
+/// Fetch a full advisory by ID.
+pub fn fetch(id: &str) -> io::Result<Value> {
+    let body = http::get_cached(&format!("{}/vulns/{}", OSV_API, id), &[])?;
+    serde_json::from_slice(&body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(events: Value) -> Vec<String> {
+        event_ranges(events.as_array().unwrap())
+    }
+
+    #[test]
+    fn introduced_and_fixed() {
+        assert_eq!(ranges(json!([{"introduced": "1.0.0"}, {"fixed": "1.2.3"}])), vec![">=1.0.0 <1.2.3"]);
+        assert_eq!(ranges(json!([{"introduced": "0"}, {"fixed": "1.2.3"}])), vec!["<1.2.3"]);
+    }
+
+    #[test]
+    fn last_affected() {
+        assert_eq!(ranges(json!([{"introduced": "2.0.0"}, {"last_affected": "2.4.1"}])), vec![">=2.0.0 <=2.4.1"]);
+        assert_eq!(ranges(json!([{"introduced": "0"}, {"last_affected": "0.9.0"}])), vec!["<=0.9.0"]);
+    }
+
+    #[test]
+    fn open_ended() {
+        assert_eq!(ranges(json!([{"introduced": "0"}])), vec!["*"]);
+        assert_eq!(ranges(json!([{"introduced": "3.1.0"}])), vec![">=3.1.0"]);
+    }
+
+    #[test]
+    fn several_ranges() {
+        let events = json!([
+            {"introduced": "1.0.0"}, {"fixed": "1.0.5"},
+            {"introduced": "2.0.0"}, {"fixed": "2.1.1"},
+            {"introduced": "3.0.0"}
+        ]);
+        assert_eq!(ranges(events), vec![">=1.0.0 <1.0.5", ">=2.0.0 <2.1.1", ">=3.0.0"]);
+        // An introduced event without a fix before the next one stays open
+        assert_eq!(ranges(json!([{"introduced": "1.0.0"}, {"introduced": "2.0.0"}, {"fixed": "2.0.1"}])), vec![">=1.0.0", ">=2.0.0 <2.0.1"]);
+        // A fix without an introduced event has nothing to close
+        assert!(ranges(json!([{"fixed": "1.0.0"}])).is_empty());
+    }
+
+    #[test]
+    fn parses_advisories() {
+        let advisory = json!({
+            "id": "GHSA-xxxx-yyyy-zzzz",
+            "affected": [
+                {
+                    "package": {"ecosystem": "npm", "name": "debug"},
+                    "versions": ["4.4.2"],
+                    "ranges": [
+                        {"type": "SEMVER", "events": [{"introduced": "4.0.0"}, {"fixed": "4.3.1"}]},
+                        {"type": "GIT", "repo": "https://github.com/debug-js/debug", "events": [{"introduced": "abc"}]}
+                    ]
+                },
+                {"package": {"ecosystem": "PyPI", "name": "debug"}, "versions": ["1.0.0"]}
+            ]
+        });
+        let entries = parse(&json!({ "vulns": [advisory] }), "npm");
+        let listed: Vec<(&str, &str)> = entries.iter().map(|e| (e.name.as_str(), e.version.as_str())).collect();
+        assert_eq!(listed, vec![("debug", "4.4.2"), ("debug", ">=4.0.0 <4.3.1")]);
+        assert!(entries.iter().all(|e| e.reason.as_deref() == Some("GHSA-xxxx-yyyy-zzzz")));
+    }
+
+    #[test]
+    fn prefixes_other_ecosystems() {
+        let advisory = json!([{
+            "id": "RUSTSEC-2024-0001",
+            "affected": [{"package": {"ecosystem": "crates.io", "name": "serde"}, "ranges": [{"type": "ECOSYSTEM", "events": [{"introduced": "0"}, {"fixed": "1.0.5"}]}]}]
+        }]);
+        let entries = parse(&advisory, "crates.io");
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].name.as_str(), entries[0].version.as_str()), ("cargo:serde", "<1.0.5"));
+    }
+
+    #[test]
+    fn recognises_ids_and_data() {
+        assert!(looks_like_id("GHSA-xxxx-yyyy-zzzz"));
+        assert!(looks_like_id("CVE-2024-1234"));
+        assert!(!looks_like_id("left-pad"));
+        assert!(is_osv(&json!({"vulns": []})));
+        assert!(!is_osv(&json!({"name": "debug"})));
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use crate::registry::Packument;
+use crate::semver::parse_semver;
+
+/// Report of the direct dependencies compared with the registry.
+pub const FILE_NAME: &str = "output.outdated.csv";
+
+/// A direct dependency compared with the latest release in the registry.
+pub struct Entry {
+    pub package: String,
+    pub location: String,
+    /// Declared range
+    pub range: String,
+    /// Highest version the lockfiles or node_modules have, empty when none does
+    pub current: String,
+    /// What a fresh install of the range picks today
+    pub wanted: String,
+    pub latest: String,
+    /// The largest part `latest` is ahead of `current` in, `major`, `minor` or
+    /// `patch`, `missing` when nothing is locked or installed and empty when
+    /// up to date
+    pub behind: &'static str,
+    /// Releases published after `current`, up to `latest`
+    pub releases_behind: usize,
+}
+
+impl Entry {
+    /// Compare `current` with the packument. None when the package has no
+    /// `latest` dist-tag.
+    pub fn new(package: &str, location: &str, range: &str, current: Option<&str>, packument: &Packument) -> Option<Entry> {
+        let latest = packument.dist_tags.get("latest")?;
+        let latest_semver = parse_semver(latest)?;
+        let current_semver = current.and_then(parse_semver);
+        let behind = match &current_semver {
+            None => "missing",
+            Some(c) if *c >= latest_semver => "",
+            Some(c) if c.release.0 != latest_semver.release.0 => "major",
+            Some(c) if c.release.1 != latest_semver.release.1 => "minor",
+            Some(_) => "patch",
+        };
+        let releases_behind = match &current_semver {
+            Some(c) => packument
+                .versions
+                .iter()
+                .filter_map(|v| parse_semver(v))
+                .filter(|v| !v.is_prerelease() && v > c && *v <= latest_semver)
+                .count(),
+            None => 0,
+        };
+        Some(Entry {
+            package: package.to_string(),
+            location: location.to_string(),
+            range: range.to_string(),
+            current: current.unwrap_or_default().to_string(),
+            wanted: packument.resolve(range).unwrap_or_default().to_string(),
+            latest: latest.clone(),
+            behind,
+            releases_behind,
+        })
+    }
+}
+
+/// Write every compared dependency, up to date ones included.
+pub fn write(path: &Path, entries: &[Entry]) -> io::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["package", "location", "range", "current", "wanted", "latest", "behind", "releases_behind"])?;
+    for e in entries {
+        writer.write_record([
+            &e.package,
+            &e.location,
+            &e.range,
+            &e.current,
+            &e.wanted,
+            &e.latest,
+            e.behind,
+            &e.releases_behind.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Print the dependencies that are behind, grouped by location.
+pub fn print(entries: &[Entry]) {
+    let mut grouped: BTreeMap<&str, Vec<&Entry>> = BTreeMap::new();
+    for e in entries.iter().filter(|e| !e.behind.is_empty()) {
+        grouped.entry(&e.location).or_default().push(e);
+    }
+    for (location, behind) in &mut grouped {
+        behind.sort_by_key(|e| &e.package);
+        println!("{}", location);
+        for e in behind.iter() {
+            let wanted = if e.wanted.is_empty() { "nothing" } else { &e.wanted };
+            if e.current.is_empty() {
+                println!("  {}: not installed, latest {} ({} resolves to {})", e.package, e.latest, e.range, wanted);
+            } else {
+                println!(
+                    "  {}: {} -> {}, {} behind by {} releases ({} resolves to {})",
+                    e.package, e.current, e.latest, e.behind, e.releases_behind, e.range, wanted
+                );
+            }
+        }
+    }
+    let outdated: usize = grouped.values().map(|b| b.len()).sum();
+    println!("{} of {} dependencies are behind their latest release, see {}", outdated, entries.len(), FILE_NAME);
+}
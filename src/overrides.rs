@@ -0,0 +1,136 @@
+use serde_json::Value;
+
+use crate::semver::{Range, parse_version};
+
+/// A version forced on a package by `overrides` (npm), `resolutions` (yarn)
+/// or `pnpm.overrides`.
+pub struct Override {
+    /// package.json field the override comes from
+    pub field: &'static str,
+    pub name: String,
+    /// Range the override is limited to, e.g. `<2` in `foo@<2`
+    pub selector: Option<String>,
+    /// Package the override is limited to the dependencies of, e.g. `bar` in
+    /// `bar/foo` or `bar>foo`
+    pub parent: Option<String>,
+    pub version: String,
+}
+
+/// The overrides a package.json declares. npm's `$name` references are
+/// replaced by the spec of that direct dependency.
+pub fn load(pkg_json: &Value) -> Vec<Override> {
+    let mut overrides: Vec<Override> = Vec::new();
+    if let Some(map) = pkg_json.get("overrides") {
+        npm(map, None, &mut overrides);
+    }
+    for (field, map) in [("resolutions", pkg_json.get("resolutions")), ("pnpm.overrides", pkg_json.pointer("/pnpm/overrides"))] {
+        let Some(map) = map.and_then(|m| m.as_object()) else {
+            continue;
+        };
+        for (key, version) in map {
+            let Some(version) = version.as_str() else {
+                continue;
+            };
+            let path: Vec<&str> = if field == "resolutions" {
+                split_path(key).into_iter().filter(|p| *p != "**").collect()
+            } else {
+                key.split('>').collect()
+            };
+            let Some((target, parents)) = path.split_last() else {
+                continue;
+            };
+            let (name, selector) = split_selector(target);
+            overrides.push(Override {
+                field,
+                name: name.to_string(),
+                selector,
+                parent: parents.last().map(|p| split_selector(p).0.to_string()),
+                version: version.to_string(),
+            });
+        }
+    }
+    for o in overrides.iter_mut() {
+        if let Some(reference) = o.version.strip_prefix('$') {
+            o.version = ["dependencies", "devDependencies", "optionalDependencies"]
+                .iter()
+                .find_map(|s| pkg_json.get(s).and_then(|d| d.get(reference)).and_then(|v| v.as_str()))
+                .unwrap_or_default()
+                .to_string();
+        }
+    }
+    overrides.retain(|o| !o.version.is_empty());
+    overrides
+}
+
+/// npm `overrides`: a value is either the version or an object whose `.` key
+/// overrides the package itself and whose other keys override its
+/// dependencies.
+fn npm(map: &Value, parent: Option<&str>, overrides: &mut Vec<Override>) {
+    let Some(map) = map.as_object() else {
+        return;
+    };
+    for (key, value) in map {
+        if key == "." {
+            continue;
+        }
+        let (name, selector) = split_selector(key);
+        let version = match value {
+            Value::String(v) => Some(v.as_str()),
+            Value::Object(nested) => nested.get(".").and_then(|v| v.as_str()),
+            _ => None,
+        };
+        if let Some(version) = version {
+            overrides.push(Override {
+                field: "overrides",
+                name: name.to_string(),
+                selector: selector.clone(),
+                parent: parent.map(|p| p.to_string()),
+                version: version.to_string(),
+            });
+        }
+        if value.is_object() {
+            npm(value, Some(name), overrides);
+        }
+    }
+}
+
+/// Split a yarn resolution key such as `@scope/parent/**/dep` into its
+/// package names, keeping scoped names together.
+fn split_path(key: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut rest = key;
+    while !rest.is_empty() {
+        let end = if rest.starts_with('@') {
+            rest.match_indices('/').nth(1).map_or(rest.len(), |(i, _)| i)
+        } else {
+            rest.find('/').unwrap_or(rest.len())
+        };
+        parts.push(&rest[..end]);
+        rest = rest.get(end + 1..).unwrap_or("");
+    }
+    parts
+}
+
+/// Split `name@range` at the `@` after the scope.
+fn split_selector(key: &str) -> (&str, Option<String>) {
+    let from = usize::from(key.starts_with('@'));
+    match key[from..].find('@') {
+        Some(i) => (&key[..from + i], Some(key[from + i + 1..].to_string())),
+        None => (key, None),
+    }
+}
+
+/// The override that changes the direct dependency `name` declared as
+/// `spec`: one for every occurrence of the package, limited to no parent,
+/// whose selector admits the declared version.
+pub fn for_dependency<'a>(overrides: &'a [Override], name: &str, spec: &str) -> Option<&'a Override> {
+    let declared = spec.trim_start_matches('^').trim_start_matches('~');
+    overrides.iter().find(|o| {
+        o.name == name
+            && o.version != spec
+            && o.parent.is_none()
+            && o.selector.as_deref().is_none_or(|s| {
+                Range::parse(s).is_some_and(|r| parse_version(declared).is_some_and(|v| r.matches(v)))
+            })
+    })
+}
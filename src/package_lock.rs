@@ -0,0 +1,147 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde_json::{Map, Value};
+
+/// Sections of a `packages` entry whose names are resolved to other entries.
+const DEPENDENCY_SECTIONS: [&str; 4] = ["dependencies", "optionalDependencies", "peerDependencies", "devDependencies"];
+
+/// Name of the package installed at `key` of the `packages` map of a v2/v3
+/// package-lock.json: its `name` field for aliases, else the path after the
+/// last `node_modules/`.
+pub fn key_name<'a>(key: &'a str, entry: &'a Value) -> Option<&'a str> {
+    let (_, name) = key.rsplit_once("node_modules/")?;
+    Some(entry.get("name").and_then(|n| n.as_str()).unwrap_or(name))
+}
+
+/// Dependency paths from the root project to every `name@version` in the
+/// `packages` map, e.g. `app > express > debug`. Each entry is linked to the
+/// package that requires it the way node resolves: the closest
+/// `node_modules/<dep>` from the requiring entry upwards, so hoisted and
+/// nested (`node_modules/a/node_modules/b`) installs get their real parent.
+pub fn dependency_paths(lock: &Value, name: &str, version: &str) -> Vec<String> {
+    let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) else {
+        return Vec::new();
+    };
+    let parents = link_parents(packages);
+    let label = |key: &str| -> String {
+        let entry = &packages[key];
+        match key_name(key, entry) {
+            Some(name) => name.to_string(),
+            // The root project or a workspace
+            None => entry
+                .get("name")
+                .or_else(|| key.is_empty().then(|| lock.get("name")).flatten())
+                .and_then(|n| n.as_str())
+                .unwrap_or(if key.is_empty() { "(root)" } else { key })
+                .to_string(),
+        }
+    };
+    let mut paths: Vec<String> = Vec::new();
+    for (key, entry) in packages {
+        if key_name(key, entry) != Some(name) || entry.get("version").and_then(|v| v.as_str()) != Some(version) {
+            continue;
+        }
+        let mut chain = vec![label(key)];
+        let mut current = key.as_str();
+        while let Some(parent) = parents.get(current) {
+            chain.push(label(parent));
+            current = parent;
+        }
+        chain.reverse();
+        paths.push(chain.join(" > "));
+    }
+    paths.sort();
+    paths
+}
+
+/// Walk the dependency graph breadth first from the root (`""`) and
+/// workspace entries, recording for every reached entry the entry that first
+/// required it.
+fn link_parents(packages: &Map<String, Value>) -> HashMap<String, String> {
+    let mut parents: HashMap<String, String> = HashMap::new();
+    let roots = packages.keys().filter(|k| !k.contains("node_modules/")).cloned();
+    let mut queue: VecDeque<String> = roots.collect();
+    let mut seen: HashSet<String> = queue.iter().cloned().collect();
+    while let Some(from) = queue.pop_front() {
+        for target in dependencies_of(packages, &from) {
+            if seen.insert(target.clone()) {
+                parents.insert(target.clone(), from.clone());
+                queue.push_back(target);
+            }
+        }
+    }
+    parents
+}
+
+/// Packages, as (name, version), reached from the entry at `from`, e.g. a
+/// workspace at `packages/app`, directly or transitively.
+pub fn reachable(lock: &Value, from: &str) -> HashSet<(String, String)> {
+    let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) else {
+        return HashSet::new();
+    };
+    let mut reached: HashSet<(String, String)> = HashSet::new();
+    let mut seen: HashSet<String> = HashSet::from([from.to_string()]);
+    let mut queue: Vec<String> = vec![from.to_string()];
+    while let Some(key) = queue.pop() {
+        for target in dependencies_of(packages, &key) {
+            if !seen.insert(target.clone()) {
+                continue;
+            }
+            let entry = &packages[&target];
+            if let (Some(name), Some(version)) = (key_name(&target, entry), entry.get("version").and_then(|v| v.as_str())) {
+                reached.insert((name.to_string(), version.to_string()));
+            }
+            queue.push(target);
+        }
+    }
+    reached
+}
+
+/// Keys of the entries the entry at `from` requires, with workspace links
+/// followed to the workspace entry.
+fn dependencies_of(packages: &Map<String, Value>, from: &str) -> Vec<String> {
+    let Some(entry) = packages.get(from) else {
+        return Vec::new();
+    };
+    DEPENDENCY_SECTIONS
+        .iter()
+        // devDependencies of installed packages are not installed
+        .filter(|s| !from.contains("node_modules/") || **s != "devDependencies")
+        .filter_map(|s| entry.get(s).and_then(|d| d.as_object()))
+        .flat_map(|d| d.keys())
+        .filter_map(|dep| {
+            let target = resolve(packages, from, dep)?;
+            // Workspace links point at the workspace entry
+            if let Some(link) = packages[&target].get("resolved").and_then(|r| r.as_str())
+                && packages[&target].get("link").and_then(|l| l.as_bool()) == Some(true)
+                && packages.contains_key(link)
+            {
+                return Some(link.to_string());
+            }
+            Some(target)
+        })
+        .collect()
+}
+
+/// Key of the entry `dep` resolves to when required from the entry at `from`.
+fn resolve(packages: &Map<String, Value>, from: &str, dep: &str) -> Option<String> {
+    let mut base = from;
+    loop {
+        let candidate = if base.is_empty() {
+            format!("node_modules/{}", dep)
+        } else {
+            format!("{}/node_modules/{}", base, dep)
+        };
+        if packages.contains_key(&candidate) {
+            return Some(candidate);
+        }
+        if base.is_empty() {
+            return None;
+        }
+        base = match base.rsplit_once("/node_modules/") {
+            Some((parent, _)) => parent,
+            // Top level packages and workspaces resolve from the root next
+            None => "",
+        };
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::matcher::{self, Matcher};
+use crate::{ghsa, http, maven, osv};
+
+/// Version of entries that flag a package regardless of its version.
+pub const ANY_VERSION: &str = "*";
+
+/// One entry as read from a package list, before lists are merged.
+pub struct ListEntry {
+    pub name: String,
+    pub version: String,
+    /// Why the package is listed, e.g. an advisory ID or URL
+    pub reason: Option<String>,
+    /// Set for `!name@version` entries that exempt matches instead of
+    /// flagging them, holding the path the exemption is limited to, if any
+    pub exempt: Option<Option<String>>,
+    /// CVSS base score of the advisory the entry comes from
+    pub score: Option<f32>,
+}
+
+impl ListEntry {
+    pub fn new(name: impl Into<String>, version: impl Into<String>, reason: Option<String>) -> Self {
+        ListEntry {
+            name: name.into(),
+            version: version.into(),
+            reason,
+            exempt: None,
+            score: None,
+        }
+    }
+
+    pub fn with_score(mut self, score: Option<f32>) -> Self {
+        self.score = score;
+        self
+    }
+}
+
+/// An allowlist entry: matches of `name` at `version` in directories under
+/// `path` are not reported as findings.
+pub struct Exemption {
+    pub version: String,
+    pub path: Option<PathBuf>,
+    pub reason: String,
+    matcher: Box<dyn Matcher>,
+}
+
+impl Exemption {
+    pub fn matches(&self, name: &str, location: &str) -> bool {
+        self.matcher.matches(name) && self.path.as_ref().is_none_or(|p| Path::new(location).starts_with(p))
+    }
+}
+
+/// A flagged package together with the package files that listed it.
+///
+/// `name` is the entry as written in the list, e.g. `lodash` or
+/// `glob:@evil-scope/*`, `matcher` decides which project packages it covers.
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub sources: Vec<String>,
+    pub reasons: Vec<String>,
+    /// Highest advisory score among the merged entries
+    pub score: Option<f32>,
+    pub matcher: Box<dyn Matcher>,
+}
+
+impl Package {
+    pub fn matches(&self, name: &str) -> bool {
+        self.matcher.matches(name)
+    }
+}
+
+/// Flagged packages merged from one or more package files.
+#[derive(Default)]
+pub struct PackageList {
+    entries: Vec<Package>,
+    index: HashMap<(String, String), usize>,
+    exemptions: Vec<Exemption>,
+}
+
+impl PackageList {
+    /// Add an entry read from `source`. Duplicates across files are merged
+    /// into one entry that records every source and reason. Fails when the
+    /// name does not form a valid matcher.
+    pub fn add(&mut self, entry: ListEntry, source: &str) -> Result<(), String> {
+        if let Some(path) = entry.exempt {
+            let matcher = matcher::parse(&entry.name)?;
+            self.exemptions.push(Exemption {
+                version: entry.version,
+                path: path.map(PathBuf::from),
+                reason: entry.reason.unwrap_or_else(|| format!("allowed by {}", source)),
+                matcher,
+            });
+            return Ok(());
+        }
+        let key = (entry.name, entry.version);
+        let i = match self.index.get(&key) {
+            Some(&i) => i,
+            None => {
+                let matcher = matcher::parse(&key.0)?;
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push(Package {
+                    name: key.0,
+                    version: key.1,
+                    sources: Vec::new(),
+                    reasons: Vec::new(),
+                    score: None,
+                    matcher,
+                });
+                self.entries.len() - 1
+            }
+        };
+        let package = &mut self.entries[i];
+        if entry.score > package.score {
+            package.score = entry.score;
+        }
+        if !package.sources.iter().any(|s| s == source) {
+            package.sources.push(source.to_string());
+        }
+        if let Some(reason) = entry.reason
+            && !package.reasons.contains(&reason)
+        {
+            package.reasons.push(reason);
+        }
+        Ok(())
+    }
+
+    /// Add all entries of a loaded list, warning about entries that cannot be
+    /// used.
+    pub fn extend(&mut self, list: Vec<ListEntry>, source: &str) {
+        for entry in list {
+            if let Err(e) = self.add(entry, source) {
+                eprintln!("[warning] Skipping entry in {}: {}", source, e);
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Package> {
+        self.entries.iter()
+    }
+
+    pub fn exemptions(&self) -> &[Exemption] {
+        &self.exemptions
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn has_name(&self, name: &str) -> bool {
+        self.entries.iter().any(|p| p.matches(name))
+    }
+}
+
+/// Join the sources of the given entries into a stable `;` separated list for
+/// the report.
+pub fn join_sources<'a>(entries: impl Iterator<Item = &'a Package>) -> String {
+    let mut sources: Vec<&str> = entries.flat_map(|p| p.sources.iter().map(|s| s.as_str())).collect();
+    sources.sort();
+    sources.dedup();
+    sources.join(";")
+}
+
+/// Join the reasons of the given entries for the report.
+pub fn join_reasons<'a>(entries: impl Iterator<Item = &'a Package>) -> String {
+    let mut reasons: Vec<&str> = entries.flat_map(|p| p.reasons.iter().map(|s| s.as_str())).collect();
+    reasons.sort();
+    reasons.dedup();
+    reasons.join("; ")
+}
+
+/// Load the list of flagged packages.
+///
+/// Files ending in `.csv` are read as a CSV with a header row containing at
+/// least `name` and `version` columns, `.json` files are read as an OSV or
+/// GitHub advisory export for `ecosystem`, other files use the `name@version` per
+/// line text format.
+pub fn load_packages(path: &Path, ecosystem: &str) -> io::Result<Vec<ListEntry>> {
+    let file = File::open(path)?;
+    match extension(path).as_deref() {
+        Some("csv") => parse_csv(file, &path.display().to_string()),
+        Some("json") => parse_json(file, &path.display().to_string(), ecosystem),
+        _ => Ok(parse_text(file, &path.display().to_string())),
+    }
+}
+
+fn parse_json<R: Read>(reader: R, source: &str, ecosystem: &str) -> io::Result<Vec<ListEntry>> {
+    let value: Value = serde_json::from_reader(reader).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if osv::is_osv(&value) {
+        Ok(osv::parse(&value, ecosystem))
+    } else if ghsa::is_ghsa(&value) {
+        Ok(ghsa::parse(&value, ecosystem))
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a recognised advisory format", source),
+        ))
+    }
+}
+
+/// Download a package file from `url` into `cache_dir` and return the path of
+/// the cached copy. When the download fails a previously cached copy is used,
+/// so a flaky central server does not break CI runs.
+pub fn fetch_package_file(url: &str, headers: &[String], cache_dir: &Path, verbose: bool) -> io::Result<PathBuf> {
+    let path = cache_dir.join(cache_file_name(url));
+    match http::get(url, headers) {
+        Ok(body) => {
+            fs::create_dir_all(cache_dir)?;
+            fs::write(&path, body)?;
+            if verbose {
+                eprintln!("[debug] Downloaded {} to {}", url, path.display());
+            }
+            Ok(path)
+        }
+        Err(e) if path.is_file() => {
+            eprintln!("[warning] {}, using cached copy {}", e, path.display());
+            Ok(path)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Flatten the URL into a file name, keeping the extension of the URL path so
+/// the cached copy is parsed in the same format.
+fn cache_file_name(url: &str) -> String {
+    let mut name: String = url
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    let url_path = url.split(['?', '#']).next().unwrap_or(url);
+    if let Some(ext) = extension(Path::new(url_path))
+        && !name.ends_with(&format!(".{}", ext))
+    {
+        name.push('.');
+        name.push_str(&ext);
+    }
+    name
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase())
+}
+
+/// Parse the `name@version` text format, a bare `name` or `name@*` flags any
+/// version. Blank lines and lines starting with `#` are skipped. A reason can follow the entry after ` #`, or as a trailing
+/// URL:
+///
+/// ```text
+/// # Shai-Hulud wave, September 2025
+/// chalk@5.6.1 # compromised maintainer account
+/// debug@4.4.2 https://github.com/advisories/GHSA-xxxx-xxxx-xxxx
+/// ```
+///
+/// Entries starting with `!` are exemptions, optionally limited to a path:
+/// `!debug@4.4.2 /srv/sandbox # reviewed, not shipped`.
+pub fn parse_text<R: Read>(reader: R, source: &str) -> Vec<ListEntry> {
+    let mut packages: Vec<ListEntry> = Vec::new();
+    for line in BufReader::new(reader).lines().map_while(Result::ok) {
+        let l = line.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let (entry, reason) = split_reason(l);
+        let (entry, exempt) = match entry.strip_prefix('!') {
+            Some(rest) => match rest.split_once(char::is_whitespace) {
+                Some((spec, path)) => (spec, Some(Some(path.trim().to_string()))),
+                None => (rest, Some(None)),
+            },
+            None => (entry, None),
+        };
+        let mut parsed = match split_spec(entry) {
+            (name, Some(version)) if !name.is_empty() && !version.is_empty() => ListEntry::new(name, version, reason),
+            // A bare name bans every version of the package
+            (name, None) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+                ListEntry::new(name, ANY_VERSION, reason)
+            }
+            _ => {
+                eprintln!("[warning] Invalid line in {}: {}", source, l);
+                continue;
+            }
+        };
+        parsed.exempt = exempt;
+        packages.push(parsed);
+    }
+    packages
+}
+
+/// Load an `--allow-file`: the text format where every entry is an exemption,
+/// with or without the leading `!`.
+pub fn load_allow_file(path: &Path) -> io::Result<Vec<ListEntry>> {
+    let file = File::open(path)?;
+    let mut entries = parse_text(file, &path.display().to_string());
+    for entry in &mut entries {
+        if entry.exempt.is_none() {
+            entry.exempt = Some(None);
+        }
+    }
+    Ok(entries)
+}
+
+/// Split `name@version` at the version separator, keeping the `@` of scoped
+/// names: `@scope/pkg@1.2.3` gives (`@scope/pkg`, `1.2.3`) and `@scope/pkg`
+/// gives (`@scope/pkg`, None). A matcher prefix such as `glob:` is kept in
+/// the name. Versions never contain `/`, which keeps patterns like
+/// `regex:^@evil/` whole. Maven coordinates may also separate the version
+/// with a `:`, as in `maven:group:artifact:version`.
+pub fn split_spec(spec: &str) -> (&str, Option<&str>) {
+    if let Some(coordinates) = spec.strip_prefix(maven::PREFIX)
+        && !coordinates.contains('@')
+        && coordinates.matches(':').count() == 2
+        && let Some(at) = spec.rfind(':')
+    {
+        return (&spec[..at], Some(&spec[at + 1..]));
+    }
+    let body_start = matcher::PREFIXES
+        .iter()
+        .find(|p| spec.starts_with(*p))
+        .map_or(0, |p| p.len());
+    match spec[body_start..].rfind('@') {
+        Some(i) if i > 0 && !spec[body_start + i + 1..].contains('/') => {
+            let at = body_start + i;
+            (&spec[..at], Some(&spec[at + 1..]))
+        }
+        _ => (spec, None),
+    }
+}
+
+/// The package and range an npm alias dependency points at: `npm:real@^1.0.0`
+/// gives (`real`, `^1.0.0`) and `npm:@scope/real@1.2.3` (`@scope/real`,
+/// `1.2.3`). A bare `npm:real` stands for its latest version, like npm.
+pub fn npm_alias(spec: &str) -> Option<(&str, &str)> {
+    let target = spec.strip_prefix("npm:")?;
+    let from = usize::from(target.starts_with('@'));
+    match target[from..].find('@') {
+        Some(i) => Some((&target[..from + i], &target[from + i + 1..])),
+        None => Some((target, "latest")),
+    }
+}
+
+fn split_reason(line: &str) -> (&str, Option<String>) {
+    if let Some((entry, reason)) = line.split_once(" #") {
+        let reason = reason.trim();
+        return (entry.trim(), (!reason.is_empty()).then(|| reason.to_string()));
+    }
+    if let Some((entry, url)) = line.rsplit_once(char::is_whitespace)
+        && http::is_url(url)
+    {
+        return (entry.trim(), Some(url.to_string()));
+    }
+    (line, None)
+}
+
+/// Parse a vendor CSV list (`name,version,advisory`). Columns are looked up by
+/// header name so extra or reordered columns are fine. An optional `match`
+/// column selects the matcher (`exact`, `glob`, `regex` or `mirror`).
+fn parse_csv<R: Read>(reader: R, source: &str) -> io::Result<Vec<ListEntry>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+
+    let headers = rdr.headers()?.clone();
+    let column = |wanted: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(wanted));
+    let (name_idx, version_idx) = match (column("name"), column("version")) {
+        (Some(n), Some(v)) => (n, v),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} has no name/version header columns", source),
+            ));
+        }
+    };
+    let match_idx = column("match");
+    let reason_idx = column("advisory").or_else(|| column("reason"));
+
+    let mut packages: Vec<ListEntry> = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let name = record.get(name_idx).unwrap_or("");
+        let version = record.get(version_idx).unwrap_or("");
+        if name.is_empty() {
+            eprintln!("[warning] Invalid row in {}: {}", source, record.iter().collect::<Vec<_>>().join(","));
+            continue;
+        }
+        let version = if version.is_empty() { ANY_VERSION } else { version };
+        let name = match match_idx.and_then(|i| record.get(i)).filter(|m| !m.is_empty()) {
+            Some(kind) => format!("{}:{}", kind.to_ascii_lowercase(), name),
+            None => name.to_string(),
+        };
+        let reason = reason_idx.and_then(|i| record.get(i)).filter(|r| !r.is_empty()).map(|r| r.to_string());
+        packages.push(ListEntry::new(name, version, reason));
+    }
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_plain_and_scoped_specs() {
+        assert_eq!(split_spec("debug@4.4.2"), ("debug", Some("4.4.2")));
+        assert_eq!(split_spec("debug"), ("debug", None));
+        assert_eq!(split_spec("@scope/pkg@1.2.3"), ("@scope/pkg", Some("1.2.3")));
+        assert_eq!(split_spec("@scope/pkg"), ("@scope/pkg", None));
+        assert_eq!(split_spec("debug@>=1.0.0 <2.0.0"), ("debug", Some(">=1.0.0 <2.0.0")));
+        assert_eq!(split_spec("debug@"), ("debug", Some("")));
+    }
+
+    #[test]
+    fn splits_prefixed_specs() {
+        assert_eq!(split_spec("glob:@corp/*@1.0.0"), ("glob:@corp/*", Some("1.0.0")));
+        assert_eq!(split_spec("glob:@corp/*"), ("glob:@corp/*", None));
+        assert_eq!(split_spec("regex:^@evil/"), ("regex:^@evil/", None));
+        assert_eq!(split_spec("mirror:chalk@5.6.1"), ("mirror:chalk", Some("5.6.1")));
+        assert_eq!(split_spec("exact:@scope/pkg@2.0.0"), ("exact:@scope/pkg", Some("2.0.0")));
+    }
+
+    #[test]
+    fn splits_ecosystem_specs() {
+        assert_eq!(split_spec("cargo:serde@1.0.5"), ("cargo:serde", Some("1.0.5")));
+        assert_eq!(split_spec("go:github.com/org/mod@v1.2.3"), ("go:github.com/org/mod", Some("v1.2.3")));
+        assert_eq!(split_spec("jsr:@std/path@1.0.8"), ("jsr:@std/path", Some("1.0.8")));
+        assert_eq!(split_spec("maven:org.apache.logging.log4j:log4j-core:2.14.1"), ("maven:org.apache.logging.log4j:log4j-core", Some("2.14.1")));
+        assert_eq!(split_spec("maven:org.example:lib@1.0.0"), ("maven:org.example:lib", Some("1.0.0")));
+        assert_eq!(split_spec("maven:org.example:lib"), ("maven:org.example:lib", None));
+    }
+
+    #[test]
+    fn splits_npm_aliases() {
+        assert_eq!(npm_alias("npm:real@^1.0.0"), Some(("real", "^1.0.0")));
+        assert_eq!(npm_alias("npm:@scope/real@1.2.3"), Some(("@scope/real", "1.2.3")));
+        assert_eq!(npm_alias("npm:real"), Some(("real", "latest")));
+        assert_eq!(npm_alias("^1.0.0"), None);
+    }
+
+    #[test]
+    fn splits_reasons() {
+        assert_eq!(split_reason("debug@4.4.2 # compromised"), ("debug@4.4.2", Some("compromised".to_string())));
+        assert_eq!(
+            split_reason("debug@4.4.2 https://example.com/GHSA-1"),
+            ("debug@4.4.2", Some("https://example.com/GHSA-1".to_string()))
+        );
+        assert_eq!(split_reason("debug@4.4.2"), ("debug@4.4.2", None));
+        assert_eq!(split_reason("debug@4.4.2 #"), ("debug@4.4.2", None));
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::yarn;
+
+/// Files Yarn Plug'n'Play writes instead of a node_modules folder: the loader,
+/// with its runtime state inlined unless `pnpEnableInlining` is off, and that
+/// state on its own.
+const MANIFESTS: [&str; 3] = [".pnp.cjs", ".pnp.js", ".pnp.data.json"];
+
+/// Zip archives of the fetched packages, committed for zero-installs.
+const CACHE_DIR: &str = ".yarn/cache";
+
+/// Whether `dir` is installed with Yarn Plug'n'Play, where npm finds nothing.
+pub fn is_pnp(dir: &Path) -> bool {
+    MANIFESTS.iter().any(|f| dir.join(f).is_file())
+}
+
+/// Installed versions per package name of a Plug'n'Play project, from the
+/// package registry of its runtime state, else from the file names of its
+/// `.yarn/cache`. Empty when `dir` is not a Plug'n'Play project.
+pub fn installed(dir: &Path) -> HashMap<String, HashSet<String>> {
+    if !is_pnp(dir) {
+        return HashMap::new();
+    }
+    match runtime_state(dir) {
+        Some(state) => registry_versions(&state),
+        None => cache_versions(dir),
+    }
+}
+
+/// The runtime state of `.pnp.data.json`, or the `RAW_RUNTIME_STATE` string
+/// `.pnp.cjs` inlines: JSON in a single-quoted literal, continued over lines
+/// with a trailing backslash.
+fn runtime_state(dir: &Path) -> Option<Value> {
+    if let Ok(content) = fs::read_to_string(dir.join(".pnp.data.json")) {
+        return serde_json::from_str(&content).ok();
+    }
+    let content = MANIFESTS[..2].iter().find_map(|f| fs::read_to_string(dir.join(f)).ok())?;
+    let start = content.find("RAW_RUNTIME_STATE")?;
+    let quote = start + content[start..].find('\'')?;
+    let mut json = String::new();
+    let mut chars = content[quote + 1..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => return serde_json::from_str(&json).ok(),
+            '\\' => match chars.next()? {
+                '\n' => {}
+                escaped => json.push(escaped),
+            },
+            c => json.push(c),
+        }
+    }
+    None
+}
+
+/// The package name and version a registry reference points at:
+/// `npm:1.2.3`, an alias `npm:other@1.2.3`, the `virtual:<hash>#npm:1.2.3`
+/// copy of a package with peer dependencies, or a `patch:` of an npm version.
+/// Workspaces, git and file references have no registry version.
+fn reference_version(name: &str, reference: &str) -> Option<(String, String)> {
+    let reference = match reference.strip_prefix("virtual:") {
+        Some(virtual_reference) => virtual_reference.split_once('#')?.1,
+        None => reference,
+    };
+    if let Some(patch) = reference.strip_prefix("patch:") {
+        let source = patch.split('#').next()?.replace("%3A", ":");
+        let (patched, version) = source.rsplit_once("@npm:")?;
+        return reference_version(patched, &format!("npm:{}", version));
+    }
+    let version = reference.strip_prefix("npm:")?;
+    // `@` past the first character separates an aliased package from its version
+    match version.char_indices().skip(1).find(|(_, c)| *c == '@') {
+        Some((at, _)) => Some((version[..at].to_string(), version[at + 1..].to_string())),
+        None => Some((name.to_string(), version.to_string())),
+    }
+}
+
+/// Versions of `packageRegistryData`, a list of `[name, [[reference, info]]]`
+/// where the workspace root has a null name.
+fn registry_versions(state: &Value) -> HashMap<String, HashSet<String>> {
+    let mut installed: HashMap<String, HashSet<String>> = HashMap::new();
+    let registry = state.get("packageRegistryData").and_then(|r| r.as_array()).into_iter().flatten();
+    for package in registry.filter_map(|p| p.as_array()) {
+        let Some(name) = package.first().and_then(|n| n.as_str()) else {
+            continue;
+        };
+        let references = package.get(1).and_then(|r| r.as_array()).into_iter().flatten();
+        for reference in references.filter_map(|r| r.get(0).and_then(|r| r.as_str())) {
+            if let Some((name, version)) = reference_version(name, reference) {
+                installed.entry(name).or_default().insert(version);
+            }
+        }
+    }
+    installed
+}
+
+/// Versions of the npm packages in `.yarn/cache`, named
+/// `<slug>-npm-<version>-<hash>[-<checksum>].zip` where the slug of
+/// `@scope/name` is `@scope-name`. Scopes may contain a `-` themselves, so
+/// the names of yarn.lock tell where the scope ends.
+fn cache_versions(dir: &Path) -> HashMap<String, HashSet<String>> {
+    static CACHE_FILE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^(.+)-npm-(.+?)-[0-9a-f]{10}(?:-[0-9a-f]+)?\.zip$").unwrap());
+    let locked_names: HashSet<String> = fs::read_to_string(dir.join("yarn.lock"))
+        .map(|content| yarn::parse(&content).entries.iter().map(|e| e.resolved_name().to_string()).collect())
+        .unwrap_or_default();
+    let mut installed: HashMap<String, HashSet<String>> = HashMap::new();
+    for entry in fs::read_dir(dir.join(CACHE_DIR)).into_iter().flatten().filter_map(Result::ok) {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some(caps) = CACHE_FILE.captures(&file_name) else {
+            continue;
+        };
+        let slug = &caps[1];
+        let name = match slug.strip_prefix('@') {
+            Some(scoped) => locked_names
+                .iter()
+                .find(|n| n.starts_with('@') && n.replacen('/', "-", 1) == slug)
+                .cloned()
+                .or_else(|| scoped.split_once('-').map(|(scope, name)| format!("@{}/{}", scope, name))),
+            None => Some(slug.to_string()),
+        };
+        if let Some(name) = name {
+            installed.entry(name).or_default().insert(caps[2].to_string());
+        }
+    }
+    installed
+}
@@ -0,0 +1,284 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+use crate::semver::release;
+
+/// Sections of importers and packages whose dependencies are installed.
+const DEPENDENCY_SECTIONS: [&str; 3] = ["dependencies", "devDependencies", "optionalDependencies"];
+
+/// Resolved packages of a pnpm-lock.yaml as (name, version) pairs.
+pub struct Lockfile {
+    pub packages: Vec<(String, String)>,
+    /// Workspace packages by their path from the lockfile, `.` for the root,
+    /// with the dependencies they declare as (name, version or `link:` path)
+    pub importers: HashMap<String, Vec<(String, String)>>,
+    /// Dependencies of every package, as (name, version) pairs
+    dependencies: HashMap<(String, String), Vec<(String, String)>>,
+}
+
+impl Lockfile {
+    /// Versions of `name` resolved in the lockfile, reduced to
+    /// `major.minor.patch`.
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.packages
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .filter_map(|(_, v)| release(v))
+    }
+
+    /// Packages, as (name, version), the importer at `path` pulls in,
+    /// directly, transitively or through `link:`ed workspace packages.
+    pub fn reachable(&self, path: &str) -> HashSet<(String, String)> {
+        let mut reached: HashSet<(String, String)> = HashSet::new();
+        let mut importers: Vec<String> = vec![normalize(path)];
+        let mut seen_importers: HashSet<String> = HashSet::new();
+        let mut queue: Vec<(String, String)> = Vec::new();
+        while let Some(importer) = importers.pop() {
+            if !seen_importers.insert(importer.clone()) {
+                continue;
+            }
+            for (name, version) in self.importers.get(&importer).into_iter().flatten() {
+                match version.strip_prefix("link:") {
+                    Some(link) => importers.push(normalize(&format!("{}/{}", importer, link))),
+                    None => queue.extend(target(name, version)),
+                }
+            }
+        }
+        while let Some(package) = queue.pop() {
+            if let Some(deps) = self.dependencies.get(&package) {
+                queue.extend(deps.iter().filter(|d| !reached.contains(*d)).cloned());
+            }
+            reached.insert(package);
+        }
+        reached
+    }
+}
+
+/// Parse a pnpm-lock.yaml. The package keys of every lockfile version are
+/// understood: `/name/1.2.3_peer@1.0.0` (5.x), `/name@1.2.3(peer@1.0.0)`
+/// (6.x) and `name@1.2.3` under `packages` and `snapshots` (9.x). Returns
+/// None when the file is not valid YAML.
+pub fn parse(content: &str) -> Option<Lockfile> {
+    let value: Value = serde_yaml::from_str(content).ok()?;
+    let mut packages: Vec<(String, String)> = Vec::new();
+    let mut dependencies: HashMap<(String, String), Vec<(String, String)>> = HashMap::new();
+    for section in ["packages", "snapshots"] {
+        let Some(entries) = value.get(section).and_then(|p| p.as_object()) else {
+            continue;
+        };
+        for (key, entry) in entries {
+            let Some((name, version)) = split_key(key) else {
+                continue;
+            };
+            // Entries can carry their name and version explicitly, e.g. for
+            // tarball or git dependencies
+            let name = entry.get("name").and_then(|n| n.as_str()).unwrap_or(name);
+            let version = entry.get("version").and_then(|v| v.as_str()).unwrap_or(version);
+            packages.push((name.to_string(), version.to_string()));
+            let deps = dependency_sections(entry).filter_map(|(n, v)| target(n, v));
+            dependencies.entry((name.to_string(), version.to_string())).or_default().extend(deps);
+        }
+    }
+    packages.sort();
+    packages.dedup();
+    let importers = value
+        .get("importers")
+        .and_then(|i| i.as_object())
+        .into_iter()
+        .flatten()
+        .map(|(path, importer)| {
+            // 6.x and later write `{specifier, version}` where 5.x has the version
+            let deps = dependency_sections(importer)
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect();
+            (normalize(path), deps)
+        })
+        .collect();
+    Some(Lockfile {
+        packages,
+        importers,
+        dependencies,
+    })
+}
+
+/// The (name, version) dependencies of a package or importer entry.
+fn dependency_sections(entry: &Value) -> impl Iterator<Item = (&str, &str)> {
+    DEPENDENCY_SECTIONS
+        .iter()
+        .filter_map(|s| entry.get(s).and_then(|d| d.as_object()))
+        .flatten()
+        .filter_map(|(name, v)| Some((name.as_str(), v.get("version").unwrap_or(v).as_str()?)))
+}
+
+/// The package a dependency of `name` at `version` resolves to. Aliases
+/// point at another package, written `/real/1.0.0` (5.x), `/real@1.0.0`
+/// (6.x) or `real@1.0.0` (9.x); `link:` dependencies are not packages.
+fn target(name: &str, version: &str) -> Option<(String, String)> {
+    if version.starts_with("link:") {
+        return None;
+    }
+    let (name, version) = if version.starts_with(|c: char| c.is_ascii_digit()) {
+        (name, version.split(['(', '_']).next().unwrap_or(version))
+    } else {
+        split_key(version)?
+    };
+    Some((name.to_string(), version.to_string()))
+}
+
+/// An importer path with `.` and `..` resolved, `.` for the root.
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    if parts.is_empty() { ".".to_string() } else { parts.join("/") }
+}
+
+/// Split a package key into name and version, dropping peer dependency
+/// suffixes. The name ends at the first `/` (5.x) or `@` (6.x and later)
+/// after the scope.
+fn split_key(key: &str) -> Option<(&str, &str)> {
+    let key = key.strip_prefix('/').unwrap_or(key);
+    let start = if key.starts_with('@') { key.find('/')? + 1 } else { 0 };
+    let end = start + key[start..].find(['/', '@'])?;
+    let (name, version) = (&key[..end], &key[end + 1..]);
+    let version = version.split(['(', '_']).next().unwrap_or(version);
+    (!name.is_empty() && !version.is_empty()).then_some((name, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_5x_keys() {
+        assert_eq!(split_key("/debug/4.4.2"), Some(("debug", "4.4.2")));
+        assert_eq!(split_key("/@babel/core/7.22.5"), Some(("@babel/core", "7.22.5")));
+        assert_eq!(split_key("/react-dom/18.2.0_react@18.2.0"), Some(("react-dom", "18.2.0")));
+        assert_eq!(split_key("/@scope/pkg/1.0.0-rc.1_peer@1.0.0"), Some(("@scope/pkg", "1.0.0-rc.1")));
+    }
+
+    #[test]
+    fn splits_6x_keys() {
+        assert_eq!(split_key("/debug@4.4.2"), Some(("debug", "4.4.2")));
+        assert_eq!(split_key("/@scope/pkg@1.2.3(react@18.2.0)"), Some(("@scope/pkg", "1.2.3")));
+        assert_eq!(split_key("/react-dom@18.2.0(react@18.2.0)(scheduler@0.23.0)"), Some(("react-dom", "18.2.0")));
+    }
+
+    #[test]
+    fn splits_9x_keys() {
+        assert_eq!(split_key("debug@4.4.2"), Some(("debug", "4.4.2")));
+        assert_eq!(split_key("@scope/pkg@1.2.3(react@18.2.0)"), Some(("@scope/pkg", "1.2.3")));
+        assert_eq!(split_key("debug"), None);
+        assert_eq!(split_key("@scope"), None);
+        assert_eq!(split_key("debug@"), None);
+    }
+
+    const V5: &str = "
+lockfileVersion: 5.4
+importers:
+  .:
+    specifiers:
+      web: link:packages/web
+    dependencies:
+      web: link:packages/web
+  packages/web:
+    specifiers:
+      debug: ^4.3.0
+    dependencies:
+      debug: 4.4.2
+packages:
+  /debug/4.4.2:
+    resolution: {integrity: sha512-abc}
+    dependencies:
+      ms: 2.1.3
+  /ms/2.1.3:
+    resolution: {integrity: sha512-def}
+";
+
+    const V6: &str = "
+lockfileVersion: '6.0'
+dependencies:
+  react-dom:
+    specifier: ^18.2.0
+    version: 18.2.0(react@18.2.0)
+packages:
+  /react-dom@18.2.0(react@18.2.0):
+    resolution: {integrity: sha512-abc}
+    dependencies:
+      react: 18.2.0
+  /react@18.2.0:
+    resolution: {integrity: sha512-def}
+";
+
+    const V9: &str = "
+lockfileVersion: '9.0'
+importers:
+  .:
+    dependencies:
+      strip:
+        specifier: npm:real-strip@^2.0.0
+        version: real-strip@2.1.0
+      '@scope/pkg':
+        specifier: ^1.2.0
+        version: 1.2.3(react@18.2.0)
+packages:
+  real-strip@2.1.0:
+    resolution: {integrity: sha512-abc}
+  '@scope/pkg@1.2.3':
+    resolution: {integrity: sha512-def}
+snapshots:
+  real-strip@2.1.0: {}
+  '@scope/pkg@1.2.3(react@18.2.0)':
+    dependencies:
+      react: 18.2.0
+";
+
+    fn sorted(set: HashSet<(String, String)>) -> Vec<(String, String)> {
+        let mut v: Vec<(String, String)> = set.into_iter().collect();
+        v.sort();
+        v
+    }
+
+    fn pair(name: &str, version: &str) -> (String, String) {
+        (name.to_string(), version.to_string())
+    }
+
+    #[test]
+    fn parses_5x() {
+        let lock = parse(V5).unwrap();
+        assert_eq!(lock.packages, vec![pair("debug", "4.4.2"), pair("ms", "2.1.3")]);
+        // The root reaches debug through the linked workspace package
+        assert_eq!(sorted(lock.reachable(".")), vec![pair("debug", "4.4.2"), pair("ms", "2.1.3")]);
+        assert_eq!(sorted(lock.reachable("packages/web/")), vec![pair("debug", "4.4.2"), pair("ms", "2.1.3")]);
+    }
+
+    #[test]
+    fn parses_6x() {
+        let lock = parse(V6).unwrap();
+        assert_eq!(lock.versions("react-dom").collect::<Vec<&str>>(), vec!["18.2.0"]);
+        assert_eq!(lock.versions("react").collect::<Vec<&str>>(), vec!["18.2.0"]);
+    }
+
+    #[test]
+    fn parses_9x() {
+        let lock = parse(V9).unwrap();
+        assert_eq!(lock.packages, vec![pair("@scope/pkg", "1.2.3"), pair("real-strip", "2.1.0")]);
+        assert_eq!(
+            sorted(lock.reachable(".")),
+            vec![pair("@scope/pkg", "1.2.3"), pair("react", "18.2.0"), pair("real-strip", "2.1.0")]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_yaml() {
+        assert!(parse("packages: [").is_none());
+    }
+}
@@ -0,0 +1,36 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::http;
+
+/// Upload a finished report to a collection endpoint. JSON reports are
+/// validated first so a truncated file is not sent. Failed uploads are retried
+/// `retries` times with a doubling pause, starting at one second.
+pub fn push(report: &Path, endpoint: &str, headers: &[String], retries: u32) -> io::Result<()> {
+    let content = fs::read(report)?;
+    let content_type = match report.extension().and_then(|e| e.to_str()) {
+        Some("json") => {
+            serde_json::from_slice::<serde_json::Value>(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("not valid JSON: {}", e)))?;
+            "application/json"
+        }
+        Some("csv") => "text/csv",
+        _ => "application/octet-stream",
+    };
+    let mut attempt = 0;
+    loop {
+        match http::post_file(endpoint, report, content_type, headers) {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt < retries => {
+                let pause = Duration::from_secs(1 << attempt.min(6));
+                eprintln!("[warning] {}, retrying in {}s", e, pause.as_secs());
+                thread::sleep(pause);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
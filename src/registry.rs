@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::env;
+use std::io;
+
+use serde_json::Value;
+
+use crate::http;
+use crate::npmrc::Npmrc;
+use crate::matcher::{GITHUB_PACKAGES_HOST, Namespace, canonical, jsr_npm_name, split_namespace};
+use crate::semver::{Range, parse_semver};
+
+const NPM_REGISTRY: &str = "https://registry.npmjs.org";
+const JSR_NPM_REGISTRY: &str = "https://npm.jsr.io";
+
+/// The published versions of a package and its dist-tags.
+pub struct Packument {
+    pub versions: Vec<String>,
+    /// Versions by dist-tag, such as `latest` and `next`
+    pub dist_tags: HashMap<String, String>,
+    /// Tarball of every published version
+    pub dist: HashMap<String, Dist>,
+    /// Deprecation message of every deprecated version
+    pub deprecated: HashMap<String, String>,
+}
+
+/// The `dist` of a published version: where its tarball lives and its hashes.
+pub struct Dist {
+    pub tarball: Option<String>,
+    /// Subresource integrity string, e.g. `sha512-…`
+    pub integrity: Option<String>,
+    /// Hex SHA-1 of the tarball, published for every version
+    pub shasum: Option<String>,
+}
+
+impl Packument {
+    /// The version a fresh install of `range` picks today: the version of a
+    /// dist-tag such as `next`, else `latest` when it satisfies the range,
+    /// otherwise the highest matching release.
+    pub fn resolve(&self, range: &str) -> Option<&str> {
+        if let Some(version) = self.dist_tags.get(range.trim()) {
+            return Some(version);
+        }
+        let range = Range::parse(range)?;
+        // Prereleases only when the range names one of the same version, like npm
+        let satisfies = |v: &str| parse_semver(v).is_some_and(|p| range.matches_semver(&p, false));
+        if let Some(latest) = self.dist_tags.get("latest").filter(|l| satisfies(l)) {
+            return Some(latest);
+        }
+        self.versions
+            .iter()
+            .filter(|v| satisfies(v))
+            .max_by_key(|v| parse_semver(v))
+            .map(|v| v.as_str())
+    }
+}
+
+/// Fetch the abbreviated packument of `name` from the registry of its
+/// namespace.
+pub fn fetch(name: &str, npmrc: &Npmrc) -> io::Result<Packument> {
+    let value = document(name, "application/vnd.npm.install-v1+json", npmrc)?;
+    let published = value.get("versions").and_then(|v| v.as_object());
+    let versions = published.map(|v| v.keys().cloned().collect()).unwrap_or_default();
+    let dist_tags = value
+        .get("dist-tags")
+        .and_then(|t| t.as_object())
+        .into_iter()
+        .flatten()
+        .filter_map(|(tag, version)| Some((tag.clone(), version.as_str()?.to_string())))
+        .collect();
+    let field = |dist: &Value, key: &str| dist.get(key).and_then(|f| f.as_str()).map(|f| f.to_string());
+    let dist = published
+        .into_iter()
+        .flatten()
+        .filter_map(|(version, manifest)| {
+            let dist = manifest.get("dist")?;
+            let dist = Dist {
+                tarball: field(dist, "tarball"),
+                integrity: field(dist, "integrity"),
+                shasum: field(dist, "shasum"),
+            };
+            Some((version.clone(), dist))
+        })
+        .collect();
+    let deprecated = published
+        .into_iter()
+        .flatten()
+        .filter_map(|(version, manifest)| Some((version.clone(), field(manifest, "deprecated").filter(|m| !m.is_empty())?)))
+        .collect();
+    Ok(Packument {
+        versions,
+        dist_tags,
+        dist,
+        deprecated,
+    })
+}
+
+/// What the full packument records of every version: when it was published,
+/// by whom and who maintained the package at the time.
+pub struct History {
+    /// Publish timestamp by version
+    pub times: HashMap<String, String>,
+    /// npm user who published each version
+    pub publishers: HashMap<String, String>,
+    /// Sorted maintainer names by version
+    pub maintainers: HashMap<String, Vec<String>>,
+}
+
+impl History {
+    /// The release before `version`: the highest lower version, a prerelease
+    /// only when `version` is one.
+    pub fn previous(&self, version: &str) -> Option<&str> {
+        let current = parse_semver(version)?;
+        self.maintainers
+            .keys()
+            .filter_map(|v| Some((v, parse_semver(v)?)))
+            .filter(|(_, p)| *p < current && (!p.is_prerelease() || current.is_prerelease()))
+            .max_by(|a, b| a.1.cmp(&b.1))
+            .map(|(v, _)| v.as_str())
+    }
+}
+
+/// Fetch the history of `name` from the full packument, as the abbreviated
+/// one has no publish dates or maintainers.
+pub fn history(name: &str, npmrc: &Npmrc) -> io::Result<History> {
+    let value = document(name, "application/json", npmrc)?;
+    let times = value.get("time").and_then(|t| t.as_object()).into_iter().flatten();
+    let times = times
+        .filter(|(version, _)| *version != "created" && *version != "modified")
+        .filter_map(|(version, time)| Some((version.clone(), time.as_str()?.to_string())))
+        .collect();
+    let published = value.get("versions").and_then(|v| v.as_object()).into_iter().flatten();
+    let mut publishers: HashMap<String, String> = HashMap::new();
+    let mut maintainers: HashMap<String, Vec<String>> = HashMap::new();
+    for (version, manifest) in published {
+        if let Some(user) = manifest.pointer("/_npmUser/name").and_then(|n| n.as_str()) {
+            publishers.insert(version.clone(), user.to_string());
+        }
+        let mut names: Vec<String> = manifest
+            .get("maintainers")
+            .and_then(|m| m.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|m| Some(m.get("name")?.as_str()?.to_string()))
+            .collect();
+        names.sort();
+        maintainers.insert(version.clone(), names);
+    }
+    Ok(History {
+        times,
+        publishers,
+        maintainers,
+    })
+}
+
+/// Fetch the packument of `name` in the `accept` format from the registry of
+/// its namespace: npm and JSR packages from the registry `.npmrc` configures
+/// for their scope, else from npm and JSR's npm compatibility registry, `gpr:`
+/// packages from GitHub Packages. Requests carry the `.npmrc` credentials of
+/// their registry, GitHub Packages ones fall back to `GITHUB_TOKEN` or
+/// `NODE_AUTH_TOKEN`.
+fn document(name: &str, accept: &str, npmrc: &Npmrc) -> io::Result<Value> {
+    let mut headers = vec![format!("Accept: {}", accept)];
+    let (registry, name) = match split_namespace(&canonical(name)) {
+        (Namespace::Jsr, bare) => {
+            let npm_name = jsr_npm_name(bare).unwrap_or_default();
+            (npmrc.registry(&npm_name).unwrap_or(JSR_NPM_REGISTRY).to_string(), npm_name)
+        }
+        (Namespace::GitHub, bare) => (format!("https://{}", GITHUB_PACKAGES_HOST), bare.to_string()),
+        (Namespace::Npm, bare) => (npmrc.registry(bare).unwrap_or(NPM_REGISTRY).to_string(), bare.to_string()),
+        (Namespace::Cargo, bare) => {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} is a crate, not an npm package", bare)));
+        }
+        (Namespace::Go, bare) => {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} is a Go module, not an npm package", bare)));
+        }
+        (Namespace::Composer, bare) => {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} is a Composer package, not an npm package", bare)));
+        }
+        (Namespace::RubyGems, bare) => {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} is a gem, not an npm package", bare)));
+        }
+        (Namespace::Maven, bare) => {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} is a Maven artifact, not an npm package", bare)));
+        }
+        (Namespace::NuGet, bare) => {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} is a NuGet package, not an npm package", bare)));
+        }
+    };
+    let url = format!("{}/{}", registry, name.replace('/', "%2f"));
+    let github_token = || env::var("GITHUB_TOKEN").or_else(|_| env::var("NODE_AUTH_TOKEN")).ok().map(|t| format!("Bearer {}", t));
+    let authorization = npmrc
+        .authorization(&url)
+        .or_else(|| is_from(&url, GITHUB_PACKAGES_HOST).then(github_token).flatten());
+    if let Some(authorization) = authorization {
+        headers.push(format!("Authorization: {}", authorization));
+    }
+    let body = http::get_cached(&url, &headers)?;
+    serde_json::from_slice(&body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Whether a tarball URL was downloaded from `host`.
+pub fn is_from(url: &str, host: &str) -> bool {
+    url.split("://").nth(1).and_then(|rest| rest.split('/').next()) == Some(host)
+}
@@ -0,0 +1,165 @@
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::ecosystem;
+
+/// SBOM files read next to lockfiles: CycloneDX's `bom.json` and
+/// `*.cdx.json`, and SPDX's `*.spdx.json` such as `sbom.spdx.json`.
+pub const FILES: [&str; 3] = ["bom.json", "*.cdx.json", "*.spdx.json"];
+
+/// The components an SBOM lists, as (name, version).
+pub struct Sbom {
+    pub file: String,
+    pub components: Vec<(String, String)>,
+}
+
+impl Sbom {
+    pub fn versions(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.components.iter().filter(move |(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Read the SBOM files of `dir`, in file name order. JSON files that are
+/// neither CycloneDX nor SPDX are skipped.
+pub fn load(dir: &Path) -> Vec<io::Result<Sbom>> {
+    let mut files: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|f| ecosystem::is_project_file(&FILES, f) && dir.join(f).is_file())
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .filter_map(|file| {
+            let data: Value = match File::open(dir.join(&file)).and_then(|f| serde_json::from_reader(f).map_err(io::Error::from)) {
+                Ok(data) => data,
+                Err(e) => return Some(Err(io::Error::new(e.kind(), format!("{}: {}", file, e)))),
+            };
+            let mut components = if data.get("bomFormat").and_then(|f| f.as_str()) == Some("CycloneDX") {
+                cyclonedx_components(&data)
+            } else if data.get("spdxVersion").is_some() {
+                spdx_packages(&data)
+            } else {
+                return None;
+            };
+            components.sort();
+            components.dedup();
+            Some(Ok(Sbom { file, components }))
+        })
+        .collect()
+}
+
+/// Components of a CycloneDX BOM, nested ones included. The component the
+/// BOM describes (`metadata.component`) is the artifact itself and not listed.
+fn cyclonedx_components(bom: &Value) -> Vec<(String, String)> {
+    let mut components: Vec<(String, String)> = Vec::new();
+    let mut pending: Vec<&Value> = bom.get("components").and_then(|c| c.as_array()).into_iter().flatten().collect();
+    while let Some(component) = pending.pop() {
+        pending.extend(component.get("components").and_then(|c| c.as_array()).into_iter().flatten());
+        let field = |key: &str| component.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty());
+        let entry = match field("purl") {
+            Some(purl) => from_purl(purl),
+            // Without a purl, group and name read like an npm scope and name
+            None => field("name").zip(field("version")).map(|(name, version)| {
+                let name = match field("group") {
+                    Some(group) => format!("{}/{}", group, name),
+                    None => name.to_string(),
+                };
+                (name, version.to_string())
+            }),
+        };
+        components.extend(entry);
+    }
+    components
+}
+
+/// Packages of an SPDX document, except those the document describes.
+fn spdx_packages(document: &Value) -> Vec<(String, String)> {
+    let mut described: Vec<&str> =
+        document.get("documentDescribes").and_then(|d| d.as_array()).into_iter().flatten().filter_map(|d| d.as_str()).collect();
+    for relationship in document.get("relationships").and_then(|r| r.as_array()).into_iter().flatten() {
+        let field = |key: &str| relationship.get(key).and_then(|v| v.as_str()).unwrap_or("");
+        if field("relationshipType") == "DESCRIBES" && field("spdxElementId") == "SPDXRef-DOCUMENT" {
+            described.push(field("relatedSpdxElement"));
+        }
+    }
+    let packages = document.get("packages").and_then(|p| p.as_array()).into_iter().flatten();
+    packages
+        .filter(|package| !package.get("SPDXID").and_then(|id| id.as_str()).is_some_and(|id| described.contains(&id)))
+        .filter_map(|package| {
+            let purl = package
+                .get("externalRefs")
+                .and_then(|r| r.as_array())
+                .into_iter()
+                .flatten()
+                .find(|r| r.get("referenceType").and_then(|t| t.as_str()) == Some("purl"))
+                .and_then(|r| r.get("referenceLocator").and_then(|l| l.as_str()));
+            match purl {
+                Some(purl) => from_purl(purl),
+                None => {
+                    let name = package.get("name").and_then(|n| n.as_str())?;
+                    let version = package.get("versionInfo").and_then(|v| v.as_str()).filter(|v| !v.is_empty())?;
+                    Some((name.to_string(), version.to_string()))
+                }
+            }
+        })
+        .collect()
+}
+
+/// Decode the `%XX` escapes of a purl part.
+fn decode(part: &str) -> String {
+    let bytes = part.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| part.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The list name and version of a package URL such as
+/// `pkg:npm/%40scope/name@1.0.0` or `pkg:maven/group/artifact@1.0`. Types
+/// without an ecosystem here, and purls without a version, give None.
+fn from_purl(purl: &str) -> Option<(String, String)> {
+    let rest = purl.strip_prefix("pkg:")?;
+    let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+    let (path, version) = rest.rsplit_once('@')?;
+    let (purl_type, path) = path.split_once('/')?;
+    let (namespace, name) = match path.rsplit_once('/') {
+        Some((namespace, name)) => (Some(decode(namespace)), decode(name)),
+        None => (None, decode(path)),
+    };
+    let joined = |separator: &str| match &namespace {
+        Some(namespace) => format!("{}{}{}", namespace, separator, name),
+        None => name.clone(),
+    };
+    let (name, osv_ecosystem) = match purl_type.to_lowercase().as_str() {
+        "npm" => (joined("/"), "npm"),
+        "cargo" => (name.clone(), "crates.io"),
+        "golang" => (joined("/"), "Go"),
+        "composer" => (joined("/"), "Packagist"),
+        "gem" => (name.clone(), "RubyGems"),
+        "maven" => (joined(":"), "Maven"),
+        "nuget" => (name.clone(), "NuGet"),
+        _ => return None,
+    };
+    Some((ecosystem::list_name(&name, osv_ecosystem), decode(version)))
+}
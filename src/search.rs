@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+/// A package found by `search`.
+pub struct Hit {
+    pub package: String,
+    pub version: String,
+    pub location: String,
+}
+
+/// Whether `name` contains any of `terms`, ignoring case.
+pub fn matches(name: &str, terms: &[String]) -> bool {
+    let name = name.to_lowercase();
+    terms.iter().any(|t| name.contains(&t.to_lowercase()))
+}
+
+/// Packages of a previous `output.csv` whose name contains one of `terms`.
+pub fn in_report(path: &Path, terms: &[String]) -> io::Result<Vec<Hit>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} has no {} column", path.display(), name)))
+    };
+    let (package, version, location) = (column("package")?, column("version")?, column("location")?);
+    let mut hits: Vec<Hit> = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |i: usize| record.get(i).unwrap_or("").to_string();
+        if matches(&field(package), terms) {
+            hits.push(Hit {
+                package: field(package),
+                version: field(version),
+                location: field(location),
+            });
+        }
+    }
+    Ok(hits)
+}
+
+/// Print the hits grouped by package, one line per version with the
+/// locations using it.
+pub fn print(hits: &[Hit]) {
+    let mut grouped: BTreeMap<&str, BTreeMap<&str, Vec<&str>>> = BTreeMap::new();
+    for hit in hits {
+        let locations = grouped.entry(&hit.package).or_default().entry(&hit.version).or_default();
+        if !locations.contains(&hit.location.as_str()) {
+            locations.push(&hit.location);
+        }
+    }
+    for (package, versions) in &grouped {
+        println!("{}", package);
+        for (version, locations) in versions {
+            let version = if version.is_empty() { "(no version)" } else { version };
+            println!("  {}: {}", version, locations.join(", "));
+        }
+    }
+    let locations: usize = {
+        let mut all: Vec<&str> = hits.iter().map(|h| h.location.as_str()).collect();
+        all.sort();
+        all.dedup();
+        all.len()
+    };
+    println!("{} matching package(s) in {} location(s)", grouped.len(), locations);
+}
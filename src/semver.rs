@@ -0,0 +1,495 @@
+use std::cmp::Ordering;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// A `major.minor.patch` version. Anything after the patch number (prerelease
+/// or build suffixes) is ignored. Parts are 64-bit, as some packages use
+/// timestamps such as `1.0.20240101120000`.
+pub type Version = (u64, u64, u64);
+
+static RELEASE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d+\.\d+\.\d+").unwrap());
+
+pub fn parse_version(v: &str) -> Option<Version> {
+    let mut parts = release(v)?.split('.').map(|s| s.parse::<u64>().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// The leading `major.minor.patch` of a version string, e.g. `1.2.3` for
+/// `1.2.3-beta.1` or `1.2.3(react@18.2.0)`.
+pub fn release(v: &str) -> Option<&str> {
+    RELEASE.find(v).map(|m| m.as_str())
+}
+
+/// A dot-separated part of a prerelease. Numeric identifiers sort below
+/// alphanumeric ones, which sort as ASCII.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+fn parse_prerelease(pre: &str) -> Vec<Identifier> {
+    pre.split('.')
+        .map(|part| match part.parse::<u64>() {
+            Ok(n) if part.chars().all(|c| c.is_ascii_digit()) => Identifier::Numeric(n),
+            _ => Identifier::Alphanumeric(part.to_string()),
+        })
+        .collect()
+}
+
+/// A version with its prerelease, e.g. `5.0.0-rc.1`, ordered by semver
+/// precedence: `1.0.0-alpha < 1.0.0-alpha.1 < 1.0.0-beta < 1.0.0`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Semver {
+    pub release: Version,
+    pre: Vec<Identifier>,
+}
+
+impl Semver {
+    fn new(release: Version) -> Semver {
+        Semver { release, pre: Vec::new() }
+    }
+
+    /// The lowest prerelease of `release`, `<release>-0`, which keeps the
+    /// prereleases of an excluded upper bound out of x, caret and tilde ranges.
+    fn lowest_prerelease(release: Version) -> Semver {
+        Semver {
+            release,
+            pre: vec![Identifier::Numeric(0)],
+        }
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+impl Ord for Semver {
+    fn cmp(&self, other: &Semver) -> Ordering {
+        self.release.cmp(&other.release).then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => self.pre.cmp(&other.pre),
+        })
+    }
+}
+
+impl PartialOrd for Semver {
+    fn partial_cmp(&self, other: &Semver) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Parse a semver version: `1.2.3`, `v1.2.3`, `1.2.3-rc.1` or
+/// `1.2.3+sha.abc`. Build metadata takes no part in matching. Only pnpm's
+/// peer suffixes, `1.2.3(react@18.2.0)` and `1.2.3_react@18.2.0`, may follow.
+pub fn parse_semver(v: &str) -> Option<Semver> {
+    static SEMVER: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^v?(\d+\.\d+\.\d+)(?:-([0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*))?(?:\+[0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*)?(?:$|[(_])").unwrap()
+    });
+    let caps = SEMVER.captures(v)?;
+    semver_from(&caps[1], caps.get(2).map(|p| p.as_str()))
+}
+
+/// Parse a version the way npm's loose mode and vendored manifests write
+/// them: `=1.2.3`, ` v1.2.3`, `1.2.3beta.1`, and `1.2.3.4` or `2.0.0.Final`,
+/// whose parts after the patch are ignored. Whatever follows is ignored too.
+pub fn parse_loose(v: &str) -> Option<Semver> {
+    static LOOSE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^[=v\s]*(\d+\.\d+\.\d+)(?:\.[0-9A-Za-z.]*)?(?:-?([0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*))?").unwrap()
+    });
+    let caps = LOOSE.captures(v)?;
+    semver_from(&caps[1], caps.get(2).map(|p| p.as_str()))
+}
+
+fn semver_from(release: &str, pre: Option<&str>) -> Option<Semver> {
+    Some(Semver {
+        release: parse_version(release)?,
+        pre: pre.map(parse_prerelease).unwrap_or_default(),
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+#[derive(Clone)]
+struct Comparator {
+    op: Op,
+    version: Semver,
+}
+
+impl Comparator {
+    fn matches(&self, v: &Semver) -> bool {
+        match self.op {
+            Op::Lt => *v < self.version,
+            Op::Le => *v <= self.version,
+            Op::Gt => *v > self.version,
+            Op::Ge => *v >= self.version,
+            Op::Eq => *v == self.version,
+        }
+    }
+}
+
+/// A node-semver style range: comparator sets joined by `||`. Supports
+/// `<`, `<=`, `>`, `>=`, `=`, `^`, `~`, hyphen ranges (`1.2.3 - 2.0.0`) and
+/// x-ranges (`1.x`, `1.2.*`, `1`, `*`), with prereleases (`>=2.0.0-rc.1`).
+/// The `semver` crate is not used for this: it follows Cargo, where a bare
+/// `1.2.3` means `^1.2.3` and there is no `||` or hyphen range. Requirements
+/// of the other ecosystems are converted into these ranges instead, so every
+/// list entry and every found range is matched the same way.
+pub struct Range {
+    sets: Vec<Vec<Comparator>>,
+}
+
+impl Range {
+    pub fn parse(range: &str) -> Option<Range> {
+        let mut sets = Vec::new();
+        for set in range.split("||") {
+            sets.push(parse_set(set.trim())?);
+        }
+        Some(Range { sets })
+    }
+
+    pub fn matches(&self, v: Version) -> bool {
+        self.matches_semver(&Semver::new(v), false)
+    }
+
+    /// Whether the range admits `v`. As in node-semver, a prerelease is only
+    /// admitted by a comparator set naming a prerelease of the same
+    /// `major.minor.patch`, so `^5.0.0` admits neither `5.1.0-rc.1` nor
+    /// `6.0.0-rc.1` while `>=5.1.0-rc.0` admits `5.1.0-rc.1`, unless
+    /// `include_prerelease` lets every prerelease in.
+    pub fn matches_semver(&self, v: &Semver, include_prerelease: bool) -> bool {
+        self.sets.iter().any(|set| {
+            set.iter().all(|c| c.matches(v))
+                && (include_prerelease
+                    || !v.is_prerelease()
+                    || set.iter().any(|c| c.version.is_prerelease() && c.version.release == v.release))
+        })
+    }
+
+    /// The lowest version the range admits, like node-semver's `minVersion`:
+    /// `1.2.0` for `^1.2.0`, `1.3.0` for `>1.2.x`, `0.0.0` for `*`. None when
+    /// no version satisfies it, e.g. `>2.0.0 <1.0.0`.
+    pub fn min_version(&self) -> Option<Semver> {
+        self.sets.iter().filter_map(|set| set_min(set)).min()
+    }
+
+    /// Whether some version satisfies both ranges, e.g. `^1.2.0` and
+    /// `>=1.4.0 <1.4.3`. Prereleases count as any other version here.
+    pub fn intersects(&self, other: &Range) -> bool {
+        self.sets.iter().any(|a| {
+            other.sets.iter().any(|b| {
+                let both: Vec<Comparator> = a.iter().chain(b).cloned().collect();
+                set_min(&both).is_some()
+            })
+        })
+    }
+}
+
+/// The lowest version a comparator set admits: its highest lower bound, when
+/// that satisfies the upper bounds too.
+fn set_min(set: &[Comparator]) -> Option<Semver> {
+    let candidate = set
+        .iter()
+        .filter_map(|c| match c.op {
+            Op::Ge | Op::Eq => Some(c.version.clone()),
+            Op::Gt if c.version.is_prerelease() => {
+                let mut next = c.version.clone();
+                next.pre.push(Identifier::Numeric(0));
+                Some(next)
+            }
+            Op::Gt => {
+                let (major, minor, patch) = c.version.release;
+                Some(Semver::new((major, minor, patch.saturating_add(1))))
+            }
+            Op::Lt | Op::Le => None,
+        })
+        .max()
+        .unwrap_or(Semver::new((0, 0, 0)));
+    set.iter().all(|c| c.matches(&candidate)).then_some(candidate)
+}
+
+/// A possibly partial version, `None` parts were omitted or wildcards, with
+/// the prerelease of a full one.
+type Partial = (Option<u64>, Option<u64>, Option<u64>, Vec<Identifier>);
+
+fn parse_partial(s: &str) -> Option<Partial> {
+    let s = s.trim_start_matches(['v', '=']);
+    let s = s.split('+').next().unwrap_or(s);
+    let (core, pre) = match s.split_once('-') {
+        Some((core, pre)) => (core, parse_prerelease(pre)),
+        None => (s, Vec::new()),
+    };
+    let mut parts = core.split('.');
+    let mut next = || -> Option<Option<u64>> {
+        match parts.next() {
+            None | Some("x") | Some("X") | Some("*") => Some(None),
+            Some(p) => p.parse().ok().map(Some),
+        }
+    };
+    let major = next()?;
+    let minor = if major.is_some() { next()? } else { None };
+    let patch = if minor.is_some() { next()? } else { None };
+    let pre = if patch.is_some() { pre } else { Vec::new() };
+    Some((major, minor, patch, pre))
+}
+
+fn parse_set(set: &str) -> Option<Vec<Comparator>> {
+    if set.is_empty() {
+        return Some(Vec::new());
+    }
+    if let Some((lower, upper)) = set.split_once(" - ") {
+        let mut comparators = desugar(">=", parse_partial(lower.trim())?);
+        comparators.extend(desugar("<=", parse_partial(upper.trim())?));
+        return Some(comparators);
+    }
+
+    // Join operators separated from their version, e.g. `>= 1.2.3`
+    let mut tokens: Vec<String> = Vec::new();
+    for token in set.split_whitespace() {
+        match tokens.last_mut() {
+            Some(last) if last.chars().all(|c| "<>=^~".contains(c)) => last.push_str(token),
+            _ => tokens.push(token.to_string()),
+        }
+    }
+
+    let mut comparators = Vec::new();
+    for token in &tokens {
+        let op_len = token.find(|c: char| !"<>=^~".contains(c)).unwrap_or(token.len());
+        let (op, version) = token.split_at(op_len);
+        comparators.extend(desugar(op, parse_partial(version)?));
+    }
+    Some(comparators)
+}
+
+fn cmp(op: Op, version: Semver) -> Comparator {
+    Comparator { op, version }
+}
+
+/// A comparator no version satisfies.
+fn nothing() -> Comparator {
+    cmp(Op::Lt, Semver::lowest_prerelease((0, 0, 0)))
+}
+
+/// Expand one operator and partial version into plain comparators.
+fn desugar(op: &str, partial: Partial) -> Vec<Comparator> {
+    let (major, minor, patch, pre) = partial;
+    let lower = Semver {
+        release: (major.unwrap_or(0), minor.unwrap_or(0), patch.unwrap_or(0)),
+        pre,
+    };
+    // Exclusive upper bound of the x-range, None when the major is a wildcard
+    let x_upper = match (major, minor, patch) {
+        (Some(m), None, _) => Some(Semver::lowest_prerelease((m + 1, 0, 0))),
+        (Some(m), Some(n), None) => Some(Semver::lowest_prerelease((m, n + 1, 0))),
+        _ => None,
+    };
+    let full = patch.is_some();
+    match op {
+        "" | "=" => match (full, x_upper) {
+            (true, _) => vec![cmp(Op::Eq, lower)],
+            (false, Some(upper)) => vec![cmp(Op::Ge, lower), cmp(Op::Lt, upper)],
+            (false, None) => Vec::new(),
+        },
+        ">" => match (full, x_upper) {
+            (true, _) => vec![cmp(Op::Gt, lower)],
+            (false, Some(upper)) => vec![cmp(Op::Ge, Semver::new(upper.release))],
+            (false, None) => vec![nothing()],
+        },
+        ">=" => vec![cmp(Op::Ge, lower)],
+        "<" => match (major, full) {
+            (Some(_), true) => vec![cmp(Op::Lt, lower)],
+            (Some(_), false) => vec![cmp(Op::Lt, Semver::lowest_prerelease(lower.release))],
+            (None, _) => vec![nothing()],
+        },
+        "<=" => match (full, x_upper) {
+            (true, _) => vec![cmp(Op::Le, lower)],
+            (false, Some(upper)) => vec![cmp(Op::Lt, upper)],
+            (false, None) => Vec::new(),
+        },
+        "~" | "~>" => match major {
+            Some(m) => {
+                let upper = match minor {
+                    Some(n) => (m, n + 1, 0),
+                    None => (m + 1, 0, 0),
+                };
+                vec![cmp(Op::Ge, lower), cmp(Op::Lt, Semver::lowest_prerelease(upper))]
+            }
+            None => Vec::new(),
+        },
+        // Caret allows changes that do not modify the leftmost non-zero part:
+        // `^0.2.3` is `<0.3.0`, `^0.0.3` is `<0.0.4` and `^0.0` is `<0.1.0`
+        "^" => {
+            let upper = match (major, minor, patch) {
+                (None, _, _) => return Vec::new(),
+                (Some(0), Some(0), Some(p)) => (0, 0, p + 1),
+                (Some(0), Some(n), _) => (0, n + 1, 0),
+                (Some(m), _, _) => (m + 1, 0, 0),
+            };
+            vec![cmp(Op::Ge, lower), cmp(Op::Lt, Semver::lowest_prerelease(upper))]
+        }
+        // Unknown operator, match nothing
+        _ => vec![nothing()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn admits(range: &str, version: &str) -> bool {
+        let range = Range::parse(range).unwrap_or_else(|| panic!("{} does not parse", range));
+        range.matches_semver(&parse_semver(version).unwrap(), false)
+    }
+
+    fn check(range: &str, inside: &[&str], outside: &[&str]) {
+        for v in inside {
+            assert!(admits(range, v), "{} should admit {}", range, v);
+        }
+        for v in outside {
+            assert!(!admits(range, v), "{} should not admit {}", range, v);
+        }
+    }
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(parse_version("1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_version("1.2.3-rc.1"), Some((1, 2, 3)));
+        assert_eq!(parse_version("1.0.20240101120000"), Some((1, 0, 20240101120000)));
+        assert_eq!(parse_version("1.2"), None);
+        assert_eq!(release("1.2.3(react@18.2.0)"), Some("1.2.3"));
+        assert!(parse_semver("v1.2.3-rc.1+build.5").is_some_and(|v| v.is_prerelease()));
+        assert!(parse_semver("1.2.3_react@18.2.0").is_some());
+        assert!(parse_semver("1.2.3.4").is_none());
+        assert!(parse_semver("=1.2.3").is_none());
+        assert_eq!(parse_loose("=1.2.3").map(|v| v.release), Some((1, 2, 3)));
+        assert_eq!(parse_loose("2.0.0.Final").map(|v| v.release), Some((2, 0, 0)));
+        assert!(parse_loose("1.2.3beta.1").is_some_and(|v| v.is_prerelease()));
+    }
+
+    #[test]
+    fn orders_prereleases() {
+        let ordered = ["1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta", "1.0.0-beta.2", "1.0.0-beta.11", "1.0.0-rc.1", "1.0.0"];
+        for pair in ordered.windows(2) {
+            assert!(parse_semver(pair[0]) < parse_semver(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+        assert!(parse_semver("1.0.0+a") == parse_semver("1.0.0+b"));
+    }
+
+    #[test]
+    fn exact_and_comparators() {
+        check("1.2.3", &["1.2.3"], &["1.2.4", "1.2.2"]);
+        check("=1.2.3", &["1.2.3"], &["1.2.4"]);
+        check("v1.2.3", &["1.2.3"], &["1.2.4"]);
+        check(">1.2.3", &["1.2.4", "2.0.0"], &["1.2.3", "1.0.0"]);
+        check(">=1.2.3", &["1.2.3", "1.3.0"], &["1.2.2"]);
+        check("<1.2.3", &["1.2.2", "0.1.0"], &["1.2.3"]);
+        check("<=1.2.3", &["1.2.3", "1.2.2"], &["1.2.4"]);
+        check(">= 1.2.3 < 2", &["1.2.3", "1.9.9"], &["2.0.0", "1.2.2"]);
+        check(">=1.0.0 <1.4.0", &["1.3.9"], &["1.4.0"]);
+    }
+
+    #[test]
+    fn caret() {
+        check("^1.2.3", &["1.2.3", "1.9.0"], &["1.2.2", "2.0.0"]);
+        check("^0.2.3", &["0.2.3", "0.2.9"], &["0.3.0", "0.2.2"]);
+        check("^0.0.3", &["0.0.3"], &["0.0.4", "0.0.2"]);
+        check("^1.2", &["1.2.0", "1.9.9"], &["1.1.9", "2.0.0"]);
+        check("^0.0", &["0.0.0", "0.0.9"], &["0.1.0"]);
+        check("^1", &["1.0.0", "1.99.0"], &["2.0.0"]);
+        check("^1.2.x", &["1.2.0", "1.5.0"], &["2.0.0"]);
+    }
+
+    #[test]
+    fn tilde() {
+        check("~1.2.3", &["1.2.3", "1.2.9"], &["1.3.0", "1.2.2"]);
+        check("~1.2", &["1.2.0", "1.2.9"], &["1.3.0"]);
+        check("~1", &["1.0.0", "1.9.0"], &["2.0.0"]);
+        check("~0.2.3", &["0.2.5"], &["0.3.0"]);
+        check("~>1.2.3", &["1.2.4"], &["1.3.0"]);
+    }
+
+    #[test]
+    fn x_ranges() {
+        check("*", &["0.0.0", "9.9.9"], &[]);
+        check("", &["1.0.0"], &[]);
+        check("1.x", &["1.0.0", "1.9.9"], &["2.0.0", "0.9.9"]);
+        check("1.2.*", &["1.2.0", "1.2.9"], &["1.3.0"]);
+        check("1.X", &["1.5.0"], &["2.0.0"]);
+        check("1", &["1.0.0", "1.5.0"], &["2.0.0"]);
+        check("1.2", &["1.2.5"], &["1.3.0"]);
+        check(">1.2", &["1.3.0"], &["1.2.9"]);
+        check(">1", &["2.0.0"], &["1.9.9"]);
+        check("<1.2", &["1.1.9"], &["1.2.0"]);
+        check("<=1.2", &["1.2.9"], &["1.3.0"]);
+        check(">*", &[], &["1.0.0"]);
+        check("<*", &[], &["0.0.0"]);
+    }
+
+    #[test]
+    fn hyphen_ranges() {
+        check("1.2.3 - 2.3.4", &["1.2.3", "2.3.4"], &["1.2.2", "2.3.5"]);
+        check("1.2 - 2.3.4", &["1.2.0"], &["1.1.9"]);
+        check("1.2.3 - 2.3", &["2.3.9"], &["2.4.0"]);
+        check("1.2.3 - 2", &["2.9.9"], &["3.0.0"]);
+    }
+
+    #[test]
+    fn or_sets() {
+        check("^1.2.0 || ^2.0.0", &["1.5.0", "2.1.0"], &["3.0.0", "1.1.0"]);
+        check("1.2.7 || >=1.2.9 <2.0.0", &["1.2.7", "1.2.9", "1.4.6"], &["1.2.8", "2.0.0"]);
+        check("<1.0.0 || >=3", &["0.9.0", "3.0.0"], &["2.0.0"]);
+    }
+
+    #[test]
+    fn prereleases() {
+        check(">=1.2.3-alpha.3", &["1.2.3-alpha.7", "1.2.3", "3.4.5"], &["1.2.3-alpha.2", "3.4.5-alpha.9"]);
+        check("^5.0.0", &["5.1.0"], &["5.1.0-rc.1", "6.0.0-rc.1"]);
+        check("^1.2.3-beta.2", &["1.2.3-beta.4", "1.2.4"], &["1.2.4-beta.2", "1.2.3-beta.1"]);
+        check("~1.2.3-beta.2", &["1.2.3-beta.4"], &["1.2.4-beta.2"]);
+        check("<2.0.0", &["1.9.9"], &["2.0.0-rc.1"]);
+        let range = Range::parse("^5.0.0").unwrap();
+        assert!(range.matches_semver(&parse_semver("5.1.0-rc.1").unwrap(), true));
+        assert!(!range.matches_semver(&parse_semver("6.0.0-rc.1").unwrap(), true));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(Range::parse("latest").is_none());
+        assert!(Range::parse("^a.b.c").is_none());
+        assert!(Range::parse("~> 5.0").is_some());
+        assert!(Range::parse("%1.0.0").is_none());
+    }
+
+    #[test]
+    fn min_version() {
+        let min = |r: &str| Range::parse(r).and_then(|r| r.min_version()).map(|v| v.release);
+        assert_eq!(min("^1.2.0"), Some((1, 2, 0)));
+        assert_eq!(min(">1.2.x"), Some((1, 3, 0)));
+        assert_eq!(min(">1.2.3"), Some((1, 2, 4)));
+        assert_eq!(min("*"), Some((0, 0, 0)));
+        assert_eq!(min("^2.0.0 || ~1.4.0"), Some((1, 4, 0)));
+        assert_eq!(min("1.2.3 - 2.0.0"), Some((1, 2, 3)));
+        assert_eq!(min(">2.0.0 <1.0.0"), None);
+        assert!(Range::parse(">1.0.0-rc.1").and_then(|r| r.min_version()).is_some_and(|v| v.is_prerelease()));
+    }
+
+    #[test]
+    fn intersects() {
+        let both = |a: &str, b: &str| Range::parse(a).unwrap().intersects(&Range::parse(b).unwrap());
+        assert!(both("^1.2.0", ">=1.4.0 <1.4.3"));
+        assert!(both("1.x", "1.2.3"));
+        assert!(!both("^1.2.0", "^2.0.0"));
+        assert!(!both("<1.0.0", ">=1.0.0"));
+        assert!(both("<1.0.0 || >=3", "3.1.0"));
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::Value;
+
+/// Maps advisory scores to severity labels for the report. Ranges are checked
+/// in order, per advisory overrides win over the score.
+pub struct SeverityMap {
+    ranges: Vec<(f32, f32, String)>,
+    overrides: HashMap<String, String>,
+}
+
+impl Default for SeverityMap {
+    /// The CVSS v3 qualitative rating scale.
+    fn default() -> Self {
+        let ranges = [(0.1, 3.9, "low"), (4.0, 6.9, "medium"), (7.0, 8.9, "high"), (9.0, 10.0, "critical")];
+        SeverityMap {
+            ranges: ranges.iter().map(|(lo, hi, l)| (*lo, *hi, l.to_string())).collect(),
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+impl SeverityMap {
+    /// Read a mapping file with `<min>-<max> <label>` lines for score ranges
+    /// and `<advisory ID> <label>` lines for overrides. Blank lines and lines
+    /// starting with `#` are skipped.
+    ///
+    /// ```text
+    /// 9.0-10.0 P1
+    /// 7.0-8.9  P2
+    /// 0.0-6.9  P3
+    /// GHSA-xxxx-xxxx-xxxx P1 # actively exploited
+    /// ```
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let mut map = SeverityMap {
+            ranges: Vec::new(),
+            overrides: HashMap::new(),
+        };
+        for line in content.lines() {
+            let l = line.split(" #").next().unwrap_or("").trim();
+            if l.is_empty() || l.starts_with('#') {
+                continue;
+            }
+            let Some((key, label)) = l.split_once(char::is_whitespace) else {
+                eprintln!("[warning] Invalid line in {}: {}", path.display(), l);
+                continue;
+            };
+            let label = label.trim().to_string();
+            let range = key
+                .split_once('-')
+                .and_then(|(lo, hi)| Some((lo.parse::<f32>().ok()?, hi.parse::<f32>().ok()?)));
+            match range {
+                Some((lo, hi)) => map.ranges.push((lo, hi, label)),
+                None => {
+                    map.overrides.insert(key.to_string(), label);
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    /// Label for an entry listed for `reasons` with the given score. A reason
+    /// matches an override when it is the advisory ID or a URL ending in it.
+    pub fn label<'a>(&self, reasons: impl Iterator<Item = &'a str>, score: Option<f32>) -> Option<String> {
+        for reason in reasons {
+            let id = reason.rsplit('/').next().unwrap_or(reason);
+            if let Some(label) = self.overrides.get(id) {
+                return Some(label.clone());
+            }
+        }
+        let score = score?;
+        self.ranges
+            .iter()
+            .find(|(lo, hi, _)| score >= *lo && score <= *hi)
+            .map(|(_, _, label)| label.clone())
+    }
+}
+
+/// Score of an OSV advisory from its CVSS v3 vector, falling back to the
+/// qualitative `database_specific.severity` of GitHub sourced records.
+pub fn osv_score(vuln: &Value) -> Option<f32> {
+    let vectors = vuln.get("severity").and_then(|s| s.as_array()).into_iter().flatten();
+    for severity in vectors {
+        if severity.get("type").and_then(|t| t.as_str()) == Some("CVSS_V3")
+            && let Some(score) = severity.get("score").and_then(|s| s.as_str()).and_then(cvss_v3_score)
+        {
+            return Some(score);
+        }
+    }
+    vuln.get("database_specific")
+        .and_then(|d| d.get("severity"))
+        .and_then(|s| s.as_str())
+        .and_then(qualitative_score)
+}
+
+/// Score of a GitHub REST advisory from `cvss_severities`, `cvss` or the
+/// qualitative `severity`.
+pub fn ghsa_score(advisory: &Value) -> Option<f32> {
+    let numeric = |v: Option<&Value>| v.and_then(|s| s.as_f64()).filter(|s| *s > 0.0).map(|s| s as f32);
+    numeric(advisory.pointer("/cvss_severities/cvss_v3/score"))
+        .or_else(|| numeric(advisory.pointer("/cvss/score")))
+        .or_else(|| advisory.get("severity").and_then(|s| s.as_str()).and_then(qualitative_score))
+}
+
+/// Lowest score of a qualitative rating, so ranges put it in the same bucket.
+fn qualitative_score(rating: &str) -> Option<f32> {
+    match rating.to_ascii_lowercase().as_str() {
+        "critical" => Some(9.0),
+        "high" => Some(7.0),
+        "moderate" | "medium" => Some(4.0),
+        "low" => Some(0.1),
+        _ => None,
+    }
+}
+
+/// Base score of a `CVSS:3.x/AV:N/AC:L/…` vector as defined by the CVSS v3.1
+/// specification.
+pub fn cvss_v3_score(vector: &str) -> Option<f32> {
+    let mut metrics: HashMap<&str, &str> = HashMap::new();
+    let mut parts = vector.split('/');
+    if !parts.next()?.starts_with("CVSS:3") {
+        return None;
+    }
+    for part in parts {
+        let (k, v) = part.split_once(':')?;
+        metrics.insert(k, v);
+    }
+    let changed = *metrics.get("S")? == "C";
+    let av = match *metrics.get("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return None,
+    };
+    let ac = match *metrics.get("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return None,
+    };
+    let pr = match (*metrics.get("PR")?, changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let ui = match *metrics.get("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return None,
+    };
+    let cia = |k: &str| match metrics.get(k).copied() {
+        Some("H") => Some(0.56),
+        Some("L") => Some(0.22),
+        Some("N") => Some(0.0),
+        _ => None,
+    };
+    let iss: f64 = 1.0 - (1.0 - cia("C")?) * (1.0 - cia("I")?) * (1.0 - cia("A")?);
+    let impact = if changed {
+        7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15)
+    } else {
+        6.42 * iss
+    };
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let exploitability = 8.22 * av * ac * pr * ui;
+    let base = if changed {
+        (1.08 * (impact + exploitability)).min(10.0)
+    } else {
+        (impact + exploitability).min(10.0)
+    };
+    Some(round_up(base) as f32)
+}
+
+/// CVSS v3.1 Roundup: the smallest one-decimal number not below `value`.
+fn round_up(value: f64) -> f64 {
+    let int_input = (value * 100_000.0).round() as i64;
+    if int_input % 10_000 == 0 {
+        int_input as f64 / 100_000.0
+    } else {
+        ((int_input / 10_000) + 1) as f64 / 10.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn scores_known_vectors() {
+        // Base scores as published by NVD
+        let known = [
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H", 9.8),
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:C/C:H/I:H/A:H", 10.0),
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N", 6.1),
+            ("CVSS:3.1/AV:L/AC:L/PR:L/UI:N/S:U/C:H/I:H/A:H", 7.8),
+            ("CVSS:3.1/AV:N/AC:H/PR:N/UI:N/S:U/C:H/I:N/A:N", 5.9),
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:H", 7.5),
+            ("CVSS:3.0/AV:N/AC:L/PR:L/UI:N/S:C/C:L/I:L/A:N", 6.4),
+            ("CVSS:3.1/AV:N/AC:L/PR:H/UI:N/S:C/C:H/I:H/A:H", 9.1),
+            ("CVSS:3.1/AV:A/AC:L/PR:N/UI:N/S:U/C:L/I:N/A:N", 4.3),
+            ("CVSS:3.1/AV:P/AC:H/PR:H/UI:R/S:U/C:L/I:N/A:N", 1.6),
+            ("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N", 0.0),
+        ];
+        for (vector, score) in known {
+            assert_eq!(cvss_v3_score(vector), Some(score), "{}", vector);
+        }
+    }
+
+    #[test]
+    fn metric_order_does_not_matter() {
+        assert_eq!(cvss_v3_score("CVSS:3.1/C:H/I:H/A:H/AV:N/AC:L/PR:N/UI:N/S:U"), Some(9.8));
+    }
+
+    #[test]
+    fn rejects_invalid_vectors() {
+        assert_eq!(cvss_v3_score("CVSS:2.0/AV:N/AC:L/Au:N/C:P/I:P/A:P"), None);
+        assert_eq!(cvss_v3_score("AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"), None);
+        assert_eq!(cvss_v3_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H"), None);
+        assert_eq!(cvss_v3_score("CVSS:3.1/AV:X/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"), None);
+        assert_eq!(cvss_v3_score("CVSS:3.1/AV"), None);
+    }
+
+    #[test]
+    fn rounds_up() {
+        assert_eq!(round_up(4.0), 4.0);
+        assert_eq!(round_up(4.02), 4.1);
+        assert_eq!(round_up(4.000_000_1), 4.0);
+    }
+
+    #[test]
+    fn scores_osv_advisories() {
+        let vector = json!({"severity": [{"type": "CVSS_V3", "score": "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"}]});
+        assert_eq!(osv_score(&vector), Some(9.8));
+        let qualitative = json!({"database_specific": {"severity": "MODERATE"}});
+        assert_eq!(osv_score(&qualitative), Some(4.0));
+        assert_eq!(osv_score(&json!({})), None);
+    }
+
+    #[test]
+    fn labels_scores() {
+        let map = SeverityMap::default();
+        assert_eq!(map.label(std::iter::empty(), Some(9.8)).as_deref(), Some("critical"));
+        assert_eq!(map.label(std::iter::empty(), Some(5.0)).as_deref(), Some("medium"));
+        assert_eq!(map.label(std::iter::empty(), None), None);
+    }
+}
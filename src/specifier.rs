@@ -0,0 +1,113 @@
+use crate::semver::Range;
+
+/// Dependency specifiers that name a source instead of a registry version,
+/// so there is no version to match against the listed ones.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Git,
+    Url,
+    File,
+}
+
+impl Kind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Git => "git",
+            Kind::Url => "url",
+            Kind::File => "file",
+        }
+    }
+}
+
+/// Prefixes of git specifiers, besides scp-like `git@host:org/repo` and the
+/// `org/repo` GitHub shorthand.
+const GIT_PREFIXES: [&str; 6] = ["git+", "git://", "github:", "gitlab:", "bitbucket:", "gist:"];
+
+/// Prefixes of local specifiers: npm's `file:`, yarn and pnpm's `link:` and
+/// `portal:`, and plain paths.
+const FILE_PREFIXES: [&str; 7] = ["file:", "link:", "portal:", "./", "../", "/", "~/"];
+
+/// What a package.json spec installs from when it is not a registry range:
+/// a git repository, a tarball URL or a local path. None for ranges, tags
+/// and protocols such as `npm:` and `workspace:`.
+pub fn kind(spec: &str) -> Option<Kind> {
+    let spec = spec.trim();
+    let location = spec.split('#').next().unwrap_or(spec);
+    if GIT_PREFIXES.iter().any(|p| spec.starts_with(p)) || spec.starts_with("git@") || is_github_shorthand(location) {
+        Some(Kind::Git)
+    } else if spec.starts_with("http://") || spec.starts_with("https://") {
+        Some(if location.ends_with(".git") { Kind::Git } else { Kind::Url })
+    } else if FILE_PREFIXES.iter().any(|p| spec.starts_with(p)) {
+        Some(Kind::File)
+    } else {
+        None
+    }
+}
+
+/// A dist-tag such as `latest` or `next`, which installs whatever version the
+/// registry points the tag at. npm reads a spec as a tag when it is not a
+/// range, so `1.x` or `*` never is one.
+pub fn is_dist_tag(spec: &str) -> bool {
+    let spec = spec.trim();
+    !spec.is_empty()
+        && spec.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        && kind(spec).is_none()
+        && Range::parse(spec).is_none()
+}
+
+/// `org/repo`, which npm fetches from GitHub. Ranges never contain a `/`.
+fn is_github_shorthand(location: &str) -> bool {
+    let mut parts = location.split('/');
+    let valid = |part: Option<&str>| {
+        part.is_some_and(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)))
+    };
+    !location.starts_with('.') && valid(parts.next()) && valid(parts.next()) && parts.next().is_none()
+}
+
+/// The URL git fetches a git specifier from and the commit-ish of its
+/// `#` fragment, if any. Fragments are `::`-separated; a `#semver:` range
+/// needs the tags of the repository to resolve and gives None, as do
+/// specifiers that are not git.
+pub fn git_source(spec: &str) -> Option<(String, Option<String>)> {
+    let spec = spec.trim();
+    let (location, fragment) = match spec.split_once('#') {
+        Some((location, fragment)) => (location, Some(fragment)),
+        None => (spec, None),
+    };
+    let mut rev: Option<String> = None;
+    for part in fragment.into_iter().flat_map(|f| f.split("::")).filter(|p| !p.is_empty()) {
+        if part.starts_with("semver:") {
+            return None;
+        }
+        // `path:` selects a workspace of the repository, not a commit
+        if !part.starts_with("path:") {
+            rev = Some(part.to_string());
+        }
+    }
+    let hosted = |host: &str, path: &str| format!("https://{}/{}.git", host, path.trim_end_matches(".git"));
+    let url = if let Some(rest) = location.strip_prefix("git+") {
+        // npm accepts `git+ssh://git@host:org/repo`, which git only takes
+        // written scp-like
+        match rest.strip_prefix("ssh://") {
+            Some(target) if target.split('/').next().and_then(|h| h.split_once(':')).is_some_and(|(_, port)| port.parse::<u16>().is_err()) => {
+                target.to_string()
+            }
+            _ => rest.to_string(),
+        }
+    } else if let Some(path) = location.strip_prefix("github:") {
+        hosted("github.com", path)
+    } else if let Some(path) = location.strip_prefix("gitlab:") {
+        hosted("gitlab.com", path)
+    } else if let Some(path) = location.strip_prefix("bitbucket:") {
+        hosted("bitbucket.org", path)
+    } else if let Some(id) = location.strip_prefix("gist:") {
+        format!("https://gist.github.com/{}.git", id.rsplit('/').next().unwrap_or(id))
+    } else if is_github_shorthand(location) {
+        hosted("github.com", location)
+    } else if kind(spec) == Some(Kind::Git) {
+        location.to_string()
+    } else {
+        return None;
+    };
+    Some((url, rev))
+}
@@ -0,0 +1,62 @@
+use std::io;
+use std::path::Path;
+
+/// Report of previously flagged findings no list entry covers anymore.
+pub const FILE_NAME: &str = "output.stale.csv";
+
+/// A flagged row of a previous `output.csv`.
+pub struct Finding {
+    pub package: String,
+    pub version: String,
+    pub location: String,
+    pub project: String,
+    pub source: String,
+    pub reason: String,
+    /// Why the finding was suppressed, empty when it was reported
+    pub allowed: String,
+}
+
+/// The rows of a previous report that matched a list entry, suppressed ones
+/// included.
+pub fn previous_findings(path: &Path) -> io::Result<Vec<Finding>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let required = |name: &str| {
+        column(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} has no {} column", path.display(), name)))
+    };
+    let (package, version, location) = (required("package")?, required("version")?, required("location")?);
+    let (match_package, match_version) = (required("match_package")?, required("match_version")?);
+    let mut findings: Vec<Finding> = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |i: Option<usize>| i.and_then(|i| record.get(i)).unwrap_or("").to_string();
+        if field(Some(match_package)) != "true" || field(Some(match_version)) != "true" {
+            continue;
+        }
+        findings.push(Finding {
+            package: field(Some(package)),
+            version: field(Some(version)),
+            location: field(Some(location)),
+            project: field(column("project")),
+            source: field(column("source")),
+            reason: field(column("reason")),
+            allowed: field(column("allowed")),
+        });
+    }
+    Ok(findings)
+}
+
+/// Write the stale findings, with `suppression` in the `kind` column for
+/// findings an allow entry suppressed and `finding` for reported ones.
+pub fn write(path: &Path, findings: &[&Finding]) -> io::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["package", "version", "location", "project", "kind", "previous_source", "previous_reason", "allowed"])?;
+    for f in findings {
+        let kind = if f.allowed.is_empty() { "finding" } else { "suppression" };
+        writer.write_record([&f.package, &f.version, &f.location, &f.project, kind, &f.source, &f.reason, &f.allowed])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use serde_json::Value;
+
+/// Files of a packed package that are scanned, in the order they are
+/// preferred: npm publishes npm-shrinkwrap.json but never package-lock.json.
+pub const FILES: [&str; 5] = ["package.json", "npm-shrinkwrap.json", "package-lock.json", "yarn.lock", "pnpm-lock.yaml"];
+
+/// Largest file read from a package or archive, bigger ones are skipped so a
+/// crafted archive cannot exhaust memory.
+pub const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Folders of a project that `npm pack --pack-destination` and release
+/// tooling commonly write tarballs to, besides the project itself.
+const ARTIFACT_DIRS: [&str; 5] = ["dist", "artifacts", "release", "releases", "out"];
+
+/// The contents of a packed npm package, as `npm pack` writes it: everything
+/// under one top-level folder, usually `package/`.
+pub struct Tarball {
+    /// `FILES` found at the top of the package, as (file name, content)
+    pub files: Vec<(String, String)>,
+    /// Versions of the bundled dependencies under its node_modules
+    pub bundled: HashMap<String, HashSet<String>>,
+}
+
+/// Read a `.tgz` in memory.
+pub fn read(path: &Path) -> io::Result<Tarball> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+    let mut tarball = Tarball {
+        files: Vec::new(),
+        bundled: HashMap::new(),
+    };
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let entry_path = entry.path()?.into_owned();
+        // Strip the top-level folder, whatever it is called
+        let Ok(relative) = entry_path.strip_prefix(entry_path.iter().next().unwrap_or_default()) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let is_top_file = FILES.contains(&relative.as_str());
+        let is_bundled = relative.starts_with("node_modules/") && relative.ends_with("/package.json");
+        if !is_top_file && !is_bundled {
+            continue;
+        }
+        let Some(content) = read_limited(&mut entry)? else {
+            eprintln!("[warning] Skipping {} in {}, it is larger than {} bytes", relative, path.display(), MAX_FILE_SIZE);
+            continue;
+        };
+        if is_top_file {
+            tarball.files.push((relative, content));
+        } else if let Some((name, version)) = bundled_package(&relative, &content) {
+            tarball.bundled.entry(name).or_default().insert(version);
+        }
+    }
+    tarball.files.sort_by_key(|(file, _)| FILES.iter().position(|f| f == file));
+    Ok(tarball)
+}
+
+/// Read a file of a package or archive as text, None when it is larger than
+/// `MAX_FILE_SIZE`.
+pub fn read_limited(reader: impl Read) -> io::Result<Option<String>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    reader.take(MAX_FILE_SIZE + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_FILE_SIZE {
+        return Ok(None);
+    }
+    String::from_utf8(bytes).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Name and version of a `node_modules/<name>/package.json` (also scoped and
+/// nested), skipping other package.json files such as those of fixtures.
+pub fn bundled_package(relative: &str, content: &str) -> Option<(String, String)> {
+    let folder = relative.strip_suffix("/package.json")?;
+    let (_, installed_as) = folder.rsplit_once("node_modules/")?;
+    let manifest: Value = serde_json::from_str(content).ok()?;
+    let name = manifest.get("name")?.as_str()?;
+    let version = manifest.get("version")?.as_str()?;
+    (installed_as == name).then(|| (name.to_string(), version.to_string()))
+}
+
+/// The `.tgz` files in a project directory and its artifact folders.
+pub fn discover(dir: &Path) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = std::iter::once(dir.to_path_buf())
+        .chain(ARTIFACT_DIRS.iter().map(|a| dir.join(a)))
+        .flat_map(|folder| fs::read_dir(folder).into_iter().flatten().filter_map(Result::ok))
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "tgz") && p.is_file())
+        .collect();
+    found.sort();
+    found
+}
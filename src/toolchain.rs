@@ -0,0 +1,48 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const TOOLS: [&str; 6] = ["node", "npm", "yarn", "pnpm", "bun", "curl"];
+
+/// Versions of the JavaScript toolchain (and curl, used for downloads) found
+/// on PATH, None for tools that are missing.
+pub fn detect() -> BTreeMap<String, Option<String>> {
+    TOOLS.iter().map(|tool| (tool.to_string(), tool_version(tool))).collect()
+}
+
+fn tool_version(tool: &str) -> Option<String> {
+    let output = Command::new(tool).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // curl prints a multi-line banner, the version is the second word
+    let first_line = String::from_utf8_lossy(&output.stdout).lines().next()?.trim().to_string();
+    match tool {
+        "curl" => first_line.split_whitespace().nth(1).map(|v| v.to_string()),
+        _ => Some(first_line),
+    }
+}
+
+/// Tool versions pinned by a project in `.nvmrc` and `.tool-versions`.
+pub fn project_versions(dir: &Path) -> BTreeMap<String, String> {
+    let mut versions: BTreeMap<String, String> = BTreeMap::new();
+    if let Ok(content) = fs::read_to_string(dir.join(".nvmrc")) {
+        let v = content.trim();
+        if !v.is_empty() {
+            versions.insert("node".to_string(), v.to_string());
+        }
+    }
+    if let Ok(content) = fs::read_to_string(dir.join(".tool-versions")) {
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let mut parts = line.split_whitespace();
+            if let (Some(tool), Some(version)) = (parts.next(), parts.next()) {
+                // asdf calls node "nodejs"
+                let tool = if tool == "nodejs" { "node" } else { tool };
+                versions.insert(tool.to_string(), version.to_string());
+            }
+        }
+    }
+    versions
+}
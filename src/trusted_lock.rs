@@ -0,0 +1,96 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::git;
+use crate::match_type::MatchType;
+use crate::{Checks, Finding, Preload, Row, file_preload, lockfile_versions, present_lockfiles, project_names};
+
+/// A package whose resolution differs from the trusted lockfile.
+struct LockDrift {
+    lockfile: &'static str,
+    name: String,
+    trusted: Vec<String>,
+    current: Vec<String>,
+}
+
+/// Compare every package resolved by the lockfiles of a directory with the
+/// trusted copies read through `spec`: a lockfile path, which is compared
+/// with the lockfile of the same name in start paths only, or a git ref.
+/// Returns the lockfiles the trusted ref does not have, whose packages all
+/// count as changed, and the changed packages.
+fn drift(spec: &str, dir: &str, preload: &Preload, is_start_path: bool) -> io::Result<(Vec<&'static str>, Vec<LockDrift>)> {
+    let path = Path::new(spec);
+    let mut absent: Vec<&'static str> = Vec::new();
+    let mut drift: Vec<LockDrift> = Vec::new();
+    for lockfile in present_lockfiles(preload) {
+        let content = if path.is_file() {
+            if !is_start_path || path.file_name() != Some(OsStr::new(lockfile)) {
+                continue;
+            }
+            Some(fs::read_to_string(path)?)
+        } else {
+            git::read_at_rev(spec, Path::new(dir), lockfile)?
+        };
+        let trusted = match content {
+            Some(content) => file_preload(lockfile, &content),
+            None => {
+                absent.push(lockfile);
+                file_preload("package.json", "")
+            }
+        };
+        let mut names: Vec<String> = project_names(&trusted).union(&project_names(preload)).cloned().collect();
+        names.sort();
+        for name in names {
+            let versions = |p: &Preload| {
+                let mut v: Vec<String> = lockfile_versions(&name, p).remove(lockfile).unwrap_or_default().into_iter().collect();
+                v.sort();
+                v
+            };
+            let (trusted_versions, current_versions) = (versions(&trusted), versions(preload));
+            if trusted_versions != current_versions {
+                drift.push(LockDrift {
+                    lockfile,
+                    name,
+                    trusted: trusted_versions,
+                    current: current_versions,
+                });
+            }
+        }
+    }
+    Ok((absent, drift))
+}
+
+/// Compare the lockfiles of the project in `dir` with their trusted copies.
+/// Returns the report lines and a row for every changed package.
+pub fn check(
+    spec: &str,
+    dir: &str,
+    project: &str,
+    preload: &Preload,
+    is_start_path: bool,
+    checks: Checks,
+) -> io::Result<(Vec<String>, Vec<Row>)> {
+    let (absent, drifts) = drift(spec, dir, preload, is_start_path)?;
+    let mut lines: Vec<String> = absent
+        .into_iter()
+        .map(|lockfile| format!("{}: {} is not in {}, every package it resolves is new", dir, lockfile, spec))
+        .collect();
+    let mut rows: Vec<Row> = Vec::new();
+    for drift in drifts {
+        let trusted = if drift.trusted.is_empty() { "nothing".to_string() } else { drift.trusted.join(", ") };
+        let current = if drift.current.is_empty() { "nothing".to_string() } else { drift.current.join(", ") };
+        lines.push(format!("{}: {} in {} was {}, now {}", dir, drift.name, drift.lockfile, trusted, current));
+        rows.push(checks.finding_row(Finding {
+            name: &drift.name,
+            versions: &drift.current,
+            location: dir,
+            project,
+            check: "trusted-lock",
+            reason: format!("{} pins {} in the trusted lockfile", drift.lockfile, trusted),
+            match_type: MatchType::Locked,
+        }));
+    }
+    Ok((lines, rows))
+}
@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobMatcher};
+use ignore::WalkBuilder;
+use serde_json::Value;
+
+use crate::{git, workspaces};
+
+/// Name turbo gives the workspace root in filters.
+const ROOT_NAME: &str = "//";
+
+/// A package of a Turborepo: the workspace root or one of its members.
+pub struct Package {
+    pub name: String,
+    pub root: PathBuf,
+    /// Names of the packages it depends on
+    dependencies: Vec<String>,
+}
+
+/// Whether `dir` is the root of a Turborepo.
+pub fn is_repo(dir: &Path) -> bool {
+    dir.join("turbo.json").is_file() || dir.join("turbo.jsonc").is_file()
+}
+
+/// The workspace root and members of the Turborepo at `root`, as the
+/// package manager's workspaces define them.
+pub fn packages(root: &Path) -> Vec<Package> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let walker = WalkBuilder::new(&root)
+        .hidden(false)
+        .filter_entry(|e| e.file_name() != "node_modules")
+        .build();
+    let mut packages: Vec<Package> = Vec::new();
+    for entry in walker.filter_map(Result::ok) {
+        let (Some(dir), Some("package.json")) = (entry.path().parent(), entry.file_name().to_str()) else {
+            continue;
+        };
+        let is_member = dir == root || workspaces::root(dir).or_else(|| workspaces::pnpm_root(dir)).as_deref() == Some(root.as_path());
+        let Some(manifest) = is_member
+            .then(|| File::open(entry.path()).ok())
+            .flatten()
+            .and_then(|f| serde_json::from_reader::<_, Value>(f).ok())
+        else {
+            continue;
+        };
+        let dependencies = ["dependencies", "devDependencies", "optionalDependencies", "peerDependencies"]
+            .iter()
+            .filter_map(|s| manifest.get(s).and_then(|d| d.as_object()))
+            .flat_map(|d| d.keys().cloned())
+            .collect();
+        let name = match manifest.get("name").and_then(|n| n.as_str()) {
+            _ if dir == root => ROOT_NAME.to_string(),
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        packages.push(Package {
+            name,
+            root: dir.to_path_buf(),
+            dependencies,
+        });
+    }
+    packages.sort_by(|a, b| a.root.cmp(&b.root));
+    packages
+}
+
+/// One `--filter` expression: a package name glob, a `./dir` or `{dir}`
+/// glob and a `[ref]` of changes since that ref, optionally preceded by
+/// `...` (dependents) and followed by `...` (dependencies), where `^`
+/// leaves the matched packages themselves out.
+struct Selector {
+    exclude: bool,
+    name: Option<GlobMatcher>,
+    directory: Option<GlobMatcher>,
+    changed_since: Option<String>,
+    dependents: bool,
+    dependencies: bool,
+    exclude_self: bool,
+}
+
+fn glob(pattern: &str) -> io::Result<GlobMatcher> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|g| g.compile_matcher())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+fn parse(filter: &str) -> io::Result<Selector> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid turbo filter '{}'", filter));
+    let (exclude, mut rest) = match filter.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, filter),
+    };
+    let mut exclude_self = false;
+    let dependents = match rest.strip_prefix("...") {
+        Some(r) => {
+            rest = r.strip_prefix('^').inspect(|_| exclude_self = true).unwrap_or(r);
+            true
+        }
+        None => false,
+    };
+    let dependencies = match rest.strip_suffix("...") {
+        Some(r) => {
+            rest = r.strip_suffix('^').inspect(|_| exclude_self = true).unwrap_or(r);
+            true
+        }
+        None => false,
+    };
+    let mut changed_since = None;
+    if let Some(start) = rest.find('[') {
+        let end = rest.rfind(']').filter(|e| *e > start).ok_or_else(invalid)?;
+        changed_since = Some(rest[start + 1..end].to_string());
+        rest = &rest[..start];
+    }
+    let mut directory = None;
+    if let Some(start) = rest.find('{') {
+        let end = rest.rfind('}').filter(|e| *e > start).ok_or_else(invalid)?;
+        directory = Some(&rest[start + 1..end]);
+        rest = &rest[..start];
+    } else if rest.starts_with('.') {
+        directory = Some(rest);
+        rest = "";
+    }
+    let directory = directory.map(|d| glob(d.trim_start_matches("./").trim_end_matches('/'))).transpose()?;
+    let name = (!rest.is_empty()).then(|| glob(rest)).transpose()?;
+    if name.is_none() && directory.is_none() && changed_since.is_none() {
+        return Err(invalid());
+    }
+    Ok(Selector {
+        exclude,
+        name,
+        directory,
+        changed_since,
+        dependents,
+        dependencies,
+        exclude_self,
+    })
+}
+
+/// The packages of the Turborepo at `root` selected by `filters`, the way
+/// `turbo run --filter` selects them: the union of the filters, less the
+/// ones starting with `!`.
+pub fn select<'a>(root: &Path, packages: &'a [Package], filters: &[String]) -> io::Result<Vec<&'a Package>> {
+    let root = root.canonicalize()?;
+    let selectors = filters.iter().map(|f| parse(f)).collect::<io::Result<Vec<Selector>>>()?;
+    let mut included: HashSet<usize> = HashSet::new();
+    let mut excluded: HashSet<usize> = HashSet::new();
+    for selector in &selectors {
+        let changed: Option<HashSet<usize>> = match &selector.changed_since {
+            Some(base) => Some(git::changed_files(&root, base)?.iter().filter_map(|f| owner(packages, f)).collect()),
+            None => None,
+        };
+        let matched: HashSet<usize> = packages
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| selector.name.as_ref().is_none_or(|g| g.is_match(&p.name)))
+            .filter(|(_, p)| {
+                selector.directory.as_ref().is_none_or(|g| {
+                    let rel = p.root.strip_prefix(&root).unwrap_or(&p.root);
+                    g.is_match(if rel.as_os_str().is_empty() { Path::new(".") } else { rel })
+                })
+            })
+            .filter(|(i, _)| changed.as_ref().is_none_or(|c| c.contains(i)))
+            .map(|(i, _)| i)
+            .collect();
+        let mut selected: HashSet<usize> = if selector.exclude_self { HashSet::new() } else { matched.clone() };
+        if selector.dependencies {
+            selected.extend(walk(packages, &matched, |from, to| from.dependencies.contains(&to.name)));
+        }
+        if selector.dependents {
+            selected.extend(walk(packages, &matched, |from, to| to.dependencies.contains(&from.name)));
+        }
+        if selector.exclude {
+            excluded.extend(selected);
+        } else {
+            included.extend(selected);
+        }
+    }
+    // Only exclusions select everything else
+    if selectors.iter().all(|s| s.exclude) {
+        included = (0..packages.len()).collect();
+    }
+    let mut selected: Vec<&Package> = included.difference(&excluded).map(|i| &packages[*i]).collect();
+    selected.sort_by(|a, b| a.root.cmp(&b.root));
+    Ok(selected)
+}
+
+/// The deepest package containing `file`, which turbo assigns changes to.
+fn owner(packages: &[Package], file: &Path) -> Option<usize> {
+    packages
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| file.starts_with(&p.root))
+        .max_by_key(|(_, p)| p.root.components().count())
+        .map(|(i, _)| i)
+}
+
+/// Packages reached from `start` by following `edge(from, to)`, without
+/// `start` itself unless it is reached again.
+fn walk(packages: &[Package], start: &HashSet<usize>, edge: impl Fn(&Package, &Package) -> bool) -> HashSet<usize> {
+    let mut reached: HashSet<usize> = HashSet::new();
+    let mut queue: Vec<usize> = start.iter().copied().collect();
+    while let Some(from) = queue.pop() {
+        for (to, package) in packages.iter().enumerate() {
+            if edge(&packages[from], package) && reached.insert(to) {
+                queue.push(to);
+            }
+        }
+    }
+    reached
+}
@@ -0,0 +1,135 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashSet};
+
+use crate::match_type::MatchType;
+use crate::matcher::{Namespace, split_namespace};
+use crate::semver::parse_semver;
+use crate::{Checks, Finding, Preload, Row, get_pkg_range};
+
+/// Popular npm packages shipped with the binary.
+const BUNDLED: &str = include_str!("popular_packages.txt");
+
+/// Shortest name checked for a single edit, below it too many legitimate
+/// packages are one letter apart.
+const MIN_EDIT_LENGTH: usize = 5;
+
+/// Prefixes and suffixes added to a popular name to make it look official.
+const AFFIXES: [&str; 5] = ["js", "-js", ".js", "node-", "-node"];
+
+/// Look-alike character sequences, rewritten to the letters they imitate.
+const HOMOGLYPHS: [(&str, &str); 6] = [("0", "o"), ("1", "l"), ("3", "e"), ("5", "s"), ("rn", "m"), ("vv", "w")];
+
+/// The bundled popular package names.
+pub fn popular() -> BTreeSet<&'static str> {
+    BUNDLED.lines().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with('#')).collect()
+}
+
+/// The popular package `name` imitates and how, or None. Popular packages
+/// themselves are never reported.
+pub fn imitated(name: &str, popular: &BTreeSet<&'static str>) -> Option<(&'static str, &'static str)> {
+    if popular.contains(name) {
+        return None;
+    }
+    popular.iter().find_map(|target| Some((*target, technique(name, target)?)))
+}
+
+/// Check the npm packages of the project in `dir`, with their `known`
+/// versions, for names imitating a popular package. Returns the report lines
+/// and a row for each at its highest known version, else its declared range.
+pub fn check(
+    dir: &str,
+    project: &str,
+    preload: &Preload,
+    mut known: Vec<(String, HashSet<String>)>,
+    popular: &BTreeSet<&'static str>,
+    checks: Checks,
+) -> (Vec<String>, Vec<Row>) {
+    known.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut lines: Vec<String> = Vec::new();
+    let mut rows: Vec<Row> = Vec::new();
+    for (name, versions) in known {
+        if split_namespace(&name).0 != Namespace::Npm {
+            continue;
+        }
+        let Some((target, technique)) = imitated(&name, popular) else {
+            continue;
+        };
+        let (version, match_type) = match versions.into_iter().max_by_key(|v| parse_semver(v)) {
+            Some(version) => (version, MatchType::Locked),
+            None => (get_pkg_range(&name, preload), MatchType::Declared),
+        };
+        let note = format!("looks like {} ({})", target, technique);
+        lines.push(format!("{}: {}: {}", dir, name, note));
+        rows.push(checks.finding_row(Finding {
+            name: &name,
+            versions: &[version],
+            location: dir,
+            project,
+            check: "typosquat",
+            reason: note,
+            match_type,
+        }));
+    }
+    (lines, rows)
+}
+
+fn technique(name: &str, target: &str) -> Option<&'static str> {
+    if strip_separators(name) == strip_separators(target) {
+        return Some("different separators");
+    }
+    if deglyph(name) == deglyph(target) {
+        return Some("look-alike characters");
+    }
+    let affixed = AFFIXES
+        .iter()
+        .any(|a| name.strip_prefix(a) == Some(target) || name.strip_suffix(a) == Some(target));
+    if affixed {
+        return Some("added prefix or suffix");
+    }
+    if target.len() >= MIN_EDIT_LENGTH && name.len().abs_diff(target.len()) <= 1 && edit_distance(name, target) == 1 {
+        return Some(match name.len().cmp(&target.len()) {
+            Ordering::Greater => "extra character",
+            Ordering::Less => "missing character",
+            Ordering::Equal if sorted_chars(name) == sorted_chars(target) => "swapped characters",
+            Ordering::Equal => "substituted character",
+        });
+    }
+    None
+}
+
+fn sorted_chars(name: &str) -> Vec<char> {
+    let mut chars: Vec<char> = name.chars().collect();
+    chars.sort();
+    chars
+}
+
+fn strip_separators(name: &str) -> String {
+    name.chars().filter(|c| !matches!(c, '-' | '_' | '.')).collect()
+}
+
+fn deglyph(name: &str) -> String {
+    HOMOGLYPHS.iter().fold(name.to_string(), |name, (glyph, letter)| name.replace(glyph, letter))
+}
+
+/// Damerau-Levenshtein distance counting a swap of adjacent characters as
+/// one edit, as in `lodahs` for `lodash`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
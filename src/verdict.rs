@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use serde_json::{Map, Value, json};
+
+use crate::{config, digest};
+
+/// Per-directory verdicts written with `--verdicts`.
+pub const FILE_NAME: &str = "output.verdicts.json";
+
+/// Files of a directory that decide its verdict.
+const INPUT_FILES: [&str; 31] = [
+    "package.json",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "pnpm-workspace.yaml",
+    "bun.lock",
+    "bun.lockb",
+    "deno.json",
+    "deno.jsonc",
+    "deno.lock",
+    "Cargo.toml",
+    "Cargo.lock",
+    "go.mod",
+    "go.sum",
+    "composer.json",
+    "composer.lock",
+    "Gemfile.lock",
+    "gems.locked",
+    "pom.xml",
+    "gradle.lockfile",
+    "buildscript-gradle.lockfile",
+    "dependencies-lock.json",
+    "packages.lock.json",
+    "obj/project.assets.json",
+    "bom.json",
+    "sbom.spdx.json",
+    ".pnp.cjs",
+    ".pnp.data.json",
+    "DEPENDENCIES.json",
+    config::DIR_FILE_NAME,
+];
+
+/// SHA-256 of every input file present in `dir`, keyed by file name.
+pub fn inputs(dir: &Path) -> Map<String, Value> {
+    INPUT_FILES
+        .iter()
+        .filter_map(|f| Some((f.to_string(), json!(digest::sha256_file(&dir.join(f)).ok()?))))
+        .collect()
+}
+
+/// The verdict of a scanned directory: `errors` when an input could not be
+/// read, since its findings may be incomplete, else `findings` or `clean`.
+pub fn scanned(location: &str, project: &str, findings: Vec<&str>, errors: Vec<String>) -> Value {
+    let verdict = if !errors.is_empty() {
+        "errors"
+    } else if !findings.is_empty() {
+        "findings"
+    } else {
+        "clean"
+    };
+    json!({
+        "location": location,
+        "project": project,
+        "verdict": verdict,
+        "findings": findings,
+        "errors": errors,
+        "inputs": inputs(Path::new(location)),
+    })
+}
+
+/// The verdict of a directory that was found but not scanned.
+pub fn skipped(location: &str, project: &str, reason: &str) -> Value {
+    json!({
+        "location": location,
+        "project": project,
+        "verdict": "skipped",
+        "reason": reason,
+        "inputs": inputs(Path::new(location)),
+    })
+}
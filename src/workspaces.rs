@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobSetBuilder};
+use serde_json::Value;
+
+use crate::{package_lock, packages, pnpm, yarn};
+
+/// Sections of a member package.json installed with the workspace.
+const MEMBER_SECTIONS: [&str; 3] = ["dependencies", "devDependencies", "optionalDependencies"];
+
+/// The lockfiles of a workspace root.
+#[derive(Clone, Copy)]
+pub struct Lockfiles<'a> {
+    pub plock: Option<&'a Value>,
+    pub yarn: Option<&'a yarn::Lockfile>,
+    pub pnpm: Option<&'a pnpm::Lockfile>,
+}
+
+/// A workspace member and what it resolves to in the lockfile of its root.
+pub struct Member {
+    pub name: String,
+    /// Packages, as (name, version), the member pulls in through the root
+    /// package-lock.json, yarn.lock or pnpm-lock.yaml, directly or
+    /// transitively
+    pub resolved: HashSet<(String, String)>,
+}
+
+impl Member {
+    /// Resolve the member at `dir` of the workspace at `root`: from its
+    /// workspace entry in a package-lock.json or its importer in a
+    /// pnpm-lock.yaml, or from the ranges its package.json declares in a
+    /// yarn.lock.
+    pub fn new(root: &Path, dir: &Path, manifest: &Value, lockfiles: Lockfiles) -> Self {
+        let path = dir.strip_prefix(root).unwrap_or(dir).to_string_lossy().replace('\\', "/");
+        let mut resolved: HashSet<(String, String)> = HashSet::new();
+        if let Some(plock) = lockfiles.plock {
+            resolved.extend(package_lock::reachable(plock, &path));
+        }
+        if let Some(pnpm) = lockfiles.pnpm {
+            resolved.extend(pnpm.reachable(&path));
+        }
+        if let Some(yarn) = lockfiles.yarn {
+            let declared: Vec<(String, String)> = MEMBER_SECTIONS
+                .iter()
+                .filter_map(|s| manifest.get(s).and_then(|d| d.as_object()))
+                .flatten()
+                .filter_map(|(n, r)| Some((n.clone(), r.as_str()?.to_string())))
+                .collect();
+            resolved.extend(yarn.reachable(&declared));
+        }
+        Member {
+            name: manifest.get("name").and_then(|n| n.as_str()).map_or(path, |n| n.to_string()),
+            resolved,
+        }
+    }
+
+    /// Add the versions of its declared dependencies hoisted to the root
+    /// `node_modules`, as read into `installed`.
+    pub fn add_hoisted(&mut self, manifest: &Value, installed: &HashMap<String, HashSet<String>>) {
+        // node_modules holds aliases under their own name, installed holds
+        // the package they point at
+        let declared = MEMBER_SECTIONS
+            .iter()
+            .filter_map(|s| manifest.get(s).and_then(|d| d.as_object()))
+            .flatten()
+            .map(|(n, r)| r.as_str().and_then(packages::npm_alias).map_or(n.as_str(), |(target, _)| target));
+        for name in declared {
+            for version in installed.get(name).into_iter().flatten() {
+                self.resolved.insert((name.to_string(), version.clone()));
+            }
+        }
+    }
+
+    /// Whether the member pulls in `name` at one of `versions`.
+    pub fn uses<'a>(&self, name: &str, mut versions: impl Iterator<Item = &'a String>) -> bool {
+        versions.any(|v| self.resolved.contains(&(name.to_string(), v.clone())))
+    }
+}
+
+/// Member globs of the `workspaces` field of a package.json: either a list or,
+/// in yarn's older form, an object with a `packages` list.
+pub fn patterns(pkg_json: &Value) -> Vec<String> {
+    let Some(workspaces) = pkg_json.get("workspaces") else {
+        return Vec::new();
+    };
+    workspaces
+        .get("packages")
+        .unwrap_or(workspaces)
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_str())
+        .map(|p| p.trim_start_matches("./").trim_end_matches('/').to_string())
+        .collect()
+}
+
+/// The closest parent of `dir`, up to the repository root, whose package.json
+/// lists `dir` as one of its workspaces, as an absolute path.
+pub fn root(dir: &Path) -> Option<PathBuf> {
+    find_root(dir, |ancestor| {
+        File::open(ancestor.join("package.json"))
+            .ok()
+            .and_then(|f| serde_json::from_reader::<_, Value>(f).ok())
+            .map(|manifest| patterns(&manifest))
+            .unwrap_or_default()
+    })
+}
+
+/// The closest parent of `dir`, up to the repository root, whose
+/// pnpm-workspace.yaml lists `dir` in its `packages`, as an absolute path.
+pub fn pnpm_root(dir: &Path) -> Option<PathBuf> {
+    find_root(dir, |ancestor| {
+        fs::read_to_string(ancestor.join("pnpm-workspace.yaml"))
+            .ok()
+            .and_then(|content| serde_yaml::from_str::<Value>(&content).ok())
+            .map(|workspace| {
+                workspace
+                    .get("packages")
+                    .and_then(|p| p.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|p| p.as_str())
+                    .map(|p| p.trim_start_matches("./").trim_end_matches('/').to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    })
+}
+
+/// The closest parent of `dir`, up to the repository root, whose lerna.json
+/// lists `dir` in its `packages` (`packages/*` when not given), as an
+/// absolute path.
+pub fn lerna_root(dir: &Path) -> Option<PathBuf> {
+    find_root(dir, |ancestor| {
+        let Some(lerna) = read_lerna(ancestor) else {
+            return Vec::new();
+        };
+        match lerna.get("packages").and_then(|p| p.as_array()) {
+            Some(packages) => packages
+                .iter()
+                .filter_map(|p| p.as_str())
+                .map(|p| p.trim_start_matches("./").trim_end_matches('/').to_string())
+                .collect(),
+            None => vec!["packages/*".to_string()],
+        }
+    })
+}
+
+/// Whether the Lerna project at `root` bootstraps with `hoist`, which
+/// installs the dependencies of its packages in the root `node_modules`.
+pub fn lerna_hoists(root: &Path) -> bool {
+    let Some(lerna) = read_lerna(root) else {
+        return false;
+    };
+    // `true`, or globs of the packages to hoist
+    match lerna.pointer("/command/bootstrap/hoist").or_else(|| lerna.get("hoist")) {
+        Some(Value::Bool(hoist)) => *hoist,
+        Some(Value::String(_)) => true,
+        Some(Value::Array(globs)) => !globs.is_empty(),
+        _ => false,
+    }
+}
+
+fn read_lerna(dir: &Path) -> Option<Value> {
+    File::open(dir.join("lerna.json")).ok().and_then(|f| serde_json::from_reader(f).ok())
+}
+
+/// The closest parent of `dir` whose member globs, as read by `patterns_of`,
+/// match `dir`. The search stops at the repository root.
+fn find_root(dir: &Path, patterns_of: impl Fn(&Path) -> Vec<String>) -> Option<PathBuf> {
+    // `.` has no parents to look at
+    let dir = &dir.canonicalize().ok()?;
+    for ancestor in dir.ancestors().skip(1) {
+        if let Ok(member) = dir.strip_prefix(ancestor)
+            && is_member(&patterns_of(ancestor), member)
+        {
+            return Some(ancestor.to_path_buf());
+        }
+        if ancestor.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
+/// Whether the relative path `member` matches the workspace globs, honouring
+/// `!` exclusions.
+fn is_member(patterns: &[String], member: &Path) -> bool {
+    let mut include = GlobSetBuilder::new();
+    let mut exclude = GlobSetBuilder::new();
+    for pattern in patterns {
+        let (builder, pattern) = match pattern.strip_prefix('!') {
+            Some(negated) => (&mut exclude, negated),
+            None => (&mut include, pattern.as_str()),
+        };
+        if let Ok(glob) = GlobBuilder::new(pattern).literal_separator(true).build() {
+            builder.add(glob);
+        }
+    }
+    match (include.build(), exclude.build()) {
+        (Ok(include), Ok(exclude)) => include.is_match(member) && !exclude.is_match(member),
+        _ => false,
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::registry::is_from;
+use crate::semver::release;
+
+/// One resolved package in a yarn.lock, classic (v1) or Berry (v2+).
+pub struct Entry {
+    /// Descriptors resolving to this entry as (name, range), e.g.
+    /// (`@babel/core`, `npm:^7.0.0`) or (`lodash`, `^4.17.0`)
+    pub descriptors: Vec<(String, String)>,
+    pub version: String,
+    /// Berry `resolution`, e.g. `@babel/core@npm:7.22.5`
+    pub resolution: Option<String>,
+    /// Classic `resolved` tarball URL, which tells the registry it came from
+    pub resolved: Option<String>,
+    /// Classic `integrity` hash of the tarball, e.g. `sha512-…`
+    pub integrity: Option<String>,
+    /// `dependencies` and `optionalDependencies` as (name, range)
+    pub dependencies: Vec<(String, String)>,
+}
+
+impl Entry {
+    /// Package names the entry is known under: the descriptor names and, for
+    /// npm aliases (`alias@npm:real@^1.0.0`) and patches, the name of the
+    /// package that was actually resolved.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.descriptors.iter().map(|(n, _)| n.as_str()).collect();
+        if let Some(resolution) = &self.resolution {
+            names.push(split_descriptor(resolution).0);
+        }
+        for (_, range) in &self.descriptors {
+            if let Some(target) = range.strip_prefix("npm:").filter(|t| t.contains('@')) {
+                names.push(split_descriptor(target).0);
+            }
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Name of the package that was actually resolved: the target of an npm
+    /// alias or the Berry resolution, else the descriptor name.
+    pub fn resolved_name(&self) -> &str {
+        if let Some(resolution) = &self.resolution {
+            return split_descriptor(resolution).0;
+        }
+        self.descriptors
+            .iter()
+            .find_map(|(_, r)| r.strip_prefix("npm:").filter(|t| t.contains('@')).map(|t| split_descriptor(t).0))
+            .or_else(|| self.descriptors.first().map(|(n, _)| n.as_str()))
+            .unwrap_or("")
+    }
+
+    /// Workspace packages are the project itself, not an installed dependency.
+    pub fn is_workspace(&self) -> bool {
+        self.resolution.as_deref().is_some_and(|r| r.contains("@workspace:"))
+            || self.descriptors.iter().any(|(_, r)| r.starts_with("workspace:"))
+    }
+}
+
+/// A parsed yarn.lock.
+pub struct Lockfile {
+    pub entries: Vec<Entry>,
+}
+
+impl Lockfile {
+    /// Versions of `name` resolved in the lockfile, reduced to
+    /// `major.minor.patch`. With `registry` set, only entries downloaded from
+    /// that host are considered.
+    pub fn versions<'a>(&'a self, name: &'a str, registry: Option<&'a str>) -> impl Iterator<Item = &'a str> {
+        self.entries
+            .iter()
+            .filter(move |e| !e.is_workspace() && e.names().contains(&name))
+            .filter(move |e| registry.is_none_or(|host| e.resolved.as_deref().is_some_and(|r| is_from(r, host))))
+            .filter_map(|e| release(&e.version))
+    }
+
+    /// The declared ranges that resolve `name` to `version`, as `name@range`,
+    /// e.g. `debug@^4.3.0` for a flagged `debug@4.4.2`.
+    pub fn requested_ranges(&self, name: &str, version: &str) -> Vec<String> {
+        let mut ranges: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|e| !e.is_workspace() && e.names().contains(&name) && release(&e.version) == Some(version))
+            .flat_map(|e| e.descriptors.iter().map(|(n, r)| format!("{}@{}", n, r)))
+            .collect();
+        ranges.sort();
+        ranges.dedup();
+        ranges
+    }
+
+    /// Packages, as (name, version), that the declared `(name, range)`
+    /// dependencies resolve to, directly or transitively.
+    pub fn reachable(&self, roots: &[(String, String)]) -> HashSet<(String, String)> {
+        let by_descriptor: HashMap<(&str, &str), usize> = self
+            .entries
+            .iter()
+            .enumerate()
+            .flat_map(|(i, e)| e.descriptors.iter().map(move |(n, r)| ((n.as_str(), r.as_str()), i)))
+            .collect();
+        // Berry descriptors carry the `npm:` protocol that package.json omits
+        let lookup = |name: &str, range: &str| {
+            let npm = format!("npm:{}", range);
+            by_descriptor.get(&(name, range)).or_else(|| by_descriptor.get(&(name, npm.as_str()))).copied()
+        };
+        let mut reached: HashSet<(String, String)> = HashSet::new();
+        let mut seen: HashSet<usize> = HashSet::new();
+        let mut queue: Vec<usize> = roots.iter().filter_map(|(n, r)| lookup(n, r)).collect();
+        while let Some(i) = queue.pop() {
+            if !seen.insert(i) {
+                continue;
+            }
+            let entry = &self.entries[i];
+            if let Some(version) = release(&entry.version) {
+                reached.insert((entry.resolved_name().to_string(), version.to_string()));
+            }
+            queue.extend(entry.dependencies.iter().filter_map(|(n, r)| lookup(n, r)));
+        }
+        reached
+    }
+}
+
+/// Parse a classic or Berry yarn.lock. Records start with an unindented
+/// header listing their descriptors and hold indented `version` and
+/// `resolution` fields, written `version "1.2.3"` in v1 and `version: 1.2.3`
+/// in Berry, and nested `dependencies` maps. The Berry `__metadata` record is
+/// skipped.
+pub fn parse(content: &str) -> Lockfile {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut current: Option<Entry> = None;
+    let mut in_dependencies = false;
+    for line in content.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            entries.extend(current.take().filter(|e| !e.version.is_empty()));
+            let header = line.trim_end().trim_end_matches(':');
+            if header == "__metadata" {
+                continue;
+            }
+            let descriptors = header
+                .split(", ")
+                .map(|d| split_descriptor(d.trim().trim_matches('"')))
+                .map(|(n, r)| (n.to_string(), r.to_string()))
+                .collect();
+            current = Some(Entry {
+                descriptors,
+                version: String::new(),
+                resolution: None,
+                resolved: None,
+                integrity: None,
+                dependencies: Vec::new(),
+            });
+            in_dependencies = false;
+            continue;
+        }
+        let Some(entry) = current.as_mut() else {
+            continue;
+        };
+        let indent = line.len() - line.trim_start().len();
+        if indent > 2 {
+            // Nested maps only matter for the dependencies of the record
+            if in_dependencies {
+                let field = line.trim();
+                let (name, range) = match field.split_once(": ") {
+                    Some((n, r)) => (n, r),
+                    None => field.split_once(' ').unwrap_or((field, "")),
+                };
+                entry
+                    .dependencies
+                    .push((name.trim_matches('"').to_string(), range.trim().trim_matches('"').to_string()));
+            }
+            continue;
+        }
+        let field = line.trim();
+        in_dependencies = matches!(field, "dependencies:" | "optionalDependencies:");
+        let (key, value) = match field.split_once(": ") {
+            Some((k, v)) => (k, v),
+            None => field.split_once(' ').unwrap_or((field, "")),
+        };
+        let value = value.trim().trim_matches('"');
+        match key.trim_end_matches(':') {
+            "version" => entry.version = value.to_string(),
+            "resolution" => entry.resolution = Some(value.to_string()),
+            "resolved" => entry.resolved = Some(value.to_string()),
+            "integrity" => entry.integrity = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    entries.extend(current.filter(|e| !e.version.is_empty()));
+    Lockfile { entries }
+}
+
+/// Split a descriptor at the `@` that ends the package name, so
+/// `@scope/pkg@npm:^1.0.0` gives (`@scope/pkg`, `npm:^1.0.0`) and
+/// `left-pad@patch:left-pad@npm%3A1.3.0#…` gives (`left-pad`, `patch:…`).
+fn split_descriptor(descriptor: &str) -> (&str, &str) {
+    let from = usize::from(descriptor.starts_with('@'));
+    match descriptor[from..].find('@') {
+        Some(i) => (&descriptor[..from + i], &descriptor[from + i + 1..]),
+        None => (descriptor, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLASSIC: &str = r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@babel/code-frame@^7.0.0", "@babel/code-frame@^7.22.5":
+  version "7.22.13"
+  resolved "https://registry.yarnpkg.com/@babel/code-frame/-/code-frame-7.22.13.tgz#abc"
+  integrity sha512-XktuhWlJ5g+3TJXc5upd9Ks1HutSArik6jf2eAjYFyIOf4ej3RN+184cZbzDvbPnuTJIUhPKKJE3cIsYTiAT3w==
+  dependencies:
+    "@babel/highlight" "^7.22.13"
+    chalk "^2.4.2"
+
+debug@^4.3.0, debug@~4.4.0:
+  version "4.4.2"
+  resolved "https://registry.yarnpkg.com/debug/-/debug-4.4.2.tgz#def"
+  dependencies:
+    ms "^2.1.3"
+
+ms@^2.1.3:
+  version "2.1.3"
+  resolved "https://npm.example.com/ms/-/ms-2.1.3.tgz"
+
+my-debug@npm:debug@4.3.4:
+  version "4.3.4"
+  resolved "https://registry.yarnpkg.com/debug/-/debug-4.3.4.tgz"
+"#;
+
+    const BERRY: &str = r#"# This file is generated by running "yarn install" inside your project.
+
+__metadata:
+  version: 6
+  cacheKey: 8
+
+"app@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "app@workspace:."
+  dependencies:
+    debug: "npm:^4.3.0"
+    left-pad: "patch:left-pad@npm%3A1.3.0#./left-pad.patch::locator=app%40workspace%3A."
+  languageName: unknown
+  linkType: soft
+
+"debug@npm:^4.3.0, debug@npm:^4.3.4":
+  version: 4.4.0
+  resolution: "debug@npm:4.4.0"
+  dependencies:
+    ms: "npm:^2.1.3"
+  checksum: 10c0/abc
+  languageName: node
+  linkType: hard
+
+"ms@npm:^2.1.3":
+  version: 2.1.3
+  resolution: "ms@npm:2.1.3"
+  languageName: node
+  linkType: hard
+
+"left-pad@patch:left-pad@npm%3A1.3.0#./left-pad.patch::locator=app%40workspace%3A.":
+  version: 1.3.0
+  resolution: "left-pad@patch:left-pad@npm%3A1.3.0#./left-pad.patch::version=1.3.0&hash=abc"
+  languageName: node
+  linkType: hard
+
+"strip@npm:real-strip@^2.0.0":
+  version: 2.1.0
+  resolution: "real-strip@npm:2.1.0"
+  languageName: node
+  linkType: hard
+"#;
+
+    fn versions(lock: &Lockfile, name: &str) -> Vec<String> {
+        lock.versions(name, None).map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn classic_entries() {
+        let lock = parse(CLASSIC);
+        assert_eq!(lock.entries.len(), 4);
+        let frame = &lock.entries[0];
+        assert_eq!(frame.descriptors, vec![
+            ("@babel/code-frame".to_string(), "^7.0.0".to_string()),
+            ("@babel/code-frame".to_string(), "^7.22.5".to_string())
+        ]);
+        assert_eq!(frame.version, "7.22.13");
+        assert!(frame.integrity.as_deref().is_some_and(|i| i.starts_with("sha512-")));
+        assert_eq!(frame.dependencies, vec![
+            ("@babel/highlight".to_string(), "^7.22.13".to_string()),
+            ("chalk".to_string(), "^2.4.2".to_string())
+        ]);
+        assert_eq!(versions(&lock, "ms"), vec!["2.1.3"]);
+        assert_eq!(lock.requested_ranges("debug", "4.4.2"), vec!["debug@^4.3.0", "debug@~4.4.0"]);
+    }
+
+    #[test]
+    fn classic_aliases_and_registries() {
+        let lock = parse(CLASSIC);
+        // `my-debug@npm:debug@4.3.4` installs debug
+        assert_eq!(versions(&lock, "debug"), vec!["4.4.2", "4.3.4"]);
+        assert_eq!(versions(&lock, "my-debug"), vec!["4.3.4"]);
+        assert_eq!(lock.entries[3].resolved_name(), "debug");
+        let from_mirror: Vec<&str> = lock.versions("ms", Some("npm.example.com")).collect();
+        assert_eq!(from_mirror, vec!["2.1.3"]);
+        assert_eq!(lock.versions("debug", Some("npm.example.com")).count(), 0);
+    }
+
+    #[test]
+    fn berry_entries() {
+        let lock = parse(BERRY);
+        // __metadata is not a package
+        assert_eq!(lock.entries.len(), 5);
+        assert_eq!(versions(&lock, "debug"), vec!["4.4.0"]);
+        let debug = &lock.entries[1];
+        assert_eq!(debug.descriptors[0], ("debug".to_string(), "npm:^4.3.0".to_string()));
+        assert_eq!(debug.resolution.as_deref(), Some("debug@npm:4.4.0"));
+        assert_eq!(debug.dependencies, vec![("ms".to_string(), "npm:^2.1.3".to_string())]);
+    }
+
+    #[test]
+    fn berry_workspaces_patches_and_aliases() {
+        let lock = parse(BERRY);
+        assert!(lock.entries[0].is_workspace());
+        assert!(versions(&lock, "app").is_empty());
+        assert_eq!(lock.entries[3].resolved_name(), "left-pad");
+        assert_eq!(versions(&lock, "left-pad"), vec!["1.3.0"]);
+        assert_eq!(versions(&lock, "real-strip"), vec!["2.1.0"]);
+        assert_eq!(lock.entries[4].resolved_name(), "real-strip");
+    }
+
+    #[test]
+    fn reachable_follows_dependencies() {
+        let lock = parse(BERRY);
+        let reached = lock.reachable(&[("debug".to_string(), "^4.3.0".to_string())]);
+        let mut reached: Vec<(String, String)> = reached.into_iter().collect();
+        reached.sort();
+        assert_eq!(reached, vec![("debug".to_string(), "4.4.0".to_string()), ("ms".to_string(), "2.1.3".to_string())]);
+    }
+
+    #[test]
+    fn splits_descriptors() {
+        assert_eq!(split_descriptor("@scope/pkg@npm:^1.0.0"), ("@scope/pkg", "npm:^1.0.0"));
+        assert_eq!(split_descriptor("left-pad@patch:left-pad@npm%3A1.3.0"), ("left-pad", "patch:left-pad@npm%3A1.3.0"));
+        assert_eq!(split_descriptor("lodash"), ("lodash", ""));
+    }
+}